@@ -0,0 +1,24 @@
+use mygui::{Application, Button, Center, Window};
+
+fn main() {
+    Application::new()
+        .name("mygui button example")
+        .window(
+            Window::new()
+                .centered()
+                .title("Button example")
+                .ui(|world| {
+                    Center::new()
+                        .child(
+                            Button::new("Click me".to_string())
+                                .size(110, 30)
+                                .on_click(|_button, _world| println!("button clicked"))
+                                .build(world)
+                        )
+                        .build(world)
+                })
+                .build(),
+        )
+        .build()
+        .run();
+}
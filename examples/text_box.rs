@@ -0,0 +1,24 @@
+use mygui::{Application, Center, TextBox, Window};
+
+fn main() {
+    Application::new()
+        .name("mygui text box example")
+        .window(
+            Window::new()
+                .centered()
+                .title("TextBox example")
+                .ui(|world| {
+                    Center::new()
+                        .child(
+                            TextBox::new()
+                                .text("edit me".to_string())
+                                .size(200, 24)
+                                .build(world)
+                        )
+                        .build(world)
+                })
+                .build(),
+        )
+        .build()
+        .run();
+}
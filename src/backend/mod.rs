@@ -0,0 +1,19 @@
+mod offscreen;
+mod window;
+
+pub use self::offscreen::*;
+pub use self::window::*;
+
+use tiny_skia::Pixmap;
+
+/// A surface the RenderingSystem draws its frames onto.
+pub trait RenderBackend {
+    /// Hands a finished frame over to the backend to be displayed or stored.
+    fn present(&mut self, frame: &mut Pixmap);
+}
+
+impl<B: RenderBackend + ?Sized> RenderBackend for &mut B {
+    fn present(&mut self, frame: &mut Pixmap) {
+        (**self).present(frame)
+    }
+}
@@ -0,0 +1,31 @@
+use tiny_skia::Pixmap;
+use crate::RenderBackend;
+
+/// Keeps the last rendered frame in memory instead of displaying it.
+/// Does not need a display server, so it can be used on build machines and in tests.
+#[derive(Default)]
+pub struct OffscreenBackend {
+    frame: Option<Pixmap>,
+}
+
+impl OffscreenBackend {
+    pub fn new() -> OffscreenBackend {
+        OffscreenBackend { frame: None }
+    }
+
+    /// Returns the last rendered frame, if any frame has been rendered yet.
+    pub fn frame(&self) -> Option<&Pixmap> {
+        self.frame.as_ref()
+    }
+
+    /// Takes the last rendered frame out of the backend.
+    pub fn take_frame(&mut self) -> Option<Pixmap> {
+        self.frame.take()
+    }
+}
+
+impl RenderBackend for OffscreenBackend {
+    fn present(&mut self, frame: &mut Pixmap) {
+        self.frame = Some(frame.clone());
+    }
+}
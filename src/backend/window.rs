@@ -0,0 +1,37 @@
+use std::{cell::RefCell, rc::Rc};
+use orbclient::Renderer;
+use tiny_skia::Pixmap;
+use crate::{RenderBackend, Window};
+
+/// Displays the frames in an orbclient window.
+pub struct WindowBackend {
+    window: Rc<RefCell<Window>>,
+}
+
+impl WindowBackend {
+    pub fn new(window: Rc<RefCell<Window>>) -> WindowBackend {
+        WindowBackend { window }
+    }
+}
+
+impl RenderBackend for WindowBackend {
+    fn present(&mut self, frame: &mut Pixmap) {
+        // frame buffer flipping code is borrowed from orbtk
+        // https://github.com/redox-os/orbtk/blob/develop/orbtk_orbclient/src/orbclient/window.rs
+        let bytes = frame.data_mut();
+        let len = bytes.len() / std::mem::size_of::<orbclient::Color>();
+        let color_data = unsafe {
+            std::slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut orbclient::Color, len)
+        };
+
+        if color_data.len() == self.window.borrow().inner().data().len() {
+            self.window
+                .borrow_mut()
+                .inner_mut()
+                .data_mut()
+                .clone_from_slice(color_data);
+        }
+
+        self.window.borrow_mut().inner_mut().sync();
+    }
+}
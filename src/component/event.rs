@@ -0,0 +1,46 @@
+use std::rc::Rc;
+use specs::{Component, Entity, VecStorage, World};
+use crate::{EventPhase, EventResult, WidgetEvent};
+
+pub type EventHandler = dyn Fn(Entity, &WidgetEvent, &World) -> EventResult;
+
+/// Holds the event handlers of a widget.
+/// Capture handlers are called while the event travels down to its target,
+/// bubble handlers are called on its way back up to the root.
+#[derive(Clone, Default)]
+pub struct EventHandlerComponent {
+    capture: Vec<Rc<EventHandler>>,
+    bubble: Vec<Rc<EventHandler>>,
+}
+
+impl EventHandlerComponent {
+    pub fn new() -> EventHandlerComponent {
+        EventHandlerComponent {
+            capture: Vec::new(),
+            bubble: Vec::new(),
+        }
+    }
+
+    /// Registers a handler called in the capture phase.
+    pub fn on_capture<F: 'static + Fn(Entity, &WidgetEvent, &World) -> EventResult>(mut self, handler: F) -> Self {
+        self.capture.push(Rc::new(handler));
+        self
+    }
+
+    /// Registers a handler called in the bubble phase.
+    pub fn on_bubble<F: 'static + Fn(Entity, &WidgetEvent, &World) -> EventResult>(mut self, handler: F) -> Self {
+        self.bubble.push(Rc::new(handler));
+        self
+    }
+
+    pub fn handlers(&self, phase: EventPhase) -> &[Rc<EventHandler>] {
+        match phase {
+            EventPhase::Capture => &self.capture,
+            EventPhase::Bubble => &self.bubble,
+        }
+    }
+}
+
+impl Component for EventHandlerComponent {
+    type Storage = VecStorage<Self>;
+}
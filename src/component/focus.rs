@@ -0,0 +1,24 @@
+use specs::{Component, VecStorage};
+
+/// Makes a widget able to receive the keyboard focus.
+#[derive(Copy, Clone, Debug)]
+pub struct FocusComponent {
+    pub focusable: bool,
+    /// The position of the widget in the Tab traversal order.
+    /// Widgets with a positive index come first in ascending order, then the widgets with a zero index
+    /// in the order they appear in the EntityTree. Widgets with a negative index can be focused by clicking only.
+    pub tab_index: i32,
+}
+
+impl Default for FocusComponent {
+    fn default() -> Self {
+        FocusComponent {
+            focusable: true,
+            tab_index: 0,
+        }
+    }
+}
+
+impl Component for FocusComponent {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,141 @@
+use std::{fs, io::Cursor, path::Path, sync::Arc};
+use jpeg_decoder::{Decoder, PixelFormat};
+use specs::{Component, FlaggedStorage, VecStorage};
+use tiny_skia::{ColorU8, Pixmap};
+
+/// A bitmap drawn within the bounds of its widget.
+#[derive(Clone)]
+pub struct ImageComponent {
+    /// The pixels of the image. They are shared, so the same image can be shown by many widgets.
+    pub image: Arc<Pixmap>,
+    pub fit: ImageFit,
+    pub sampling: ImageSampling,
+}
+
+impl Component for ImageComponent {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
+
+/// How an image is fitted into the bounds of its widget.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ImageFit {
+    /// Scales the image to the bounds, regardless of its aspect ratio.
+    Stretch,
+    /// Scales the image to the largest size fitting in the bounds, keeping its aspect ratio, and centers it.
+    #[default]
+    Contain,
+    /// Scales the image to the smallest size covering the bounds, keeping its aspect ratio, and centers it.
+    /// The parts outside of the bounds are cut off.
+    Cover,
+    /// Centers the image in its own size.
+    None,
+    /// Repeats the image in its own size from the top left corner of the bounds.
+    Tile,
+}
+
+/// How the pixels of a scaled image are computed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ImageSampling {
+    /// Interpolates between the pixels of the image, for photos and illustrations.
+    #[default]
+    Smooth,
+    /// Takes the nearest pixel of the image, for pixel art and crisp icons.
+    Nearest,
+}
+
+/// Decodes a PNG or a JPEG image. The format is recognized from the data.
+pub fn decode_image(data: &[u8]) -> Result<Pixmap, String> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Pixmap::decode_png(data).map_err(|err| format!("invalid PNG image: {}", err))
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        decode_jpeg(data)
+    } else {
+        Err("unsupported image format, only PNG and JPEG images are supported".to_string())
+    }
+}
+
+/// Reads and decodes a PNG or a JPEG image file.
+pub fn load_image<P: AsRef<Path>>(path: P) -> Result<Pixmap, String> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    decode_image(&data).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+fn decode_jpeg(data: &[u8]) -> Result<Pixmap, String> {
+    let mut decoder = Decoder::new(Cursor::new(data));
+    let pixels = decoder.decode().map_err(|err| format!("invalid JPEG image: {}", err))?;
+    let info = decoder.info().ok_or_else(|| "invalid JPEG image: missing header".to_string())?;
+    let mut pixmap = Pixmap::new(info.width as u32, info.height as u32)
+        .ok_or_else(|| "invalid JPEG image: empty image".to_string())?;
+
+    let color = |pixel: &[u8]| match info.pixel_format {
+        PixelFormat::L8 => ColorU8::from_rgba(pixel[0], pixel[0], pixel[0], 255),
+        PixelFormat::RGB24 => ColorU8::from_rgba(pixel[0], pixel[1], pixel[2], 255),
+        PixelFormat::CMYK32 => {
+            let key = 255 - pixel[3] as u16;
+            let channel = |ink: u8| ((255 - ink as u16) * key / 255) as u8;
+            ColorU8::from_rgba(channel(pixel[0]), channel(pixel[1]), channel(pixel[2]), 255)
+        }
+    };
+    let stride = info.pixel_format.pixel_bytes();
+    for (target, source) in pixmap.pixels_mut().iter_mut().zip(pixels.chunks_exact(stride)) {
+        *target = color(source).premultiply();
+    }
+    Ok(pixmap)
+}
+
+#[cfg(test)]
+mod test {
+    use tiny_skia::{ColorU8, Pixmap};
+    use crate::{decode_image, load_image};
+
+    /// A baseline JPEG of 8x8 gray pixels of the value 192: a single block with no AC coefficients,
+    /// encoded with single-code Huffman tables.
+    const GRAY_JPEG: &[u8] = &[
+        0xFF, 0xD8,
+        // the quantization table, the DC coefficient is divided by 8
+        0xFF, 0xDB, 0x00, 0x43, 0x00, 8, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1,
+        // a single grayscale component of 8x8 pixels
+        0xFF, 0xC0, 0x00, 0x0B, 8, 0x00, 0x08, 0x00, 0x08, 1, 1, 0x11, 0,
+        // the DC table codes the magnitude category 7, the AC table the end of block, both with the code "0"
+        0xFF, 0xC4, 0x00, 0x14, 0x00, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7,
+        0xFF, 0xC4, 0x00, 0x14, 0x10, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0xFF, 0xDA, 0x00, 0x08, 1, 1, 0x00, 0, 63, 0,
+        // DC category 7 ("0"), the value 64 ("1000000"), end of block ("0"), padded with ones
+        0x40, 0x7F,
+        0xFF, 0xD9,
+    ];
+
+    /// Returns a 2x1 image with a red and a blue pixel.
+    fn red_blue() -> Pixmap {
+        let mut pixmap = Pixmap::new(2, 1).unwrap();
+        pixmap.pixels_mut()[0] = ColorU8::from_rgba(255, 0, 0, 255).premultiply();
+        pixmap.pixels_mut()[1] = ColorU8::from_rgba(0, 0, 255, 128).premultiply();
+        pixmap
+    }
+
+    #[test]
+    fn png_images_are_decoded() {
+        let image = red_blue();
+        let decoded = decode_image(&image.encode_png().unwrap()).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2, 1));
+        assert_eq!(decoded.pixels(), image.pixels());
+    }
+
+    #[test]
+    fn jpeg_images_are_decoded() {
+        let decoded = decode_image(GRAY_JPEG).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (8, 8));
+        assert!(decoded.pixels().iter().all(|pixel| *pixel == ColorU8::from_rgba(192, 192, 192, 255).premultiply()));
+    }
+
+    #[test]
+    fn broken_and_unknown_images_are_rejected() {
+        assert!(decode_image(b"GIF89a").unwrap_err().contains("unsupported"));
+        assert!(decode_image(b"\x89PNG\r\n\x1a\nbroken").unwrap_err().contains("PNG"));
+        assert!(decode_image(&GRAY_JPEG[..40]).unwrap_err().contains("JPEG"));
+        assert!(load_image("no/such/image.png").unwrap_err().contains("no/such/image.png"));
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use specs::{Component, Entities, Entity, ReadStorage, ReaderId, Tracked, World, WorldExt, storage::ComponentEvent};
+use crate::{Constraints, DesiredSize, EntityTree, LayoutComponent, MarginComponent, PaddingComponent, PreferredSizeComponent, TextComponent, register_reader};
+
+/// The measurements kept per widget. Layouts measure a child with a few different constraints,
+/// e.g. a grid measures it in the space available to the grid first, then in its cell.
+const MEASUREMENTS_PER_WIDGET: usize = 4;
+
+/// A resource keeping the results of the layout of the widgets between frames.
+///
+/// A widget having a layout is measured again only when it gets constraints it has not been measured with,
+/// and arranged again only when it gets another size. When the TextComponent, the PreferredSizeComponent,
+/// the PaddingComponent or the MarginComponent of a widget changes, or the widget gets a new child,
+/// the results of the widget are dropped, and so are the results of its ancestors
+/// up to the first one whose size does not depend on its children.
+///
+/// Changes the cache cannot see, e.g. a font registered under the name of a font in use, need a call to invalidate().
+pub struct LayoutCache {
+    entries: HashMap<Entity, CacheEntry>,
+    // widgets to invalidate at the next layout
+    invalid: Vec<Entity>,
+    // some widget has been arranged since the layout system last asked
+    arranged: bool,
+    readers: Readers,
+}
+
+#[derive(Default)]
+struct CacheEntry {
+    measurements: Vec<(Constraints, DesiredSize)>,
+    arranged: Option<(u32, u32)>,
+}
+
+struct Readers {
+    texts: ReaderId<ComponentEvent>,
+    preferred_sizes: ReaderId<ComponentEvent>,
+    paddings: ReaderId<ComponentEvent>,
+    margins: ReaderId<ComponentEvent>,
+}
+
+impl LayoutCache {
+    /// Starts tracking the changes of the components of the world. The first layout runs in full.
+    pub fn new(world: &mut World) -> LayoutCache {
+        let readers = Readers {
+            texts: register_reader::<TextComponent>(world),
+            preferred_sizes: register_reader::<PreferredSizeComponent>(world),
+            paddings: register_reader::<PaddingComponent>(world),
+            margins: register_reader::<MarginComponent>(world),
+        };
+
+        LayoutCache {
+            entries: HashMap::new(),
+            invalid: Vec::new(),
+            arranged: false,
+            readers,
+        }
+    }
+
+    /// Makes the next frame lay out the whole tree again.
+    pub fn invalidate(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Makes the next frame measure and arrange the widget and the ancestors depending on its size again.
+    pub fn invalidate_widget(&mut self, widget: Entity) {
+        self.invalid.push(widget);
+    }
+
+    /// Returns the size the widget desired last time it was measured with the given constraints.
+    pub(crate) fn measurement(&self, widget: Entity, constraints: &Constraints) -> Option<DesiredSize> {
+        let entry = self.entries.get(&widget)?;
+        entry.measurements.iter()
+            .find(|(measured, _)| measured == constraints)
+            .map(|(_, size)| *size)
+    }
+
+    pub(crate) fn store_measurement(&mut self, widget: Entity, constraints: &Constraints, size: DesiredSize) {
+        let measurements = &mut self.entries.entry(widget).or_default().measurements;
+        if measurements.len() == MEASUREMENTS_PER_WIDGET {
+            measurements.remove(0);
+        }
+        measurements.push((*constraints, size));
+    }
+
+    /// Tells whether the children of the widget are still arranged for the given size.
+    pub(crate) fn is_arranged(&self, widget: Entity, size: &DesiredSize) -> bool {
+        match self.entries.get(&widget) {
+            Some(entry) => entry.arranged == Some((size.width, size.height)),
+            None => false,
+        }
+    }
+
+    pub(crate) fn store_arranged(&mut self, widget: Entity, size: &DesiredSize) {
+        self.entries.entry(widget).or_default().arranged = Some((size.width, size.height));
+        self.arranged = true;
+    }
+
+    /// Tells whether any widget has been arranged since the last call.
+    pub(crate) fn take_arranged(&mut self) -> bool {
+        std::mem::replace(&mut self.arranged, false)
+    }
+
+    /// Drops the results invalidated by the changes of the world since the last layout.
+    pub(crate) fn update(&mut self, world: &World) {
+        let entities = world.entities();
+        read_events(&world.read_storage::<TextComponent>(), &mut self.readers.texts, &entities, &mut self.invalid);
+        read_events(&world.read_storage::<PaddingComponent>(), &mut self.readers.paddings, &entities, &mut self.invalid);
+        read_events(&world.read_storage::<MarginComponent>(), &mut self.readers.margins, &entities, &mut self.invalid);
+        {
+            // layouts insert the preferred sizes, only the changes made by the application count
+            let preferred_sizes = world.read_storage::<PreferredSizeComponent>();
+            for event in preferred_sizes.channel().read(&mut self.readers.preferred_sizes) {
+                if let ComponentEvent::Modified(id) = event {
+                    self.invalid.push(entities.entity(*id));
+                }
+            }
+        }
+
+        let mut tree = world.write_resource::<EntityTree>();
+        self.invalid.extend(tree.take_changed());
+        if self.invalid.is_empty() {
+            return;
+        }
+
+        let layouts = world.read_storage::<LayoutComponent>();
+        for widget in std::mem::take(&mut self.invalid) {
+            self.entries.remove(&widget);
+            // every ancestor arranges its children again, but the ones sized independently of them keep their measurements
+            let mut measure = true;
+            for ancestor in tree.path_to(widget).into_iter().rev().skip(1) {
+                if let Some(layout) = layouts.get(ancestor) {
+                    measure = measure && layout.object.sized_by_children();
+                }
+                if let Some(entry) = self.entries.get_mut(&ancestor) {
+                    if measure {
+                        entry.measurements.clear();
+                    }
+                    entry.arranged = None;
+                }
+            }
+        }
+        self.entries.retain(|widget, _| entities.is_alive(*widget));
+    }
+}
+
+/// Collects the widgets whose component has been inserted, changed or removed.
+fn read_events<T>(storage: &ReadStorage<T>, reader: &mut ReaderId<ComponentEvent>, entities: &Entities, changed: &mut Vec<Entity>)
+where
+    T: Component,
+    T::Storage: Tracked,
+{
+    for event in storage.channel().read(reader) {
+        match event {
+            ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) | ComponentEvent::Removed(id) => {
+                changed.push(entities.entity(*id));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, rc::Rc};
+    use crate::{BoxItemComponent, BoxLayout, Center, Constraints, DesiredSize, EntityTree, HeadlessShell, Label, Layout, LayoutComponent, MarginComponent, Orientation, PaddingComponent, PositionComponent, PreferredSizeComponent, RelativePositionComponent, SizeComponent, TextComponent, Thickness};
+    use specs::{Builder, Entity, World, WorldExt};
+
+    /// A vertical box counting how many times it is measured and arranged.
+    struct CountingLayout {
+        inner: BoxLayout,
+        measured: Rc<Cell<usize>>,
+        arranged: Rc<Cell<usize>>,
+    }
+
+    impl Layout for CountingLayout {
+        fn arrange(&self, widget: Entity, desired_size: &DesiredSize, world: &World) {
+            self.arranged.set(self.arranged.get() + 1);
+            self.inner.arrange(widget, desired_size, world);
+        }
+
+        fn measure(&self, widget: Entity, constraints: &Constraints, world: &World) -> DesiredSize {
+            self.measured.set(self.measured.get() + 1);
+            self.inner.measure(widget, constraints, world)
+        }
+    }
+
+    struct Counted {
+        shell: HeadlessShell,
+        measured: Rc<Cell<usize>>,
+        arranged: Rc<Cell<usize>>,
+    }
+
+    impl Counted {
+        /// Lays out a centered counting box holding a label and a widget without a layout, then resets the counters.
+        fn new() -> Counted {
+            let (measured, arranged) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+            let (measured_by_ui, arranged_by_ui) = (measured.clone(), arranged.clone());
+            let mut shell = HeadlessShell::new(200, 200, move |world| {
+                world.register::<BoxItemComponent>();
+                world.register::<LayoutComponent>();
+                world.register::<PositionComponent>();
+                world.register::<RelativePositionComponent>();
+                let label = Label::new("text".to_string()).build(world);
+                let plain = world.create_entity()
+                    .with(SizeComponent { width: 30, height: 20 })
+                    .with(PositionComponent::default())
+                    .build();
+                let layout = LayoutComponent {
+                    constraints: Constraints::default(),
+                    object: Box::new(CountingLayout {
+                        inner: BoxLayout { orientation: Orientation::Vertical, spacing: 0 },
+                        measured: measured_by_ui.clone(),
+                        arranged: arranged_by_ui.clone(),
+                    }),
+                };
+                let widget = world.create_entity().with(layout).with(PositionComponent::default()).build();
+
+                {
+                    let mut tree = world.write_resource::<EntityTree>();
+                    tree.add_node(widget);
+                    for child in [label, plain].iter() {
+                        tree.add_node(*child);
+                        tree.append_child(widget, *child);
+                    }
+                }
+                Center::new().child(widget).build(world)
+            });
+            shell.render();
+            let counted = Counted { shell, measured, arranged };
+            counted.reset();
+            counted
+        }
+
+        fn reset(&self) {
+            self.measured.set(0);
+            self.arranged.set(0);
+        }
+
+        fn counting_box(&self) -> Entity {
+            let tree = self.shell.world().read_resource::<EntityTree>();
+            let center = tree.child_of(self.shell.root()).unwrap();
+            tree.child_of(center).unwrap()
+        }
+
+        fn children(&self) -> Vec<Entity> {
+            let widget = self.counting_box();
+            let tree = self.shell.world().read_resource::<EntityTree>();
+            tree.try_children_of(widget).unwrap().filter_map(|node| tree.entitiy_of(node)).collect()
+        }
+
+        /// Renders a frame and tells whether the box has been measured and arranged again.
+        fn laid_out(&mut self) -> bool {
+            self.shell.render();
+            let laid_out = self.measured.get() > 0 && self.arranged.get() > 0;
+            self.reset();
+            laid_out
+        }
+    }
+
+    #[test]
+    fn clean_frame_does_no_layout() {
+        let mut counted = Counted::new();
+        assert!(!counted.laid_out());
+        assert_eq!((counted.measured.get(), counted.arranged.get()), (0, 0));
+    }
+
+    #[test]
+    fn text_change_invalidates_ancestors() {
+        let mut counted = Counted::new();
+        let label = counted.children()[0];
+        counted.shell.world().write_storage::<TextComponent>().get_mut(label).unwrap().text = "longer text".to_string();
+        assert!(counted.laid_out());
+        assert!(!counted.laid_out());
+    }
+
+    #[test]
+    fn preferred_size_change_invalidates_ancestors() {
+        let mut counted = Counted::new();
+        let plain = counted.children()[1];
+        counted.shell.world().write_storage::<PreferredSizeComponent>().get_mut(plain).unwrap().width = 60;
+        assert!(counted.laid_out());
+        assert_eq!(counted.shell.world().read_storage::<SizeComponent>().get(plain).unwrap().width, 60);
+        assert!(!counted.laid_out());
+    }
+
+    #[test]
+    fn padding_change_invalidates_ancestors() {
+        let mut counted = Counted::new();
+        let label = counted.children()[0];
+        let padding = PaddingComponent { padding: Thickness::uniform(4) };
+        counted.shell.world().write_storage::<PaddingComponent>().insert(label, padding).unwrap();
+        assert!(counted.laid_out());
+        assert!(!counted.laid_out());
+    }
+
+    #[test]
+    fn margin_change_invalidates_ancestors() {
+        let mut counted = Counted::new();
+        let plain = counted.children()[1];
+        let margin = MarginComponent { margin: Thickness::uniform(4) };
+        counted.shell.world().write_storage::<MarginComponent>().insert(plain, margin).unwrap();
+        assert!(counted.laid_out());
+        assert_eq!(counted.shell.world().read_storage::<RelativePositionComponent>().get(plain).map(|position| position.x), Some(4));
+        assert!(!counted.laid_out());
+    }
+
+    #[test]
+    fn new_child_invalidates_ancestors() {
+        let mut counted = Counted::new();
+        let child = Label::new("new".to_string()).build(counted.shell.world_mut());
+        {
+            let widget = counted.counting_box();
+            let mut tree = counted.shell.world().write_resource::<EntityTree>();
+            tree.add_node(child);
+            tree.append_child(widget, child);
+        }
+        assert!(counted.laid_out());
+        assert!(counted.shell.world().read_storage::<RelativePositionComponent>().contains(child));
+        assert!(!counted.laid_out());
+    }
+}
@@ -1,15 +1,23 @@
+mod cache;
 mod text;
 mod window;
 
+pub use self::cache::*;
 pub use self::text::*;
 pub use self::window::*;
 
-use specs::{Component, Entity, VecStorage, World};
-use crate::{Constraints};
+use specs::{Component, Entity, VecStorage, World, WorldExt};
+use crate::{Constraints, MarginComponent, PaddingComponent, PreferredSizeComponent, RelativePositionComponent, SizeComponent, Thickness};
 
 pub trait Layout {
     fn arrange(&self, widget: Entity, desired_size: &DesiredSize, world: &World);
     fn measure(&self, entity: Entity, constraints: &Constraints, world: &World) -> DesiredSize;
+
+    /// Tells whether the desired size of the widget depends on the sizes of its children.
+    /// When it does not, changes of the children do not make the widget measured again.
+    fn sized_by_children(&self) -> bool {
+        true
+    }
 }
 
 pub struct LayoutComponent {
@@ -21,9 +29,194 @@ impl Component for LayoutComponent {
     type Storage = VecStorage<Self>;
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct DesiredSize {
-    pub dirty: bool,
     pub width: u32,
     pub height: u32
-}
\ No newline at end of file
+}
+
+/// Measures a widget within the given constraints, the desired size includes the margin of the widget.
+/// Widgets having a layout are asked for the size of their content within their padding,
+/// the others desire their preferred size, or their current size until they have been placed by a layout.
+pub fn measure_widget(widget: Entity, constraints: &Constraints, world: &World) -> DesiredSize {
+    let margin = margin_of(widget, world);
+    let layouts = world.read_storage::<LayoutComponent>();
+    let (width, height) = match layouts.get(widget) {
+        Some(layout) => {
+            if let Some(size) = world.try_fetch::<LayoutCache>().and_then(|cache| cache.measurement(widget, constraints)) {
+                return size;
+            }
+            let padding = padding_of(widget, world);
+            let content = layout.object.measure(widget, &constraints.deflate(&margin).deflate(&padding), world);
+            (content.width.saturating_add(padding.horizontal()), content.height.saturating_add(padding.vertical()))
+        }
+        None => {
+            let preferred = world.read_storage::<PreferredSizeComponent>().get(widget).copied();
+            match preferred {
+                Some(size) => (size.width as u32, size.height as u32),
+                None => world.read_storage::<SizeComponent>().get(widget).map_or((0, 0), |size| (size.width as u32, size.height as u32)),
+            }
+        }
+    };
+
+    let size = DesiredSize {
+        width: width.saturating_add(margin.horizontal()).max(constraints.min_width).min(constraints.max_width),
+        height: height.saturating_add(margin.vertical()).max(constraints.min_height).min(constraints.max_height)
+    };
+    if layouts.contains(widget) {
+        if let Some(mut cache) = world.try_fetch_mut::<LayoutCache>() {
+            cache.store_measurement(widget, constraints, size);
+        }
+    }
+    size
+}
+
+/// Lets a widget arrange its children within the given size less its padding, if the widget has a layout
+/// and its children have not been arranged for that size yet.
+pub fn arrange_widget(widget: Entity, size: &DesiredSize, world: &World) {
+    let layouts = world.read_storage::<LayoutComponent>();
+    if let Some(layout) = layouts.get(widget) {
+        if let Some(true) = world.try_fetch::<LayoutCache>().map(|cache| cache.is_arranged(widget, size)) {
+            return;
+        }
+        let padding = padding_of(widget, world);
+        let content = DesiredSize {
+            width: size.width.saturating_sub(padding.horizontal()),
+            height: size.height.saturating_sub(padding.vertical())
+        };
+        layout.object.arrange(widget, &content, world);
+        if let Some(mut cache) = world.try_fetch_mut::<LayoutCache>() {
+            cache.store_arranged(widget, size);
+        }
+    }
+}
+
+/// Places a child of a layout in the given slot, relative to the top left corner of the area inside the padding
+/// of the layout. The widget gets the slot less its margin, then arranges its own children.
+pub fn place_widget(widget: Entity, x: u32, y: u32, slot: &DesiredSize, world: &World) {
+    let margin = margin_of(widget, world);
+    let position = RelativePositionComponent { x: x.saturating_add(margin.left), y: y.saturating_add(margin.top) };
+    let size = &DesiredSize {
+        width: slot.width.saturating_sub(margin.horizontal()),
+        height: slot.height.saturating_sub(margin.vertical())
+    };
+    world.write_storage::<RelativePositionComponent>()
+        .insert(widget, position)
+        .expect("Cannot place a dead entity!");
+
+    let has_layout = world.read_storage::<LayoutComponent>().contains(widget);
+    {
+        let mut sizes = world.write_storage::<SizeComponent>();
+        // the size the widget was built with is kept before the layout overwrites it
+        if !has_layout {
+            let mut preferred = world.write_storage::<PreferredSizeComponent>();
+            if let (false, Some(current)) = (preferred.contains(widget), sizes.get(widget)) {
+                let current = PreferredSizeComponent { width: current.width, height: current.height };
+                preferred.insert(widget, current).expect("Cannot place a dead entity!");
+            }
+        }
+        // a size written unchanged would still be reported as modified
+        let changed = match sizes.get(widget) {
+            Some(current) => current.width != size.width as usize || current.height != size.height as usize,
+            None => false,
+        };
+        if changed {
+            if let Some(current) = sizes.get_mut(widget) {
+                current.width = size.width as usize;
+                current.height = size.height as usize;
+            }
+        }
+    }
+    arrange_widget(widget, size, world);
+}
+
+pub(crate) fn padding_of(widget: Entity, world: &World) -> Thickness {
+    world.read_storage::<PaddingComponent>().get(widget).map(|padding| padding.padding).unwrap_or_default()
+}
+
+fn margin_of(widget: Entity, world: &World) -> Thickness {
+    world.read_storage::<MarginComponent>().get(widget).map(|margin| margin.margin).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Center, EntityTree, HeadlessShell, Label, PaddingComponent, PositionComponent, SizeComponent, TextComponent, Thickness, VBox, text_area};
+    use specs::{Entity, WorldExt};
+
+    fn centered_label(padding: Thickness, margin: Thickness) -> impl Fn(&mut specs::World) -> Entity {
+        move |world| {
+            let label = Label::new("padded".to_string()).padding(padding).margin(margin).build(world);
+            Center::new().child(label).build(world)
+        }
+    }
+
+    /// Returns the first widget without children below the root.
+    fn leaf(shell: &HeadlessShell) -> Entity {
+        let tree = shell.world().read_resource::<EntityTree>();
+        let mut widget = shell.root();
+        while let Some(child) = tree.child_of(widget) {
+            widget = child;
+        }
+        widget
+    }
+
+    fn bounds_of(shell: &HeadlessShell, widget: Entity) -> (u32, u32, usize, usize) {
+        let positions = shell.world().read_storage::<PositionComponent>();
+        let sizes = shell.world().read_storage::<SizeComponent>();
+        let (position, size) = (positions.get(widget).unwrap(), sizes.get(widget).unwrap());
+        (position.x, position.y, size.width, size.height)
+    }
+
+    #[test]
+    fn padding_grows_the_desired_size_and_margin_moves_the_widget() {
+        let mut plain = HeadlessShell::new(300, 200, centered_label(Thickness::default(), Thickness::default()));
+        plain.render();
+        let (_, _, width, height) = bounds_of(&plain, leaf(&plain));
+
+        let mut padded = HeadlessShell::new(300, 200, centered_label(Thickness::symmetric(6, 3), Thickness::uniform(4)));
+        padded.render();
+        // the margin is left around the widget, but not included in its size
+        let (outer_width, outer_height) = (width as u32 + 12 + 8, height as u32 + 6 + 8);
+        let expected = ((300 - outer_width) / 2 + 4, (200 - outer_height) / 2 + 4, width + 12, height + 6);
+        assert_eq!(bounds_of(&padded, leaf(&padded)), expected);
+    }
+
+    #[test]
+    fn padding_and_margin_larger_than_the_window_leave_no_space() {
+        let mut shell = HeadlessShell::new(20, 10, centered_label(Thickness::uniform(50), Thickness::uniform(30)));
+        shell.render();
+        let label = leaf(&shell);
+        assert_eq!(bounds_of(&shell, label), (30, 30, 0, 0));
+
+        let world = shell.world();
+        let (positions, sizes, paddings) = (world.read_storage::<PositionComponent>(), world.read_storage::<SizeComponent>(), world.read_storage::<PaddingComponent>());
+        let (_, _, width, height) = text_area(positions.get(label).unwrap(), sizes.get(label), paddings.get(label));
+        assert_eq!((width, height), (Some(0.0), Some(0.0)));
+    }
+
+    #[test]
+    fn padding_larger_than_the_box_leaves_no_space_for_the_children() {
+        let mut shell = HeadlessShell::new(50, 40, |world| {
+            let label = Label::new("inside".to_string()).build(world);
+            VBox::new().padding(Thickness::uniform(u32::MAX / 2)).child(label).build(world)
+        });
+        shell.render();
+        let label = leaf(&shell);
+        assert_eq!(bounds_of(&shell, label).2, 0);
+
+        // the text changes, and the box is laid out again within the same space
+        shell.world().write_storage::<TextComponent>().get_mut(label).unwrap().text = "longer text".to_string();
+        shell.render();
+        assert_eq!(bounds_of(&shell, label).2, 0);
+    }
+
+    #[test]
+    fn huge_padding_does_not_overflow_the_initial_size() {
+        let mut shell = HeadlessShell::new(50, 40, |world| {
+            let label = Label::new("huge".to_string()).padding(Thickness::uniform(u32::MAX)).build(world);
+            Center::new().child(label).build(world)
+        });
+        shell.render();
+        assert_eq!(bounds_of(&shell, leaf(&shell)), (0, 0, 50, 40));
+    }
+}
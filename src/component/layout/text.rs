@@ -1,28 +1,32 @@
 use specs::{WorldExt, Entity, World};
-use crate::{Layout, DesiredSize, Constraints, SizeComponent};
+use crate::{Layout, DesiredSize, Constraints, FontRegistry, TextComponent, TextWrap, measure_text};
 
+/// Sizes a widget to the extents of its text, the padding of the widget is added around the text.
+/// Wrapping text is broken into lines at the maximum width of the constraints less the padding.
 pub struct TextLayout {}
 
 impl Layout for TextLayout {
-    fn arrange(&self, _widget: Entity, _desired_size: &DesiredSize, _world: &World) {
-        
-    }
+    // the layout of the parent gives the widget its size, the text is laid out when it is drawn
+    fn arrange(&self, _widget: Entity, _desired_size: &DesiredSize, _world: &World) {}
 
-    fn measure(&self, entity: Entity, _constraints: &Constraints, world: &World) -> DesiredSize {
-        let sizes = world.read_storage::<SizeComponent>();
+    fn measure(&self, entity: Entity, constraints: &Constraints, world: &World) -> DesiredSize {
+        let texts = world.read_storage::<TextComponent>();
 
-        if let Some(size) = sizes.get(entity) {
-            DesiredSize {
-                dirty: false,
-                width: size.width as u32,
-                height: size.height as u32
-            }
-        } else {
-            DesiredSize {
-                dirty: false,
-                width: 0,
-                height: 0
+        let (width, height) = match texts.get(entity) {
+            Some(text) => {
+                let mut fonts = world.write_resource::<FontRegistry>();
+                let max_width = match text.wrap {
+                    TextWrap::NoWrap => None,
+                    _ => Some(constraints.max_width as f32),
+                };
+                measure_text(&mut fonts, text, max_width)
             }
+            None => (0.0, 0.0),
+        };
+
+        DesiredSize {
+            width: (width.ceil() as u32).max(constraints.min_width).min(constraints.max_width),
+            height: (height.ceil() as u32).max(constraints.min_height).min(constraints.max_height)
         }
     }
-}
\ No newline at end of file
+}
@@ -1,52 +1,28 @@
 use specs::{Entity, World, WorldExt};
-use crate::{Layout, Constraints, DesiredSize, EntityTree, LayoutComponent, WindowComponent};
+use crate::{Layout, Constraints, DesiredSize, EntityTree, WindowComponent, place_widget};
 
+/// The layout of the root widget: its only child fills the window.
 pub struct WindowLayout {}
 
 impl Layout for WindowLayout {
     fn arrange(&self, root: Entity, desired_size: &DesiredSize, world: &World) {
-        let tree = world.read_resource::<EntityTree>();
-        let layouts = world.read_storage::<LayoutComponent>();
-
-        if let Some(child) = tree.child_of(root) {
-            if let Some(child_layout) = layouts.get(child) {
-                child_layout.object.arrange(child, desired_size, world)
-            }
+        let child = world.read_resource::<EntityTree>().child_of(root);
+        if let Some(child) = child {
+            place_widget(child, 0, 0, desired_size, world);
         }
     }
 
     fn measure(&self, root: Entity, root_constraints: &Constraints, world: &World) -> DesiredSize {
-        let tree = world.read_resource::<EntityTree>();
-        let layouts = world.read_storage::<LayoutComponent>();
         let windows = world.read_component::<WindowComponent>();
-
-        if let Some(child) = tree.child_of(root) {
-            if let Some(child_layout) = layouts.get(child) {
-                let window_comp = windows.get(root).expect("WindowLayout: No WindowComponent found for root!");
-                // mixing minimum constraints with current window size as maximum constraints
-                let constraints = Constraints {
-                    min_height: root_constraints.min_height,
-                    min_width: root_constraints.min_width,
-                    max_height: window_comp.height,
-                    max_width: window_comp.width
-                };
-                child_layout.object.measure(child, &constraints, world)
-            } else {
-                let root_layout = layouts.get(root).unwrap();
-                return DesiredSize {
-                    dirty: false,
-                    width: root_layout.constraints.max_width,
-                    height: root_layout.constraints.max_height
-                }
-            }
-        } else {
-            // An edges-case: the root has no child, just return the actual size of the window
-            let root_layout = layouts.get(root).unwrap();
-            return DesiredSize {
-                dirty: false,
-                width: root_layout.constraints.max_width,
-                height: root_layout.constraints.max_height
-            }
+        let window_comp = windows.get(root).expect("WindowLayout: No WindowComponent found for root!");
+        // the window keeps its current size whatever its child desires, but not below the minimum constraints
+        DesiredSize {
+            width: window_comp.width.max(root_constraints.min_width),
+            height: window_comp.height.max(root_constraints.min_height)
         }
     }
-}
\ No newline at end of file
+
+    fn sized_by_children(&self) -> bool {
+        false
+    }
+}
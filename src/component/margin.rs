@@ -0,0 +1,12 @@
+use specs::{Component, FlaggedStorage, VecStorage};
+use crate::Thickness;
+
+/// The space around a widget which the layout of its parent keeps free.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MarginComponent {
+    pub margin: Thickness,
+}
+
+impl Component for MarginComponent {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
@@ -1,11 +1,21 @@
+mod event;
+mod focus;
+mod image;
 mod layout;
+mod margin;
+mod padding;
 mod position;
 mod render;
 mod size;
 mod text;
 mod window;
 
+pub use self::event::*;
+pub use self::focus::*;
+pub use self::image::*;
 pub use self::layout::*;
+pub use self::margin::*;
+pub use self::padding::*;
 pub use self::position::*;
 pub use self::render::*;
 pub use self::size::*;
@@ -0,0 +1,12 @@
+use specs::{Component, FlaggedStorage, VecStorage};
+use crate::Thickness;
+
+/// The space between the bounds of a widget and its content: its children, its text or its image.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaddingComponent {
+    pub padding: Thickness,
+}
+
+impl Component for PaddingComponent {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
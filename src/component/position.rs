@@ -1,4 +1,4 @@
-use specs::{Component, VecStorage};
+use specs::{Component, FlaggedStorage, VecStorage};
 
 #[derive(Copy, Clone, Debug, Default)]
 pub struct PositionComponent {
@@ -7,5 +7,18 @@ pub struct PositionComponent {
 }
 
 impl Component for PositionComponent {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
+
+/// The position of a widget relative to the top left corner of the area inside the padding of its parent,
+/// set by the layout of the parent.
+/// The layout system derives the PositionComponent of the widget from it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RelativePositionComponent {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Component for RelativePositionComponent {
     type Storage = VecStorage<Self>;
 }
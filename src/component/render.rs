@@ -1,11 +1,18 @@
 use orbclient::Color;
-use specs::{Component, VecStorage};
+use specs::{Component, FlaggedStorage, VecStorage};
 use crate::Border;
 
-#[derive(Copy, Clone, Debug)]
+/// The outline of a widget within the bounds given by its PositionComponent and SizeComponent.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Shape {
+    /// A circle with the given radius in the center of the bounds.
+    /// A radius of zero (or less) makes the circle inscribed in the bounds.
     Circle(f64),
+    /// An ellipse inscribed in the bounds.
+    Ellipse,
     Rectangle,
+    /// A rectangle with rounded corners of the given radius.
+    RoundedRect(f64),
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -16,5 +23,5 @@ pub struct RenderComponent {
 }
 
 impl Component for RenderComponent {
-    type Storage = VecStorage<Self>;
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
 }
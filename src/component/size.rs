@@ -1,4 +1,4 @@
-use specs::{Component, VecStorage};
+use specs::{Component, FlaggedStorage, VecStorage};
 
 #[derive(Copy, Clone, Debug, Default)]
 pub struct SizeComponent {
@@ -7,5 +7,18 @@ pub struct SizeComponent {
 }
 
 impl Component for SizeComponent {
-    type Storage = VecStorage<Self>;
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
+
+/// The size a widget without a layout of its own asks for.
+/// The layout of the parent may give the widget another size, so the original one is kept here
+/// and the widget is measured by it, instead of by the size it got last time.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PreferredSizeComponent {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Component for PreferredSizeComponent {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
 }
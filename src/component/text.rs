@@ -1,17 +1,78 @@
 use std::usize;
 use orbclient::Color;
-use specs::{Component, VecStorage};
+use specs::{Component, FlaggedStorage, VecStorage};
+use crate::{FontStyle, FontWeight, TextSpan};
 
 #[derive(Debug, Clone)]
 pub struct TextComponent {
     pub font_family: String,
     pub font_size: usize,
+    pub font_style: FontStyle,
+    pub font_weight: FontWeight,
     pub text: String,
-    pub text_color: Color
+    pub text_color: Color,
+    /// The parts of the text drawn in a style of their own.
+    pub spans: Vec<TextSpan>,
+    /// How the text is broken into lines when it is wider than the widget. Line feeds always break the line.
+    pub wrap: TextWrap,
+    pub text_alignment: TextAlignment,
+    pub vertical_alignment: VerticalAlignment,
+    /// The height of the lines as a multiple of the height of the font.
+    pub line_height: f32,
+    /// The text is cut off after this many lines.
+    pub max_lines: Option<usize>,
+    /// Ends the text cut off by max_lines, or by the bounds of the widget, with an ellipsis.
+    pub ellipsis: bool,
+    /// The base direction of the paragraphs, it decides the order of the left-to-right and right-to-left runs.
+    pub direction: TextDirection,
+}
+
+/// The ways of breaking text into lines.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextWrap {
+    /// Only line feeds break the text.
+    #[default]
+    NoWrap,
+    /// The text is broken between words. Words longer than a line are broken between characters.
+    Word,
+    /// The text is broken between any characters.
+    Character,
+}
+
+/// The base direction of a paragraph of bidirectional text.
+/// The alignments are not mirrored by the direction, right-to-left text is still aligned to the left by TextAlignment::Left.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextDirection {
+    /// The direction of the first strong directional character of each paragraph, like in HTML's `dir="auto"`.
+    #[default]
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+/// The horizontal alignment of the lines of text within the widget.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// The spaces are stretched to make the wrapped lines as wide as the widget.
+    /// The last line of each paragraph is aligned to the left.
+    Justify,
+}
+
+/// The vertical alignment of the block of text lines within the widget.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum VerticalAlignment {
+    #[default]
+    Top,
+    Center,
+    Bottom,
 }
 
 impl Component for TextComponent {
-    type Storage = VecStorage<Self>;
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
 }
 
 impl Default for TextComponent {
@@ -19,8 +80,18 @@ impl Default for TextComponent {
         TextComponent {
             font_family: "Roboto-Medium".to_string(),
             font_size: 12,
+            font_style: FontStyle::default(),
+            font_weight: FontWeight::default(),
             text: String::new(),
-            text_color: Color::rgba(0, 0, 0, 255)
+            text_color: Color::rgba(0, 0, 0, 255),
+            spans: Vec::new(),
+            wrap: TextWrap::default(),
+            text_alignment: TextAlignment::default(),
+            vertical_alignment: VerticalAlignment::default(),
+            line_height: 1.0,
+            max_lines: None,
+            ellipsis: false,
+            direction: TextDirection::default(),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,34 @@
+use orbclient::Color;
+use specs::Entity;
+
+/// A resource tracking the widget that owns the keyboard.
+/// Use set_focus() to move the focus, so the affected widgets get notified.
+#[derive(Copy, Clone, Debug)]
+pub struct FocusManager {
+    pub(crate) focused: Option<Entity>,
+    /// The color of the ring drawn around the focused widget.
+    pub ring_color: Color,
+    /// The width of the focus ring in pixels.
+    pub ring_width: f32,
+}
+
+impl FocusManager {
+    pub fn new() -> FocusManager {
+        FocusManager {
+            focused: None,
+            ring_color: Color::rgba(51, 153, 255, 255),
+            ring_width: 2.0,
+        }
+    }
+
+    /// Returns the widget receiving the keyboard events.
+    pub fn focused(&self) -> Option<Entity> {
+        self.focused
+    }
+}
+
+impl Default for FocusManager {
+    fn default() -> Self {
+        FocusManager::new()
+    }
+}
@@ -0,0 +1,17 @@
+use specs::Entity;
+
+/// The last known state of the input devices.
+/// Needed because orbclient reports button states instead of button presses.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct InputState {
+    /// The topmost widget under the pointer.
+    pub hovered: Option<Entity>,
+    pub mouse_x: i32,
+    pub mouse_y: i32,
+    pub left_button: bool,
+    pub middle_button: bool,
+    pub right_button: bool,
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
@@ -0,0 +1,11 @@
+mod focus;
+mod input;
+mod queue;
+mod scheduler;
+mod widget_event;
+
+pub use self::focus::*;
+pub use self::input::*;
+pub use self::queue::*;
+pub use self::scheduler::*;
+pub use self::widget_event::*;
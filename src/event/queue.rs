@@ -0,0 +1,50 @@
+use std::collections::VecDeque;
+use specs::Entity;
+use crate::WidgetEvent;
+
+/// An event waiting for dispatching.
+/// When the target is None, the event is delivered to the root of the EntityTree.
+#[derive(Copy, Clone, Debug)]
+pub struct QueuedEvent {
+    pub event: WidgetEvent,
+    pub target: Option<Entity>,
+}
+
+/// A resource holding the events not yet dispatched by the event system.
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    events: VecDeque<QueuedEvent>,
+}
+
+impl EventQueue {
+    pub fn new() -> EventQueue {
+        EventQueue {
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Queues an event to be routed to the root of the tree.
+    pub fn push(&mut self, event: WidgetEvent) {
+        self.events.push_back(QueuedEvent { event, target: None });
+    }
+
+    /// Queues an event to be routed to the given entity.
+    pub fn push_to(&mut self, target: Entity, event: WidgetEvent) {
+        self.events.push_back(QueuedEvent {
+            event,
+            target: Some(target),
+        });
+    }
+
+    pub fn pop(&mut self) -> Option<QueuedEvent> {
+        self.events.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+}
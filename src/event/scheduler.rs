@@ -0,0 +1,276 @@
+use std::{sync::{Arc, Condvar, Mutex}, thread, time::{Duration, Instant}};
+use specs::World;
+
+/// A message sent from another thread, run on the UI thread with access to the world of the window.
+pub type ShellMessage = Box<dyn FnOnce(&mut World) + Send>;
+
+/// A resource deciding when the shell processes the next frame.
+///
+/// The shell sleeps while there is nothing to do. A frame is processed when the window receives an event,
+/// when a frame has been requested, when a timer is due or when a message arrives from another thread.
+/// The timers follow the clock of the scheduler, see now().
+///
+/// The Shell of a Window sleeps waiting for the events of the window, even while a ShellProxy is kept:
+/// the proxies and an Alarm set to the next timer wake it by pushing an event to the window.
+pub struct Scheduler {
+    clock: Clock,
+    frame_requested: bool,
+    timers: Vec<Instant>,
+    mailbox: Arc<Mailbox>,
+}
+
+/// The time source of a Scheduler.
+/// A manual clock only moves when the shell would sleep, so the timers of a test are due at exact times.
+pub(crate) enum Clock {
+    System,
+    Manual(Mutex<Instant>),
+}
+
+impl Clock {
+    /// Returns a manual clock starting at the current time.
+    pub(crate) fn manual() -> Clock {
+        Clock::Manual(Mutex::new(Instant::now()))
+    }
+
+    fn now(&self) -> Instant {
+        match self {
+            Clock::System => Instant::now(),
+            Clock::Manual(time) => *time.lock().unwrap(),
+        }
+    }
+}
+
+/// Wakes a shell which sleeps outside of Scheduler::wait(), e.g. waiting for the events of its window.
+pub(crate) type Waker = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Default)]
+struct Mailbox {
+    messages: Mutex<Vec<ShellMessage>>,
+    arrived: Condvar,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Scheduler {
+    /// Creates a scheduler with the first frame already requested.
+    pub fn new() -> Scheduler {
+        Scheduler::with_clock(Clock::System)
+    }
+
+    pub(crate) fn with_clock(clock: Clock) -> Scheduler {
+        Scheduler {
+            clock,
+            frame_requested: true,
+            timers: Vec::new(),
+            mailbox: Arc::new(Mailbox::default()),
+        }
+    }
+
+    /// Returns the current time of the scheduler. Timers are due, and carets blink, by this time instead of Instant::now().
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    /// Processes a frame as soon as possible. Animations request the next frame in every frame until they end.
+    pub fn request_frame(&mut self) {
+        self.frame_requested = true;
+    }
+
+    /// Processes a frame at the given time.
+    pub fn wake_at(&mut self, time: Instant) {
+        if !self.timers.contains(&time) {
+            self.timers.push(time);
+        }
+    }
+
+    /// Processes a frame after the given delay.
+    pub fn wake_after(&mut self, delay: Duration) {
+        self.wake_at(self.now() + delay);
+    }
+
+    /// Returns a handle other threads can send messages to the UI thread with.
+    pub fn proxy(&self) -> ShellProxy {
+        ShellProxy {
+            mailbox: self.mailbox.clone(),
+        }
+    }
+
+    /// Returns when the next frame is due, or None when the shell may sleep until the window receives an event.
+    pub(crate) fn next_frame(&self) -> Option<Instant> {
+        if self.frame_requested || !self.mailbox.messages.lock().unwrap().is_empty() {
+            return Some(self.now());
+        }
+        self.timers.iter().min().copied()
+    }
+
+    pub(crate) fn is_due(&self) -> bool {
+        match self.next_frame() {
+            Some(time) => time <= self.now(),
+            None => false,
+        }
+    }
+
+    /// Calls the waker whenever a message is sent from now on.
+    pub(crate) fn set_waker(&self, waker: Waker) {
+        *self.mailbox.waker.lock().unwrap() = Some(waker);
+    }
+
+    /// Starts a frame: forgets the request and the timers which are due, and takes the messages to run.
+    pub(crate) fn begin_frame(&mut self) -> Vec<ShellMessage> {
+        let now = self.now();
+        self.frame_requested = false;
+        self.timers.retain(|time| *time > now);
+        std::mem::take(&mut *self.mailbox.messages.lock().unwrap())
+    }
+
+    /// Sleeps until the deadline, or until a message arrives.
+    /// A manual clock is moved to the deadline at once, unless a message has already arrived.
+    pub(crate) fn wait(&self, deadline: Instant) {
+        let mut messages = self.mailbox.messages.lock().unwrap();
+        if let Clock::Manual(time) = &self.clock {
+            if messages.is_empty() {
+                let mut time = time.lock().unwrap();
+                *time = deadline.max(*time);
+            }
+            return;
+        }
+        while messages.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                return;
+            }
+            messages = self.mailbox.arrived.wait_timeout(messages, deadline - now).unwrap().0;
+        }
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Scheduler::new()
+    }
+}
+
+/// Sends messages to the UI thread of a window from any thread.
+#[derive(Clone)]
+pub struct ShellProxy {
+    mailbox: Arc<Mailbox>,
+}
+
+impl ShellProxy {
+    /// Runs the closure on the UI thread at the beginning of the next frame.
+    pub fn send<F: FnOnce(&mut World) + Send + 'static>(&self, message: F) {
+        self.mailbox.messages.lock().unwrap().push(Box::new(message));
+        self.mailbox.arrived.notify_all();
+        if let Some(waker) = self.mailbox.waker.lock().unwrap().as_ref() {
+            waker();
+        }
+    }
+}
+
+/// Calls a waker at a given time from a thread of its own, so the shell can sleep until its next timer
+/// while waiting for the events of the window.
+pub(crate) struct Alarm {
+    state: Arc<(Mutex<AlarmState>, Condvar)>,
+}
+
+#[derive(Default)]
+struct AlarmState {
+    time: Option<Instant>,
+    stopped: bool,
+}
+
+impl Alarm {
+    pub(crate) fn new(waker: Waker) -> Alarm {
+        let state = Arc::new((Mutex::new(AlarmState::default()), Condvar::new()));
+        let shared = state.clone();
+        thread::spawn(move || {
+            let (alarm, changed) = &*shared;
+            let mut alarm = alarm.lock().unwrap();
+            while !alarm.stopped {
+                match alarm.time {
+                    Some(time) if time <= Instant::now() => {
+                        alarm.time = None;
+                        waker();
+                    }
+                    Some(time) => alarm = changed.wait_timeout(alarm, time.saturating_duration_since(Instant::now())).unwrap().0,
+                    None => alarm = changed.wait(alarm).unwrap(),
+                }
+            }
+        });
+        Alarm { state }
+    }
+
+    /// Calls the waker at the given time, instead of the time set before. None turns the alarm off.
+    pub(crate) fn set(&self, time: Option<Instant>) {
+        let (alarm, changed) = &*self.state;
+        let mut alarm = alarm.lock().unwrap();
+        if alarm.time != time {
+            alarm.time = time;
+            changed.notify_all();
+        }
+    }
+}
+
+impl Drop for Alarm {
+    fn drop(&mut self) {
+        let (alarm, changed) = &*self.state;
+        alarm.lock().unwrap().stopped = true;
+        changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::{Arc, mpsc}, time::{Duration, Instant}};
+    use crate::{Alarm, Clock, Scheduler};
+
+    #[test]
+    fn proxies_wake_the_shell() {
+        let scheduler = Scheduler::new();
+        let (sender, woken) = mpsc::channel();
+        scheduler.set_waker(Arc::new(move || sender.send(()).unwrap()));
+        let proxy = scheduler.proxy();
+        assert!(woken.try_recv().is_err());
+        std::thread::spawn(move || proxy.send(|_| {})).join().unwrap();
+        assert!(woken.try_recv().is_ok());
+        assert!(scheduler.is_due());
+    }
+
+    #[test]
+    fn waiting_ends_when_a_message_arrives() {
+        let scheduler = Scheduler::new();
+        let proxy = scheduler.proxy();
+        let start = Instant::now();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            proxy.send(|_| {});
+        });
+        scheduler.wait(start + Duration::from_secs(60));
+        assert!(start.elapsed() < Duration::from_secs(60));
+        assert!(scheduler.next_frame().is_some());
+    }
+
+    #[test]
+    fn manual_clock_moves_to_the_deadline() {
+        let scheduler = Scheduler::with_clock(Clock::manual());
+        let start = scheduler.now();
+        scheduler.wait(start + Duration::from_secs(60));
+        assert_eq!(scheduler.now(), start + Duration::from_secs(60));
+        // time does not go back
+        scheduler.wait(start);
+        assert_eq!(scheduler.now(), start + Duration::from_secs(60));
+    }
+
+    #[test]
+    fn alarm_wakes_the_shell_at_its_time() {
+        let (sender, woken) = mpsc::channel();
+        let alarm = Alarm::new(Arc::new(move || sender.send(Instant::now()).unwrap()));
+        let time = Instant::now() + Duration::from_millis(20);
+        alarm.set(Some(time));
+        assert!(woken.recv_timeout(Duration::from_secs(60)).unwrap() >= time);
+
+        // an alarm turned off does not wake the shell
+        alarm.set(Some(Instant::now() + Duration::from_millis(20)));
+        alarm.set(None);
+        assert!(woken.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+}
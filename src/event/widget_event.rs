@@ -0,0 +1,49 @@
+/// The buttons of a pointing device.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+/// A widget-level event translated from the raw orbclient events.
+/// Pointer coordinates are in window space, except for the deltas of Scroll.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WidgetEvent {
+    MouseDown { x: i32, y: i32, button: MouseButton },
+    MouseUp { x: i32, y: i32, button: MouseButton },
+    MouseMove { x: i32, y: i32 },
+    /// The pointer moved onto the target widget.
+    MouseEnter,
+    /// The pointer left the target widget.
+    MouseLeave,
+    /// The wheel turned under the pointer. The fields are scroll deltas in wheel steps, not a position:
+    /// a positive x scrolls to the right, a positive y scrolls up, away from the user, negative values the other way.
+    Scroll { x: i32, y: i32 },
+    KeyDown { character: char, scancode: u8 },
+    KeyUp { character: char, scancode: u8 },
+    TextInput { character: char },
+    /// The target widget received the keyboard focus.
+    FocusGained,
+    /// The target widget lost the keyboard focus.
+    FocusLost,
+}
+
+/// The phase in which an event handler is invoked.
+///
+/// An event first travels from the root of the EntityTree down to its target (capture),
+/// then back from the target up to the root (bubble).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EventPhase {
+    Capture,
+    Bubble,
+}
+
+/// Tells the dispatcher whether the event should travel further along its route.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EventResult {
+    /// The event has been consumed, stop propagating it.
+    Handled,
+    /// Let the next handler on the route see the event.
+    Ignored,
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use rusttype::{point, Font, OutlineBuilder, Point, PositionedGlyph};
+use tiny_skia::{Path, PathBuilder};
+use crate::FontRegistry;
+
+/// The number of glyphs kept by a GlyphCache by default.
+pub const GLYPH_CACHE_DEFAULT_CAPACITY: usize = 2048;
+
+/// The number of horizontal and vertical positions a glyph is rendered at within a pixel.
+const SUBPIXEL_STEPS: f32 = 4.0;
+
+/// A resource caching the outlines of the glyphs drawn by the RenderingSystem across frames.
+///
+/// The outlines are keyed by the font, the glyph id, the scale and the subpixel offset of the glyph,
+/// the position of the glyph is quantized to a quarter of a pixel.
+/// When the cache grows beyond its capacity, the least recently used outlines are dropped.
+pub struct GlyphCache {
+    capacity: usize,
+    entries: HashMap<GlyphKey, CachedGlyph>,
+    // increased on every lookup, the entries are stamped with it when they are used
+    clock: u64,
+    // the generation of the FontRegistry the outlines were traced with
+    generation: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: usize,
+    glyph: u16,
+    scale: (u32, u32),
+    subpixel: (u8, u8),
+}
+
+struct CachedGlyph {
+    // glyphs without an outline, like spaces, are cached as None
+    path: Option<Path>,
+    last_used: u64,
+}
+
+impl GlyphCache {
+    pub fn new() -> GlyphCache {
+        GlyphCache::with_capacity(GLYPH_CACHE_DEFAULT_CAPACITY)
+    }
+
+    /// Creates a cache holding at most `capacity` glyphs.
+    pub fn with_capacity(capacity: usize) -> GlyphCache {
+        GlyphCache {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+            generation: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of glyphs in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Drops the outlines when a font has been replaced in the registry since the last call.
+    pub fn sync(&mut self, fonts: &FontRegistry) {
+        if self.generation != fonts.generation() {
+            self.entries.clear();
+            self.generation = fonts.generation();
+        }
+    }
+
+    /// Returns the outline of a positioned glyph and the pixel the outline is relative to.
+    /// The outline is traced on the first request only, later requests are served from the cache.
+    /// Returns None for glyphs without an outline.
+    pub fn outline(&mut self, glyph: &PositionedGlyph) -> Option<(&Path, Point<f32>)> {
+        let position = glyph.position();
+        let (x, subpixel_x) = quantize(position.x);
+        let (y, subpixel_y) = quantize(position.y);
+        let scale = glyph.scale();
+        let key = GlyphKey {
+            font: font_id(glyph.font()),
+            glyph: glyph.id().0,
+            scale: (scale.x.to_bits(), scale.y.to_bits()),
+            subpixel: (subpixel_x, subpixel_y),
+        };
+
+        self.clock += 1;
+        if !self.entries.contains_key(&key) {
+            let offset = point(subpixel_x as f32 / SUBPIXEL_STEPS, subpixel_y as f32 / SUBPIXEL_STEPS);
+            let path = trace(&glyph.unpositioned().clone().positioned(offset));
+            self.entries.insert(key, CachedGlyph { path, last_used: self.clock });
+            self.evict();
+        }
+
+        let entry = self.entries.get_mut(&key)?;
+        entry.last_used = self.clock;
+        entry.path.as_ref().map(|path| (path, point(x, y)))
+    }
+
+    /// Drops the least recently used quarter of the glyphs when the cache is over its capacity.
+    /// Evicting in batches keeps the cost of a miss low on a full cache.
+    fn evict(&mut self) {
+        if self.entries.len() <= self.capacity {
+            return;
+        }
+        let keep = self.capacity * 3 / 4;
+        if keep == 0 {
+            self.entries.clear();
+            return;
+        }
+
+        let mut stamps: Vec<u64> = self.entries.values().map(|entry| entry.last_used).collect();
+        stamps.sort_unstable();
+        let oldest_kept = stamps[stamps.len() - keep];
+        self.entries.retain(|_, entry| entry.last_used >= oldest_kept);
+    }
+}
+
+impl Default for GlyphCache {
+    fn default() -> Self {
+        GlyphCache::new()
+    }
+}
+
+/// Splits a coordinate to the whole pixel and the number of subpixel steps within the pixel.
+fn quantize(coordinate: f32) -> (f32, u8) {
+    let steps = (coordinate * SUBPIXEL_STEPS).round();
+    let pixel = (steps / SUBPIXEL_STEPS).floor();
+    (pixel, (steps - pixel * SUBPIXEL_STEPS) as u8)
+}
+
+/// Identifies a font by the address of its shared data.
+/// The address may be reused once the font is dropped, the FontRegistry tells when that may happen by its generation.
+pub(crate) fn font_id(font: &Font) -> usize {
+    match font {
+        Font::Ref(font) => std::sync::Arc::as_ptr(font) as *const u8 as usize,
+        Font::Owned(font) => std::sync::Arc::as_ptr(font) as *const u8 as usize,
+    }
+}
+
+/// Builds the outline of a glyph, relative to the pixel its origin is in.
+fn trace(glyph: &PositionedGlyph) -> Option<Path> {
+    let bbox = glyph.pixel_bounding_box()?;
+    let mut tracer = GlyphTracer {
+        path_builder: PathBuilder::new(),
+        position: point(bbox.min.x as f32, bbox.min.y as f32),
+    };
+    glyph.build_outline(&mut tracer);
+    tracer.path_builder.finish()
+}
+
+struct GlyphTracer {
+    path_builder: PathBuilder,
+    position: Point<f32>
+}
+
+impl GlyphTracer {
+    #[inline(always)]
+    fn map_point(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.position.x + x, self.position.y + y)
+    }
+}
+
+impl OutlineBuilder for GlyphTracer {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map_point(x, y);
+        self.path_builder.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map_point(x, y);
+        self.path_builder.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x, y) = self.map_point(x, y);
+        let (x1, y1) = self.map_point(x1, y1);
+        self.path_builder.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x, y) = self.map_point(x, y);
+        let (x1, y1) = self.map_point(x1, y1);
+        let (x2, y2) = self.map_point(x2, y2);
+        self.path_builder.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.path_builder.close();
+    }
+}
@@ -0,0 +1,16 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Tells for every char index of the text, the end of the text included, whether a grapheme starts there.
+///
+/// The graphemes are the extended grapheme clusters of Unicode (UAX #29), e.g. a letter with its combining marks,
+/// a flag made of two regional indicators or a line break of CR LF. The caret never stops within a grapheme.
+pub fn grapheme_boundaries(text: &str) -> Vec<bool> {
+    let mut boundaries = vec![false; text.chars().count() + 1];
+    let mut char_index = 0;
+    for grapheme in text.graphemes(true) {
+        boundaries[char_index] = true;
+        char_index += grapheme.chars().count();
+    }
+    boundaries[char_index] = true;
+    boundaries
+}
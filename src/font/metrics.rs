@@ -0,0 +1,7 @@
+use rusttype::{Font, Scale};
+
+/// Returns the height of a line of text: the distance between the highest and the lowest edge of any glyph in the font.
+pub fn line_height(font: &Font, font_size: usize) -> f32 {
+    let v_metrics = font.v_metrics(Scale::uniform(font_size as f32));
+    v_metrics.ascent - v_metrics.descent
+}
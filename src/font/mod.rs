@@ -0,0 +1,15 @@
+mod cache;
+mod grapheme;
+mod metrics;
+mod paragraph;
+mod properties;
+mod registry;
+mod span;
+
+pub use self::cache::*;
+pub use self::grapheme::*;
+pub use self::metrics::*;
+pub use self::paragraph::*;
+pub use self::properties::*;
+pub use self::registry::*;
+pub use self::span::*;
@@ -0,0 +1,860 @@
+use std::ops::Range;
+use rusttype::{point, Font, GlyphId, PositionedGlyph, Scale, ScaledGlyph};
+use rustybuzz::{Direction, Face, UnicodeBuffer};
+use unicode_bidi::{BidiInfo, Level};
+use crate::{FontRegistry, TextAlignment, TextComponent, TextDirection, TextStyle, TextWrap, VerticalAlignment, font::{cache::font_id, span::resolve_styles}, grapheme_boundaries};
+
+const ELLIPSIS: char = '…';
+
+/// The text of a TextComponent broken into lines and positioned within the bounds of its widget.
+///
+/// The text is shaped, so it gets the kerning, the ligatures and the contextual forms of its fonts,
+/// and the right-to-left runs of bidirectional text are reordered for display.
+/// The characters of the spans are drawn in their own style, all the glyphs of a line sit on a shared baseline.
+pub struct Paragraph {
+    pub lines: Vec<TextLine>,
+    /// The styles the text is drawn in. The first one is the style of the text outside of the spans.
+    pub styles: Vec<TextStyle>,
+    /// The width of the widest line.
+    pub width: f32,
+    /// The height of all the lines.
+    pub height: f32,
+    /// Tells whether a part of the text has been cut off.
+    pub truncated: bool,
+}
+
+/// A line of a Paragraph.
+pub struct TextLine {
+    /// The glyphs of the line from left to right, positioned relative to the top left corner of the bounds.
+    pub glyphs: Vec<PositionedGlyph<'static>>,
+    /// The char indices of the text shown on the line. The ellipsis is not part of the text.
+    pub chars: Range<usize>,
+    /// The parts of the line drawn in the same style, from left to right.
+    pub segments: Vec<TextSegment>,
+    /// The vertical position of the baseline.
+    pub baseline: f32,
+    /// The vertical position of the top of the line.
+    pub top: f32,
+    /// The height of the line, the highlights of the spans fill it.
+    pub height: f32,
+    /// The width of the line without its trailing whitespace.
+    pub width: f32,
+}
+
+/// Consecutive glyphs of a TextLine drawn in the same style.
+pub struct TextSegment {
+    /// The index of the style in Paragraph::styles.
+    pub style: usize,
+    /// The indices of the glyphs in TextLine::glyphs.
+    pub glyphs: Range<usize>,
+    /// The horizontal extent of the segment, without the trailing whitespace of the line.
+    pub left: f32,
+    pub right: f32,
+    /// The lines drawn across the segment for underline and strikethrough, as their top and their thickness.
+    pub decorations: Vec<(f32, f32)>,
+}
+
+/// The vertical metrics of a style, in pixels.
+struct StyleMetrics {
+    ascent: f32,
+    descent: f32,
+    /// The distance of the top of the underline below the baseline, and its thickness.
+    underline: (f32, f32),
+    /// The distance of the top of the strikethrough line above the baseline, and its thickness.
+    strikeout: (f32, f32),
+}
+
+impl StyleMetrics {
+    fn new(fonts: &mut FontRegistry, style: &TextStyle) -> StyleMetrics {
+        let size = style.font_size as f32;
+        let font = fonts.get(&style.font_family, style.font_weight, style.font_style).clone();
+        let v_metrics = font.v_metrics(Scale::uniform(size));
+        let unscaled = font.v_metrics_unscaled();
+        let units_to_pixels = size / (unscaled.ascent - unscaled.descent);
+
+        // fonts without the metrics get lines of about the weight of a regular stem
+        let thickness = (size / 14.0).max(1.0);
+        let mut underline = (thickness, thickness);
+        let mut strikeout = (size * 0.3, thickness);
+        let data = fonts.font_data(&font);
+        if let Some(face) = data.as_ref().and_then(|(data, index)| ttf_parser::Face::from_slice(data, *index).ok()) {
+            // the underline position is the center of the line, the strikeout position is its top
+            if let Some(metrics) = face.underline_metrics() {
+                let thickness = (metrics.thickness as f32 * units_to_pixels).max(1.0);
+                underline = (-metrics.position as f32 * units_to_pixels - thickness / 2.0, thickness);
+            }
+            if let Some(metrics) = face.strikeout_metrics() {
+                strikeout = (metrics.position as f32 * units_to_pixels, (metrics.thickness as f32 * units_to_pixels).max(1.0));
+            }
+        }
+        StyleMetrics { ascent: v_metrics.ascent, descent: v_metrics.descent, underline, strikeout }
+    }
+}
+
+/// A shaped glyph. The glyphs of a paragraph are kept in the logical order of the text until they are drawn.
+#[derive(Clone)]
+struct Cluster {
+    /// The char indices of the text the glyph is drawn for, more than one char for ligatures.
+    chars: Range<usize>,
+    /// The first char of the text the glyph is drawn for.
+    character: char,
+    glyph: ScaledGlyph<'static>,
+    /// The position of the glyph on the line, if the line was drawn in logical order.
+    x: f32,
+    advance: f32,
+    /// The displacement of the glyph from its pen position, the y axis points upwards.
+    offset: (f32, f32),
+    /// The bidi embedding level, odd levels are right-to-left.
+    level: u8,
+    /// The index of the style of the characters.
+    style: usize,
+}
+
+/// A line before being positioned within the bounds. The x coordinates of the clusters are relative to the line.
+struct BrokenLine {
+    clusters: Vec<Cluster>,
+    chars: Range<usize>,
+    ellipsis: Option<Cluster>,
+    // the last line of a paragraph is not justified
+    ends_paragraph: bool,
+    // the embedding level of the paragraph
+    level: u8,
+}
+
+impl BrokenLine {
+    fn content_width(&self) -> f32 {
+        self.clusters.iter()
+            .rev()
+            .find(|cluster| !cluster.character.is_whitespace())
+            .map_or(0.0, |cluster| cluster.x + cluster.advance)
+    }
+
+    /// Returns the highest ascent and the lowest descent of the styles on the line.
+    /// Empty lines get the metrics of the text outside of the spans.
+    fn extent(&self, metrics: &[StyleMetrics]) -> (f32, f32) {
+        let styles: Vec<usize> = self.clusters.iter().chain(self.ellipsis.iter()).map(|cluster| cluster.style).collect();
+        if styles.is_empty() {
+            return (metrics[0].ascent, metrics[0].descent);
+        }
+        styles.iter().fold((f32::MIN, f32::MAX), |(ascent, descent), style| {
+            (ascent.max(metrics[*style].ascent), descent.min(metrics[*style].descent))
+        })
+    }
+
+    fn width(&self) -> f32 {
+        match &self.ellipsis {
+            Some(ellipsis) => ellipsis.x + ellipsis.advance,
+            None => self.content_width(),
+        }
+    }
+
+    /// Replaces the end of the line with an ellipsis, so the line fits in the given width.
+    fn ellipsize(&mut self, ellipsis: Cluster, max_width: Option<f32>) {
+        loop {
+            let trailing_whitespace = self.clusters.last().is_some_and(|cluster| cluster.character.is_whitespace());
+            let too_wide = max_width.is_some_and(|max_width| self.content_width() + ellipsis.advance > max_width);
+            if self.clusters.is_empty() || !(trailing_whitespace || too_wide) {
+                break;
+            }
+            self.clusters.pop();
+        }
+        self.chars.end = self.clusters.last().map_or(self.chars.start, |cluster| cluster.chars.end);
+        self.ellipsis = Some(Cluster { x: self.content_width(), level: self.level, ..ellipsis });
+    }
+}
+
+impl Paragraph {
+    /// Lays out the text of the component within the given bounds.
+    ///
+    /// The text is wrapped at the width and aligned within the width and the height.
+    /// A missing width means unlimited width, then the lines are aligned to the widest one.
+    /// A missing height means the height of the lines.
+    pub fn layout(fonts: &mut FontRegistry, text: &TextComponent, width: Option<f32>, height: Option<f32>) -> Paragraph {
+        let (styles, char_styles) = resolve_styles(text);
+        let metrics: Vec<StyleMetrics> = styles.iter().map(|style| StyleMetrics::new(fonts, style)).collect();
+
+        let mut lines = Vec::new();
+        let mut index = 0;
+        for paragraph in text.text.split('\n') {
+            let (clusters, level) = shape(fonts, text, &styles, &char_styles, paragraph, index);
+            index += paragraph.chars().count() + 1;
+
+            let max_width = if text.wrap == TextWrap::NoWrap { None } else { width };
+            let ranges = break_lines(&clusters, max_width, text.wrap);
+            let count = ranges.len();
+            for (number, range) in ranges.into_iter().enumerate() {
+                let line_start = clusters.get(range.start).map_or(0.0, |cluster| cluster.x);
+                let line: Vec<Cluster> = clusters[range.clone()].iter()
+                    .map(|cluster| Cluster { x: cluster.x - line_start, ..cluster.clone() })
+                    .collect();
+                let chars = match (line.first(), line.last()) {
+                    (Some(first), Some(last)) => first.chars.start..last.chars.end,
+                    _ => index - 1..index - 1,
+                };
+                lines.push(BrokenLine { clusters: line, chars, ellipsis: None, ends_paragraph: number + 1 == count, level });
+            }
+        }
+
+        // the height of a line is the height of its tallest style, multiplied by the line height
+        let line_height = |line: &BrokenLine| {
+            let (ascent, descent) = line.extent(&metrics);
+            (ascent - descent) * text.line_height.max(0.0)
+        };
+
+        // cutting off the lines over the limit
+        let mut max_lines = text.max_lines.unwrap_or(usize::MAX).max(1);
+        if let (true, Some(height)) = (text.ellipsis, height) {
+            let mut bottom = 0.0;
+            let fitting = lines.iter().take_while(|line| {
+                bottom += line_height(line);
+                bottom <= height
+            }).count();
+            max_lines = max_lines.min(fitting.max(1));
+        }
+        let mut truncated = false;
+        if lines.len() > max_lines {
+            lines.truncate(max_lines);
+            truncated = true;
+            if text.ellipsis {
+                let ellipsis = ellipsis_cluster(fonts, text);
+                if let Some(last) = lines.last_mut() {
+                    last.ellipsize(ellipsis, width);
+                }
+            }
+        }
+        if text.ellipsis && text.wrap == TextWrap::NoWrap {
+            if let Some(width) = width {
+                let ellipsis = ellipsis_cluster(fonts, text);
+                for line in lines.iter_mut().filter(|line| line.ellipsis.is_none() && line.content_width() > width) {
+                    line.ellipsize(ellipsis.clone(), Some(width));
+                    truncated = true;
+                }
+            }
+        }
+
+        // positioning the lines within the bounds
+        let widest = lines.iter().map(BrokenLine::width).fold(0.0, f32::max);
+        let container_width = width.unwrap_or(widest);
+        let total_height: f32 = lines.iter().map(line_height).sum();
+        let top = match (text.vertical_alignment, height) {
+            (VerticalAlignment::Top, _) | (_, None) => 0.0,
+            (VerticalAlignment::Center, Some(height)) => (height - total_height) / 2.0,
+            (VerticalAlignment::Bottom, Some(height)) => height - total_height,
+        };
+
+        let mut line_top = top;
+        let mut positioned = Vec::with_capacity(lines.len());
+        for line in lines {
+            let (ascent, descent) = line.extent(&metrics);
+            let height = line_height(&line);
+            // the extra space of tall lines is shared above and below the text
+            let baseline = line_top + (height - (ascent - descent)) / 2.0 + ascent;
+            let mut positioned_line = position_line(line, text.text_alignment, container_width, baseline);
+            positioned_line.top = line_top;
+            positioned_line.height = height;
+            decorate(&mut positioned_line, &styles, &metrics);
+            positioned.push(positioned_line);
+            line_top += height;
+        }
+
+        Paragraph {
+            lines: positioned,
+            styles,
+            width: widest,
+            height: total_height,
+            truncated,
+        }
+    }
+}
+
+/// Returns the size of the text of the component laid out with the given maximum width.
+pub fn measure_text(fonts: &mut FontRegistry, text: &TextComponent, max_width: Option<f32>) -> (f32, f32) {
+    let paragraph = Paragraph::layout(fonts, text, max_width, None);
+    (paragraph.width, paragraph.height)
+}
+
+/// Returns the horizontal offsets of the caret positions within a single line of text,
+/// laid out the same way as the RenderingSystem draws it.
+///
+/// The offsets are indexed by char index. The last one belongs to the position after the last character,
+/// so the returned vector has one more element than the characters in the text.
+/// The caret before a character stands on its leading edge, which is the right edge in right-to-left runs,
+/// and the caret after the last character stands on its trailing edge.
+/// The graphemes of a ligature share its width equally, the characters within a grapheme get the offset of its start.
+pub fn caret_offsets(fonts: &mut FontRegistry, text: &TextComponent) -> Vec<f32> {
+    let (styles, char_styles) = resolve_styles(text);
+    let (mut clusters, level) = shape(fonts, text, &styles, &char_styles, &text.text, 0);
+    let count = text.text.chars().count();
+    let boundaries = grapheme_boundaries(&text.text);
+
+    // the clusters are placed from left to right in the order they are drawn
+    let content_end = clusters.iter()
+        .rposition(|cluster| !cluster.character.is_whitespace())
+        .map_or(0, |last| last + 1);
+    for cluster in clusters[content_end..].iter_mut() {
+        cluster.level = level;
+    }
+    let levels: Vec<u8> = clusters.iter().map(|cluster| cluster.level).collect();
+    let mut x = 0.0;
+    for index in visual_order(&levels) {
+        clusters[index].x = x;
+        x += clusters[index].advance;
+    }
+
+    let mut offsets = vec![0.0; count + 1];
+    let mut index = 0;
+    while index < clusters.len() {
+        // the glyphs drawn for the same characters are measured together
+        let chars = clusters[index].chars.clone();
+        let right_to_left = clusters[index].level % 2 == 1;
+        let (mut left, mut right) = (f32::MAX, f32::MIN);
+        while index < clusters.len() && clusters[index].chars == chars {
+            left = left.min(clusters[index].x);
+            right = right.max(clusters[index].x + clusters[index].advance);
+            index += 1;
+        }
+        let edge = |fraction: f32| if right_to_left { right - (right - left) * fraction } else { left + (right - left) * fraction };
+
+        let graphemes = chars.clone().filter(|char_index| boundaries[*char_index]).count().max(1);
+        let mut grapheme = 0;
+        for char_index in chars.clone() {
+            if boundaries[char_index] && char_index > chars.start {
+                grapheme += 1;
+            }
+            offsets[char_index] = edge(grapheme as f32 / graphemes as f32);
+        }
+        // the clusters are in logical order, the last one holds the last character
+        offsets[count] = edge(1.0);
+    }
+    offsets
+}
+
+/// Shapes a paragraph of the component's text on a single line, starting at zero.
+/// Returns the glyphs in logical order and the embedding level of the paragraph.
+///
+/// The text is split into runs of the same direction, style and font, then each run is shaped on its own.
+/// Every character is taken from the font of its style,
+/// or from the fallback chain of the FontRegistry when the font has no glyph for it.
+fn shape(fonts: &mut FontRegistry, text: &TextComponent, styles: &[TextStyle], char_styles: &[usize], paragraph: &str, first_index: usize) -> (Vec<Cluster>, u8) {
+    let base_level = match text.direction {
+        TextDirection::Auto => None,
+        TextDirection::LeftToRight => Some(Level::ltr()),
+        TextDirection::RightToLeft => Some(Level::rtl()),
+    };
+    if paragraph.is_empty() {
+        return (Vec::new(), base_level.map_or(0, |level| level.number()));
+    }
+    let bidi = BidiInfo::new(paragraph, base_level);
+    let paragraph_level = bidi.paragraphs.first().map_or(0, |info| info.level.number());
+
+    let chars: Vec<(usize, char)> = paragraph.char_indices().collect();
+    let char_style = |offset: usize| char_styles[first_index + offset];
+    let char_fonts: Vec<Font<'static>> = chars.iter().enumerate()
+        .map(|(offset, (_, character))| {
+            let style = &styles[char_style(offset)];
+            fonts.get_for_char(&style.font_family, style.font_weight, style.font_style, *character)
+        })
+        .collect();
+
+    let mut clusters = Vec::with_capacity(chars.len());
+    let mut caret = 0.0;
+    let mut start = 0;
+    while start < chars.len() {
+        let level = bidi.levels[chars[start].0].number();
+        let style = char_style(start);
+        let font = &char_fonts[start];
+        let mut end = start + 1;
+        while end < chars.len()
+            && bidi.levels[chars[end].0].number() == level
+            && char_style(end) == style
+            && font_id(&char_fonts[end]) == font_id(font) {
+            end += 1;
+        }
+
+        let scale = Scale::uniform(styles[style].font_size as f32);
+        let run = Run { chars: &chars[start..end], text: paragraph, first_index: first_index + start, level, style };
+        let face_data = fonts.font_data(font);
+        let face = face_data.as_ref().and_then(|(data, index)| Face::from_slice(data, *index));
+        let glyphs = match face {
+            Some(face) => run.shape(&face, font, scale),
+            None => run.place(font, scale),
+        };
+        for mut cluster in glyphs {
+            cluster.x = caret;
+            caret += cluster.advance;
+            clusters.push(cluster);
+        }
+        start = end;
+    }
+
+    // the glyphs of a grapheme, e.g. a letter and its combining marks, cover the whole grapheme,
+    // so neither the caret nor the line breaks get between them
+    let boundaries = grapheme_boundaries(paragraph);
+    for cluster in clusters.iter_mut() {
+        let (mut start, mut end) = (cluster.chars.start - first_index, cluster.chars.end - first_index);
+        while !boundaries[start] {
+            start -= 1;
+        }
+        while !boundaries[end] {
+            end += 1;
+        }
+        cluster.chars = first_index + start..first_index + end;
+    }
+    (clusters, paragraph_level)
+}
+
+/// A sequence of characters of the same direction, style and font.
+struct Run<'a> {
+    /// The byte offsets and the characters of the run within the paragraph.
+    chars: &'a [(usize, char)],
+    text: &'a str,
+    /// The char index of the first character of the run within the whole text.
+    first_index: usize,
+    level: u8,
+    style: usize,
+}
+
+impl<'a> Run<'a> {
+    /// Shapes the run with rustybuzz. Returns the glyphs in logical order.
+    fn shape(&self, face: &Face, font: &Font<'static>, scale: Scale) -> Vec<Cluster> {
+        let start_byte = self.chars[0].0;
+        let end_byte = self.chars.last().map_or(start_byte, |(offset, character)| offset + character.len_utf8());
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(&self.text[start_byte..end_byte]);
+        buffer.set_direction(if self.level % 2 == 1 { Direction::RightToLeft } else { Direction::LeftToRight });
+        buffer.guess_segment_properties();
+        let output = rustybuzz::shape(face, &[], buffer);
+
+        // the shaper works in font units, rusttype scales the height of the font to the pixel size
+        let v_metrics = font.v_metrics_unscaled();
+        let units_to_pixels = scale.y / (v_metrics.ascent - v_metrics.descent);
+
+        // the clusters of the shaper are byte offsets into the run, they are mapped to char offsets
+        let char_at = |cluster: u32| {
+            let byte = start_byte + cluster as usize;
+            self.chars.iter().position(|(offset, _)| *offset >= byte).unwrap_or(self.chars.len() - 1)
+        };
+        let mut glyphs: Vec<Cluster> = output.glyph_infos().iter()
+            .zip(output.glyph_positions().iter())
+            .map(|(info, position)| {
+                let offset = char_at(info.cluster);
+                Cluster {
+                    chars: offset..offset + 1,
+                    character: self.chars[offset].1,
+                    glyph: font.glyph(GlyphId(info.codepoint as u16)).scaled(scale),
+                    x: 0.0,
+                    advance: position.x_advance as f32 * units_to_pixels,
+                    offset: (position.x_offset as f32 * units_to_pixels, position.y_offset as f32 * units_to_pixels),
+                    level: self.level,
+                    style: self.style,
+                }
+            })
+            .collect();
+
+        // right-to-left runs come out of the shaper in visual order
+        if self.level % 2 == 1 {
+            glyphs.reverse();
+        }
+        // a glyph stands for the chars up to the next cluster, so ligatures cover all of their characters
+        let mut end = self.chars.len();
+        for glyph in glyphs.iter_mut().rev() {
+            if glyph.chars.start < end {
+                glyph.chars.end = end;
+                end = glyph.chars.start;
+            }
+        }
+        for glyph in glyphs.iter_mut() {
+            glyph.chars = self.first_index + glyph.chars.start..self.first_index + glyph.chars.end;
+        }
+        glyphs
+    }
+
+    /// Places the glyphs of the characters one after the other with kerning, for fonts the shaper cannot read.
+    fn place(&self, font: &Font<'static>, scale: Scale) -> Vec<Cluster> {
+        let mut glyphs: Vec<Cluster> = Vec::with_capacity(self.chars.len());
+        for (offset, (_, character)) in self.chars.iter().enumerate() {
+            let glyph = font.glyph(*character).scaled(scale);
+            if let Some(previous) = glyphs.last_mut() {
+                previous.advance += font.pair_kerning(scale, previous.glyph.id(), glyph.id());
+            }
+            let index = self.first_index + offset;
+            glyphs.push(Cluster {
+                chars: index..index + 1,
+                character: *character,
+                advance: glyph.h_metrics().advance_width,
+                glyph,
+                x: 0.0,
+                offset: (0.0, 0.0),
+                level: self.level,
+                style: self.style,
+            });
+        }
+        glyphs
+    }
+}
+
+/// Splits the clusters of a paragraph into lines not wider than max_width.
+/// Every line gets at least one character, and whitespace at the end of a line may overflow.
+/// The glyphs drawn for the same characters are never split between lines.
+fn break_lines(clusters: &[Cluster], max_width: Option<f32>, wrap: TextWrap) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let max_width = match max_width {
+        Some(max_width) => max_width,
+        None => {
+            lines.push(0..clusters.len());
+            return lines;
+        }
+    };
+    let breakable = |index: usize| clusters[index].chars != clusters[index - 1].chars;
+
+    let mut start = 0;
+    // the index of the first character after the last whitespace on the line
+    let mut word_start: Option<usize> = None;
+    let mut index = 0;
+    while index < clusters.len() {
+        let cluster = &clusters[index];
+        let right = cluster.x + cluster.advance - clusters[start].x;
+
+        if !cluster.character.is_whitespace() && right > max_width && index > start && breakable(index) {
+            let end = match wrap {
+                TextWrap::Word => word_start.filter(|word_start| *word_start > start).unwrap_or(index),
+                _ => index,
+            };
+            lines.push(start..end);
+            start = end;
+            word_start = None;
+            index = start;
+            continue;
+        }
+        if cluster.character.is_whitespace() {
+            word_start = Some(index + 1);
+        }
+        index += 1;
+    }
+    lines.push(start..clusters.len());
+    lines
+}
+
+/// Returns the ellipsis in the style of the text outside of the spans.
+fn ellipsis_cluster(fonts: &mut FontRegistry, text: &TextComponent) -> Cluster {
+    let font = fonts.get_for_char(&text.font_family, text.font_weight, text.font_style, ELLIPSIS);
+    let glyph = font.glyph(ELLIPSIS).scaled(Scale::uniform(text.font_size as f32));
+    let advance = glyph.h_metrics().advance_width;
+    Cluster { chars: 0..0, character: ELLIPSIS, glyph, x: 0.0, advance, offset: (0.0, 0.0), level: 0, style: 0 }
+}
+
+/// Returns the indices of the glyphs of a line in the order they are drawn from left to right.
+/// From the highest embedding level down to the lowest odd one,
+/// every sequence of glyphs at that level or higher is reversed (rule L2 of the Unicode bidi algorithm).
+fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let highest = levels.iter().copied().max().unwrap_or(0);
+    let lowest_odd = match levels.iter().copied().filter(|level| level % 2 == 1).min() {
+        Some(level) => level,
+        None => return order,
+    };
+
+    for level in (lowest_odd..=highest).rev() {
+        let mut index = 0;
+        while index < order.len() {
+            if levels[order[index]] < level {
+                index += 1;
+                continue;
+            }
+            let start = index;
+            while index < order.len() && levels[order[index]] >= level {
+                index += 1;
+            }
+            order[start..index].reverse();
+        }
+    }
+    order
+}
+
+fn position_line(line: BrokenLine, alignment: TextAlignment, container_width: f32, baseline: f32) -> TextLine {
+    let width = line.width();
+    let free_space = container_width - width;
+    let content_end = line.clusters.iter()
+        .rposition(|cluster| !cluster.character.is_whitespace())
+        .map_or(0, |last| last + 1);
+    let spaces = line.clusters[..content_end].iter().filter(|cluster| cluster.character == ' ').count();
+
+    let (left, extra_per_space) = match alignment {
+        TextAlignment::Left => (0.0, 0.0),
+        TextAlignment::Center => (free_space / 2.0, 0.0),
+        TextAlignment::Right => (free_space, 0.0),
+        TextAlignment::Justify if !line.ends_paragraph && line.ellipsis.is_none() && spaces > 0 && free_space > 0.0 => {
+            (0.0, free_space / spaces as f32)
+        }
+        // the lines which are not justified start where the paragraph does
+        TextAlignment::Justify if line.level % 2 == 1 => (free_space, 0.0),
+        TextAlignment::Justify => (0.0, 0.0),
+    };
+
+    // the whitespace at the end of the line goes to the end of the paragraph (rule L1 of the bidi algorithm)
+    let mut clusters = line.clusters;
+    for cluster in clusters[content_end..].iter_mut() {
+        cluster.level = line.level;
+    }
+    let ellipsis = line.ellipsis.is_some();
+    clusters.extend(line.ellipsis);
+    let levels: Vec<u8> = clusters.iter().map(|cluster| cluster.level).collect();
+
+    let mut pen = left;
+    let mut glyphs = Vec::with_capacity(clusters.len());
+    // the extents of the segments only cover the glyphs which are not trailing whitespace
+    let mut segments: Vec<(TextSegment, Option<(f32, f32)>)> = Vec::new();
+    for (number, index) in visual_order(&levels).into_iter().enumerate() {
+        let cluster = &clusters[index];
+        let (dx, dy) = cluster.offset;
+        glyphs.push(cluster.glyph.clone().positioned(point(pen + dx, baseline - dy)));
+        let start = pen;
+        pen += cluster.advance;
+        if cluster.character == ' ' && index < content_end {
+            pen += extra_per_space;
+        }
+
+        let content = index < content_end || (ellipsis && index == clusters.len() - 1);
+        let extent = if content { Some((start, pen)) } else { None };
+        match segments.last_mut() {
+            Some((segment, segment_extent)) if segment.style == cluster.style => {
+                segment.glyphs.end = number + 1;
+                *segment_extent = match (*segment_extent, extent) {
+                    (Some((left, right)), Some((start, end))) => Some((left.min(start), right.max(end))),
+                    (segment_extent, extent) => segment_extent.or(extent),
+                };
+            }
+            _ => {
+                let segment = TextSegment { style: cluster.style, glyphs: number..number + 1, left: start, right: start, decorations: Vec::new() };
+                segments.push((segment, extent));
+            }
+        }
+    }
+    let segments = segments.into_iter().map(|(mut segment, extent)| {
+        if let Some((left, right)) = extent {
+            segment.left = left;
+            segment.right = right;
+        }
+        segment
+    }).collect();
+
+    TextLine {
+        glyphs,
+        chars: line.chars,
+        segments,
+        baseline,
+        top: 0.0,
+        height: 0.0,
+        width: if extra_per_space > 0.0 { container_width } else { width },
+    }
+}
+
+/// Puts the underline and the strikethrough lines of the styles on the segments of the line.
+fn decorate(line: &mut TextLine, styles: &[TextStyle], metrics: &[StyleMetrics]) {
+    for segment in line.segments.iter_mut() {
+        let style = &styles[segment.style];
+        let metrics = &metrics[segment.style];
+        if style.underline {
+            segment.decorations.push((line.baseline + metrics.underline.0, metrics.underline.1));
+        }
+        if style.strikethrough {
+            segment.decorations.push((line.baseline - metrics.strikeout.0, metrics.strikeout.1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{FontRegistry, Paragraph, TextAlignment, TextComponent, TextDirection, TextWrap, VerticalAlignment, caret_offsets, grapheme_boundaries, measure_text};
+    use super::ELLIPSIS;
+
+    fn text(text: &str) -> TextComponent {
+        TextComponent { text: text.to_string(), font_size: 20, ..TextComponent::default() }
+    }
+
+    #[test]
+    fn caret_offsets_of_left_to_right_text_grow() {
+        let offsets = caret_offsets(&mut FontRegistry::new(), &text("abc"));
+        assert_eq!(offsets[0], 0.0);
+        assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn caret_offsets_follow_the_visual_order_of_mixed_text() {
+        // drawn as "abc " followed by the hebrew letters from right to left
+        let offsets = caret_offsets(&mut FontRegistry::new(), &text("abc \u{5D0}\u{5D1}\u{5D2}"));
+        assert!(offsets[0] < offsets[1] && offsets[1] < offsets[2] && offsets[2] < offsets[3] && offsets[3] < offsets[4]);
+        // the caret before a hebrew letter stands on its right edge
+        assert!(offsets[7] < offsets[6] && offsets[6] < offsets[5] && offsets[5] < offsets[4]);
+        assert_eq!(offsets[4], offsets.iter().copied().fold(0.0, f32::max));
+        // the caret after the last letter stands on its left edge, right after the space
+        let space = caret_offsets(&mut FontRegistry::new(), &text("abc "));
+        assert!((offsets[7] - space[4]).abs() < 0.01);
+    }
+
+    #[test]
+    fn caret_offsets_of_right_to_left_text_start_on_the_right() {
+        let mut text = text("\u{5D0}\u{5D1} ab");
+        text.direction = TextDirection::RightToLeft;
+        let offsets = caret_offsets(&mut FontRegistry::new(), &text);
+        // drawn as "ab" on the left, the space, then the hebrew letters from right to left
+        assert_eq!(offsets[0], offsets.iter().copied().fold(0.0, f32::max));
+        assert!(offsets[2] < offsets[1] && offsets[1] < offsets[0]);
+        assert_eq!(offsets[3], 0.0);
+        assert!(offsets[3] < offsets[4] && offsets[4] < offsets[5]);
+    }
+
+    #[test]
+    fn caret_does_not_stop_within_a_grapheme() {
+        // "e" with a combining acute accent
+        let offsets = caret_offsets(&mut FontRegistry::new(), &text("xe\u{301}y"));
+        assert_eq!(offsets[1], offsets[2]);
+        assert!(offsets[0] < offsets[1] && offsets[2] < offsets[3] && offsets[3] < offsets[4]);
+    }
+
+    #[test]
+    fn graphemes_keep_their_marks() {
+        assert_eq!(grapheme_boundaries(""), vec![true]);
+        assert_eq!(grapheme_boundaries("ae\u{301}"), vec![true, true, false, true]);
+        assert_eq!(grapheme_boundaries("\u{1F469}\u{200D}\u{1F52C}!"), vec![true, false, false, true, true]);
+        assert_eq!(grapheme_boundaries("a\n\u{301}"), vec![true, true, true, true]);
+    }
+
+    #[test]
+    fn graphemes_follow_the_unicode_rules() {
+        // the flag of Hungary, two regional indicators
+        assert_eq!(grapheme_boundaries("\u{1F1ED}\u{1F1FA}\u{1F1ED}\u{1F1FA}"), vec![true, false, true, false, true]);
+        // the hangul syllable "han" written with its leading, vowel and trailing jamo
+        assert_eq!(grapheme_boundaries("\u{1112}\u{1161}\u{11AB}a"), vec![true, false, false, true, true]);
+        assert_eq!(grapheme_boundaries("a\r\nb"), vec![true, true, false, true, true]);
+    }
+
+    fn width_of(fonts: &mut FontRegistry, text: &str) -> f32 {
+        measure_text(fonts, &self::text(text), None).0
+    }
+
+    fn line_chars(paragraph: &Paragraph) -> Vec<std::ops::Range<usize>> {
+        paragraph.lines.iter().map(|line| line.chars.clone()).collect()
+    }
+
+    fn ends_with_ellipsis(fonts: &mut FontRegistry, paragraph: &Paragraph) -> bool {
+        let ellipsis = fonts.get_for_char("Roboto-Medium", Default::default(), Default::default(), ELLIPSIS).glyph(ELLIPSIS).id();
+        paragraph.lines.last().and_then(|line| line.glyphs.last()).map(|glyph| glyph.id()) == Some(ellipsis)
+    }
+
+    #[test]
+    fn only_line_feeds_break_unwrapped_text() {
+        let mut fonts = FontRegistry::new();
+        let paragraph = Paragraph::layout(&mut fonts, &text("one two three\nfour"), Some(30.0), None);
+        assert_eq!(line_chars(&paragraph), vec![0..13, 14..18]);
+        assert!(!paragraph.truncated);
+    }
+
+    #[test]
+    fn words_are_wrapped_as_a_whole() {
+        let mut fonts = FontRegistry::new();
+        let width = width_of(&mut fonts, "one two") + 1.0;
+        let mut text = text("one two three");
+        text.wrap = TextWrap::Word;
+        let paragraph = Paragraph::layout(&mut fonts, &text, Some(width), None);
+        // the space ending a line stays on it
+        assert_eq!(line_chars(&paragraph), vec![0..8, 8..13]);
+        assert!(paragraph.lines.iter().all(|line| line.width <= width));
+        assert_eq!(paragraph.height, paragraph.lines.iter().map(|line| line.height).sum::<f32>());
+    }
+
+    #[test]
+    fn words_longer_than_a_line_and_character_wrap_break_between_characters() {
+        let mut fonts = FontRegistry::new();
+        let width = width_of(&mut fonts, "abcde") + 0.5;
+        let mut text = text("abcdefghij");
+        text.wrap = TextWrap::Word;
+        assert_eq!(line_chars(&Paragraph::layout(&mut fonts, &text, Some(width), None)), vec![0..5, 5..10]);
+
+        let width = width_of(&mut fonts, "one t") + 0.5;
+        let mut text = self::text("one two");
+        text.wrap = TextWrap::Character;
+        assert_eq!(line_chars(&Paragraph::layout(&mut fonts, &text, Some(width), None)), vec![0..5, 5..7]);
+    }
+
+    #[test]
+    fn lines_are_aligned_within_the_width() {
+        let mut fonts = FontRegistry::new();
+        let width = width_of(&mut fonts, "one two");
+        let mut text = text("one two");
+        let left_of = |fonts: &mut FontRegistry, text: &TextComponent| Paragraph::layout(fonts, text, Some(200.0), None).lines[0].glyphs[0].position().x;
+        let left = left_of(&mut fonts, &text);
+
+        text.text_alignment = TextAlignment::Center;
+        assert!((left_of(&mut fonts, &text) - left - (200.0 - width) / 2.0).abs() < 0.01);
+        text.text_alignment = TextAlignment::Right;
+        assert!((left_of(&mut fonts, &text) - left - (200.0 - width)).abs() < 0.01);
+    }
+
+    #[test]
+    fn justified_lines_fill_the_width_except_the_last_one() {
+        let mut fonts = FontRegistry::new();
+        let width = width_of(&mut fonts, "one two three") + 10.0;
+        let mut text = text("one two three four five");
+        text.wrap = TextWrap::Word;
+        text.text_alignment = TextAlignment::Justify;
+        let paragraph = Paragraph::layout(&mut fonts, &text, Some(width), None);
+        assert_eq!(line_chars(&paragraph), vec![0..14, 14..23]);
+        assert_eq!(paragraph.lines[0].width, width);
+        assert!(paragraph.lines[1].width < width);
+    }
+
+    #[test]
+    fn lines_are_aligned_vertically_within_the_height() {
+        let mut fonts = FontRegistry::new();
+        let mut text = text("one");
+        let height = Paragraph::layout(&mut fonts, &text, None, None).height;
+        text.vertical_alignment = VerticalAlignment::Center;
+        assert_eq!(Paragraph::layout(&mut fonts, &text, None, Some(100.0)).lines[0].top, (100.0 - height) / 2.0);
+        text.vertical_alignment = VerticalAlignment::Bottom;
+        assert_eq!(Paragraph::layout(&mut fonts, &text, None, Some(100.0)).lines[0].top, 100.0 - height);
+    }
+
+    #[test]
+    fn max_lines_cut_off_the_text_with_or_without_an_ellipsis() {
+        let mut fonts = FontRegistry::new();
+        let width = width_of(&mut fonts, "one two") + 1.0;
+        let mut text = text("one two three four five six");
+        text.wrap = TextWrap::Word;
+        text.max_lines = Some(2);
+
+        let paragraph = Paragraph::layout(&mut fonts, &text, Some(width), None);
+        assert_eq!(paragraph.lines.len(), 2);
+        assert!(paragraph.truncated);
+        assert!(!ends_with_ellipsis(&mut fonts, &paragraph));
+
+        text.ellipsis = true;
+        let paragraph = Paragraph::layout(&mut fonts, &text, Some(width), None);
+        assert_eq!(paragraph.lines.len(), 2);
+        assert!(ends_with_ellipsis(&mut fonts, &paragraph));
+        assert!(paragraph.lines[1].width <= width);
+
+        text.max_lines = Some(10);
+        let paragraph = Paragraph::layout(&mut fonts, &text, Some(width), None);
+        assert!(!paragraph.truncated && !ends_with_ellipsis(&mut fonts, &paragraph));
+    }
+
+    #[test]
+    fn ellipsis_cuts_off_the_text_at_the_bounds() {
+        let mut fonts = FontRegistry::new();
+        let mut text = text("one two three");
+        text.ellipsis = true;
+        let line_height = Paragraph::layout(&mut fonts, &text, None, None).height;
+
+        // unwrapped lines are cut off at the width
+        let paragraph = Paragraph::layout(&mut fonts, &text, Some(40.0), None);
+        assert!(paragraph.truncated && ends_with_ellipsis(&mut fonts, &paragraph));
+        assert!(paragraph.width <= 40.0);
+
+        // wrapped lines are cut off at the height
+        text.wrap = TextWrap::Word;
+        let paragraph = Paragraph::layout(&mut fonts, &text, Some(40.0), Some(line_height * 1.5));
+        assert_eq!(paragraph.lines.len(), 1);
+        assert!(paragraph.truncated && ends_with_ellipsis(&mut fonts, &paragraph));
+    }
+}
@@ -0,0 +1,323 @@
+use std::{collections::HashMap, fs, sync::Arc};
+use font_kit::{family_name::FamilyName, handle::Handle, properties::{Properties, Style, Weight}, source::SystemSource};
+use rusttype::Font;
+use crate::{FontStyle, FontWeight, font::cache::font_id};
+
+pub const DEFAULT_FONT_FAMILY: &[u8] = include_bytes!("../../assets/fonts/Roboto-Medium.ttf");
+pub const DEFAULT_FONT_NAME: &str = "Roboto-Medium";
+
+/// The families searched for glyphs the font of a text lacks, in this order.
+/// Missing families are skipped, the bundled font closes the chain.
+const DEFAULT_FALLBACK_CHAIN: &[&str] = &[
+    "Noto Sans",
+    "Noto Sans CJK SC",
+    "Noto Sans Arabic",
+    "Noto Sans Hebrew",
+    "Noto Sans Devanagari",
+    "Noto Sans Thai",
+    "Noto Sans Symbols",
+    "Noto Sans Symbols2",
+    "Noto Emoji",
+    "DejaVu Sans",
+    "Microsoft YaHei",
+    "Segoe UI Symbol",
+    "Arial Unicode MS",
+    DEFAULT_FONT_NAME,
+];
+
+/// A resource holding the fonts available to the widgets.
+///
+/// Fonts are looked up by their family name. Registered fonts take precedence, any other family is searched
+/// among the fonts installed on the system the first time it is asked for, then kept in a cache.
+/// Families that cannot be found fall back to the bundled Roboto-Medium font, which is always registered.
+///
+/// Characters missing from a font are drawn with the first font of the fallback chain which has a glyph for them.
+pub struct FontRegistry {
+    registered: HashMap<String, Font<'static>>,
+    // None marks the families which are not installed, so they are not searched again
+    loaded: HashMap<(String, FontWeight, FontStyle), Option<Font<'static>>>,
+    fallback: Font<'static>,
+    fallback_chain: Vec<String>,
+    // the fonts found in the fallback chain for the characters missing from the fonts asked for
+    glyph_fallbacks: HashMap<(char, FontWeight, FontStyle), Option<Font<'static>>>,
+    // the shaper needs the raw data of the fonts, rusttype does not give it back, so it is kept here by font_id
+    data: HashMap<usize, (Arc<Vec<u8>>, u32)>,
+    // increased whenever a registered font is replaced, as its font_id may be reused by the next font parsed
+    generation: u64,
+    // scanning the installed fonts is expensive, so it is postponed until a system font is really needed
+    source: Option<SystemSource>,
+}
+
+impl FontRegistry {
+    pub fn new() -> FontRegistry {
+        let fallback = Font::try_from_bytes(DEFAULT_FONT_FAMILY).expect("The bundled font is corrupt!");
+        let mut registered = HashMap::new();
+        registered.insert(DEFAULT_FONT_NAME.to_string(), fallback.clone());
+        let mut data = HashMap::new();
+        data.insert(font_id(&fallback), (Arc::new(DEFAULT_FONT_FAMILY.to_vec()), 0));
+
+        FontRegistry {
+            registered,
+            loaded: HashMap::new(),
+            fallback,
+            fallback_chain: DEFAULT_FALLBACK_CHAIN.iter().map(|family| family.to_string()).collect(),
+            glyph_fallbacks: HashMap::new(),
+            data,
+            generation: 0,
+            source: None,
+        }
+    }
+
+    /// Returns the font of the given family, weight and style.
+    ///
+    /// Registered fonts are returned regardless of the weight and the style.
+    /// The generic CSS families (serif, sans-serif, monospace, cursive and fantasy) are also understood.
+    pub fn get(&mut self, family: &str, weight: FontWeight, style: FontStyle) -> &Font<'static> {
+        if let Some(err) = self.load(family, weight, style) {
+            log::warn!("Cannot load font {} ({:?}, {:?}): {}, falling back to {}", family, weight, style, err, DEFAULT_FONT_NAME);
+        }
+        self.cached(family, weight, style).unwrap_or(&self.fallback)
+    }
+
+    /// Returns the font to draw the character with.
+    /// It is the font of the given family if it has a glyph for the character,
+    /// otherwise the first font of the fallback chain having one.
+    /// When none of them has a glyph, the font of the given family is returned to draw its replacement glyph.
+    pub fn get_for_char(&mut self, family: &str, weight: FontWeight, style: FontStyle, character: char) -> Font<'static> {
+        let primary = self.get(family, weight, style).clone();
+        if character.is_control() || has_glyph(&primary, character) {
+            return primary;
+        }
+
+        let key = (character, weight, style);
+        if !self.glyph_fallbacks.contains_key(&key) {
+            let mut found = None;
+            for family in self.fallback_chain.clone().iter() {
+                self.load(family, weight, style);
+                if let Some(font) = self.cached(family, weight, style).filter(|font| has_glyph(font, character)) {
+                    found = Some(font.clone());
+                    break;
+                }
+            }
+            self.glyph_fallbacks.insert(key, found);
+        }
+        self.glyph_fallbacks[&key].clone().unwrap_or(primary)
+    }
+
+    /// Returns the families searched for the glyphs missing from a font.
+    pub fn fallback_chain(&self) -> &[String] {
+        &self.fallback_chain
+    }
+
+    /// Replaces the families searched for the glyphs missing from a font, e.g. to put a CJK font first.
+    pub fn set_fallback_chain(&mut self, families: Vec<String>) {
+        self.fallback_chain = families;
+        self.glyph_fallbacks.clear();
+    }
+
+    /// Registers a font from the contents of a TrueType or OpenType file under the given family name,
+    /// replacing the previous font of the family.
+    pub fn register(&mut self, family: &str, data: Vec<u8>) -> Result<(), String> {
+        let font = self.parse(data, 0).ok_or_else(|| format!("{} is not a supported font", family))?;
+        // the replaced font is alive until the end of the block, so the new font cannot share its font_id
+        if let Some(replaced) = self.registered.insert(family.to_string(), font) {
+            self.data.remove(&font_id(&replaced));
+            self.generation += 1;
+        }
+        self.glyph_fallbacks.clear();
+        Ok(())
+    }
+
+    /// Returns a number changing every time a registered font is replaced.
+    /// Anything cached by font, like the outlines in the GlyphCache, is stale once it changes.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the contents of the font file and the index of the font within the file.
+    pub(crate) fn font_data(&self, font: &Font) -> Option<(Arc<Vec<u8>>, u32)> {
+        self.data.get(&font_id(font)).cloned()
+    }
+
+    fn parse(&mut self, data: Vec<u8>, index: u32) -> Option<Font<'static>> {
+        let font = Font::try_from_vec_and_index(data.clone(), index)?;
+        self.data.insert(font_id(&font), (Arc::new(data), index));
+        Some(font)
+    }
+
+    fn cached(&self, family: &str, weight: FontWeight, style: FontStyle) -> Option<&Font<'static>> {
+        self.registered.get(family).or_else(|| {
+            self.loaded.get(&(family.to_string(), weight, style)).and_then(Option::as_ref)
+        })
+    }
+
+    /// Searches the installed fonts for the family, unless it is registered or has been searched before.
+    /// Returns the reason of the failure when the family has just turned out to be unavailable.
+    fn load(&mut self, family: &str, weight: FontWeight, style: FontStyle) -> Option<String> {
+        let key = (family.to_string(), weight, style);
+        if self.registered.contains_key(family) || self.loaded.contains_key(&key) {
+            return None;
+        }
+
+        let source = self.source.get_or_insert_with(SystemSource::new);
+        let font = load_system_font(source, family, weight, style)
+            .and_then(|(data, index)| self.parse(data, index).ok_or_else(|| "unsupported font format".to_string()));
+        match font {
+            Ok(font) => {
+                self.loaded.insert(key, Some(font));
+                None
+            }
+            Err(err) => {
+                self.loaded.insert(key, None);
+                Some(err)
+            }
+        }
+    }
+}
+
+impl Default for FontRegistry {
+    fn default() -> Self {
+        FontRegistry::new()
+    }
+}
+
+/// Returns the contents of the font file which matches the family, the weight and the style best,
+/// and the index of the font within the file.
+fn load_system_font(source: &SystemSource, family: &str, weight: FontWeight, style: FontStyle) -> Result<(Vec<u8>, u32), String> {
+    let family_name = match family.to_lowercase().as_str() {
+        "serif" => FamilyName::Serif,
+        "sans-serif" => FamilyName::SansSerif,
+        "monospace" => FamilyName::Monospace,
+        "cursive" => FamilyName::Cursive,
+        "fantasy" => FamilyName::Fantasy,
+        _ => FamilyName::Title(family.to_string()),
+    };
+    let mut properties = Properties::new();
+    properties.weight = Weight(weight.0 as f32);
+    properties.style = match style {
+        FontStyle::Normal => Style::Normal,
+        FontStyle::Italic => Style::Italic,
+        FontStyle::Oblique => Style::Oblique,
+    };
+
+    let handle = source
+        .select_best_match(&[family_name], &properties)
+        .map_err(|err| err.to_string())?;
+    match handle {
+        Handle::Path { path, font_index } => {
+            let bytes = fs::read(&path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+            Ok((bytes, font_index))
+        }
+        Handle::Memory { bytes, font_index } => Ok((bytes.to_vec(), font_index)),
+    }
+}
+
+fn has_glyph(font: &Font, character: char) -> bool {
+    // glyph 0 is the replacement glyph of the font, drawn for the characters it lacks
+    font.glyph(character).id().0 != 0
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{FontRegistry, FontStyle, FontWeight, font::cache::font_id};
+    use super::DEFAULT_FONT_NAME;
+
+    const TINY_FONT: &[u8] = include_bytes!("../../assets/fonts/SourceSansPro-Regular-Tiny.ttf");
+    const MISSING_FAMILY: &str = "No Such Family Installed";
+
+    fn id(fonts: &mut FontRegistry, family: &str) -> usize {
+        font_id(fonts.get(family, FontWeight::default(), FontStyle::default()))
+    }
+
+    #[test]
+    fn installed_fonts_are_not_scanned_for_registered_families() {
+        let mut fonts = FontRegistry::new();
+        assert!(fonts.source.is_none());
+        fonts.register("Tiny", TINY_FONT.to_vec()).unwrap();
+        fonts.get("Tiny", FontWeight::BOLD, FontStyle::Italic);
+        fonts.get(DEFAULT_FONT_NAME, FontWeight::default(), FontStyle::default());
+        assert!(fonts.source.is_none());
+    }
+
+    #[test]
+    fn missing_families_fall_back_to_the_bundled_font_and_are_searched_once() {
+        let mut fonts = FontRegistry::new();
+        let bundled = id(&mut fonts, DEFAULT_FONT_NAME);
+        assert_eq!(id(&mut fonts, MISSING_FAMILY), bundled);
+        assert!(fonts.source.is_some());
+
+        let key = (MISSING_FAMILY.to_string(), FontWeight::default(), FontStyle::default());
+        assert!(matches!(fonts.loaded.get(&key), Some(None)));
+        assert_eq!(fonts.load(MISSING_FAMILY, FontWeight::default(), FontStyle::default()), None);
+    }
+
+    #[test]
+    fn registered_fonts_replace_each_other() {
+        let mut fonts = FontRegistry::new();
+        assert!(fonts.register("Broken", vec![1, 2, 3]).is_err());
+        assert_eq!(id(&mut fonts, "Broken"), id(&mut fonts, DEFAULT_FONT_NAME));
+
+        fonts.register("Custom", TINY_FONT.to_vec()).unwrap();
+        let tiny = id(&mut fonts, "Custom");
+        assert_ne!(tiny, id(&mut fonts, DEFAULT_FONT_NAME));
+        let custom = fonts.get("Custom", FontWeight::default(), FontStyle::default()).clone();
+        assert!(fonts.font_data(&custom).is_some());
+        assert_eq!(fonts.generation(), 0);
+
+        fonts.register("Custom", super::DEFAULT_FONT_FAMILY.to_vec()).unwrap();
+        assert_eq!(fonts.generation(), 1);
+        assert!(fonts.get("Custom", FontWeight::default(), FontStyle::default()).glyph('ő').id().0 != 0);
+    }
+
+    fn id_for_char(fonts: &mut FontRegistry, family: &str, character: char) -> usize {
+        font_id(&fonts.get_for_char(family, FontWeight::default(), FontStyle::default(), character))
+    }
+
+    /// Returns a registry with the tiny font, which has arrows but no accented 'ő', unlike the bundled font.
+    fn fonts_with_tiny() -> FontRegistry {
+        let mut fonts = FontRegistry::new();
+        fonts.register("Tiny", TINY_FONT.to_vec()).unwrap();
+        fonts
+    }
+
+    #[test]
+    fn characters_of_the_font_are_not_looked_up_in_the_chain() {
+        let mut fonts = fonts_with_tiny();
+        fonts.set_fallback_chain(vec!["Tiny".to_string()]);
+        let bundled = id(&mut fonts, DEFAULT_FONT_NAME);
+        assert_eq!(id_for_char(&mut fonts, DEFAULT_FONT_NAME, 'a'), bundled);
+        assert!(fonts.glyph_fallbacks.is_empty());
+    }
+
+    #[test]
+    fn missing_glyphs_come_from_the_first_font_of_the_chain_having_them() {
+        let mut fonts = fonts_with_tiny();
+        fonts.set_fallback_chain(vec![MISSING_FAMILY.to_string(), DEFAULT_FONT_NAME.to_string(), "Tiny".to_string()]);
+        let (bundled, tiny) = (id(&mut fonts, DEFAULT_FONT_NAME), id(&mut fonts, "Tiny"));
+        assert_eq!(id_for_char(&mut fonts, DEFAULT_FONT_NAME, '\u{2192}'), tiny);
+        assert_eq!(id_for_char(&mut fonts, "Tiny", '\u{151}'), bundled);
+    }
+
+    #[test]
+    fn the_font_draws_the_characters_missing_from_the_whole_chain() {
+        let mut fonts = fonts_with_tiny();
+        fonts.set_fallback_chain(vec![DEFAULT_FONT_NAME.to_string()]);
+        let tiny = id(&mut fonts, "Tiny");
+        assert_eq!(id_for_char(&mut fonts, "Tiny", '\u{151}'), id(&mut fonts, DEFAULT_FONT_NAME));
+        assert_eq!(id_for_char(&mut fonts, DEFAULT_FONT_NAME, '\u{2192}'), id(&mut fonts, DEFAULT_FONT_NAME));
+        // control characters have no glyph anywhere
+        assert_eq!(id_for_char(&mut fonts, "Tiny", '\n'), tiny);
+    }
+
+    #[test]
+    fn changing_the_chain_forgets_the_fallbacks_found() {
+        let mut fonts = fonts_with_tiny();
+        fonts.set_fallback_chain(vec![DEFAULT_FONT_NAME.to_string()]);
+        let (bundled, tiny) = (id(&mut fonts, DEFAULT_FONT_NAME), id(&mut fonts, "Tiny"));
+        assert_eq!(id_for_char(&mut fonts, DEFAULT_FONT_NAME, '\u{2192}'), bundled);
+
+        fonts.set_fallback_chain(vec!["Tiny".to_string()]);
+        assert_eq!(fonts.fallback_chain(), &["Tiny".to_string()]);
+        assert_eq!(id_for_char(&mut fonts, DEFAULT_FONT_NAME, '\u{2192}'), tiny);
+    }
+}
@@ -0,0 +1,243 @@
+use std::ops::Range;
+use orbclient::Color;
+use crate::{FontStyle, FontWeight, TextComponent};
+
+/// A part of the text of a TextComponent drawn in its own style.
+///
+/// The properties left unset are inherited from the TextComponent. Where spans overlap,
+/// the properties set by the later span win, and the decorations of both are drawn.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextSpan {
+    /// The char indices of the text the span applies to.
+    pub range: Range<usize>,
+    pub font_family: Option<String>,
+    pub font_size: Option<usize>,
+    pub font_style: Option<FontStyle>,
+    pub font_weight: Option<FontWeight>,
+    pub color: Option<Color>,
+    /// The color of the highlight behind the text.
+    pub background: Option<Color>,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl TextSpan {
+    pub fn new(range: Range<usize>) -> TextSpanBuilder {
+        TextSpanBuilder::new(range)
+    }
+}
+
+pub struct TextSpanBuilder {
+    span: TextSpan,
+}
+
+impl TextSpanBuilder {
+    pub fn new(range: Range<usize>) -> TextSpanBuilder {
+        TextSpanBuilder {
+            span: TextSpan {
+                range,
+                ..TextSpan::default()
+            },
+        }
+    }
+
+    pub fn font_family(mut self, font_family: &str) -> TextSpanBuilder {
+        self.span.font_family = Some(font_family.to_string());
+        self
+    }
+
+    pub fn font_size(mut self, font_size: usize) -> TextSpanBuilder {
+        self.span.font_size = Some(font_size);
+        self
+    }
+
+    pub fn font_style(mut self, font_style: FontStyle) -> TextSpanBuilder {
+        self.span.font_style = Some(font_style);
+        self
+    }
+
+    pub fn font_weight(mut self, font_weight: FontWeight) -> TextSpanBuilder {
+        self.span.font_weight = Some(font_weight);
+        self
+    }
+
+    /// Same as font_weight(FontWeight::BOLD).
+    pub fn bold(self) -> TextSpanBuilder {
+        self.font_weight(FontWeight::BOLD)
+    }
+
+    /// Same as font_style(FontStyle::Italic).
+    pub fn italic(self) -> TextSpanBuilder {
+        self.font_style(FontStyle::Italic)
+    }
+
+    pub fn color(mut self, color: Color) -> TextSpanBuilder {
+        self.span.color = Some(color);
+        self
+    }
+
+    pub fn background(mut self, background: Color) -> TextSpanBuilder {
+        self.span.background = Some(background);
+        self
+    }
+
+    pub fn underline(mut self) -> TextSpanBuilder {
+        self.span.underline = true;
+        self
+    }
+
+    pub fn strikethrough(mut self) -> TextSpanBuilder {
+        self.span.strikethrough = true;
+        self
+    }
+
+    pub fn build(self) -> TextSpan {
+        self.span
+    }
+}
+
+/// The style a character of a TextComponent is drawn with, after applying the spans covering it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    pub font_family: String,
+    pub font_size: usize,
+    pub font_style: FontStyle,
+    pub font_weight: FontWeight,
+    pub color: Color,
+    pub background: Option<Color>,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl TextStyle {
+    /// Returns the style of the text outside of the spans.
+    pub fn of(text: &TextComponent) -> TextStyle {
+        TextStyle {
+            font_family: text.font_family.clone(),
+            font_size: text.font_size,
+            font_style: text.font_style,
+            font_weight: text.font_weight,
+            color: text.text_color,
+            background: None,
+            underline: false,
+            strikethrough: false,
+        }
+    }
+
+    fn apply(&mut self, span: &TextSpan) {
+        if let Some(font_family) = &span.font_family {
+            self.font_family = font_family.clone();
+        }
+        self.font_size = span.font_size.unwrap_or(self.font_size);
+        self.font_style = span.font_style.unwrap_or(self.font_style);
+        self.font_weight = span.font_weight.unwrap_or(self.font_weight);
+        self.color = span.color.unwrap_or(self.color);
+        self.background = span.background.or(self.background);
+        self.underline |= span.underline;
+        self.strikethrough |= span.strikethrough;
+    }
+}
+
+/// Returns the distinct styles of the text, the first one being the style outside of the spans,
+/// and the index of the style of every char.
+pub(crate) fn resolve_styles(text: &TextComponent) -> (Vec<TextStyle>, Vec<usize>) {
+    let base = TextStyle::of(text);
+    let count = text.text.chars().count();
+    if text.spans.is_empty() {
+        return (vec![base], vec![0; count]);
+    }
+
+    let mut styles = vec![base.clone()];
+    let mut char_styles = Vec::with_capacity(count);
+    for index in 0..count {
+        let mut style = base.clone();
+        for span in text.spans.iter().filter(|span| span.range.contains(&index)) {
+            style.apply(span);
+        }
+        let position = match styles.iter().position(|known| *known == style) {
+            Some(position) => position,
+            None => {
+                styles.push(style);
+                styles.len() - 1
+            }
+        };
+        char_styles.push(position);
+    }
+    (styles, char_styles)
+}
+
+#[cfg(test)]
+mod test {
+    use orbclient::Color;
+    use crate::{FontRegistry, FontWeight, Paragraph, TextComponent, TextSpan, TextStyle};
+    use super::resolve_styles;
+
+    fn text(text: &str, spans: Vec<TextSpan>) -> TextComponent {
+        TextComponent { text: text.to_string(), spans, ..TextComponent::default() }
+    }
+
+    #[test]
+    fn text_without_spans_has_a_single_style() {
+        let text = text("abc", Vec::new());
+        assert_eq!(resolve_styles(&text), (vec![TextStyle::of(&text)], vec![0, 0, 0]));
+    }
+
+    #[test]
+    fn spans_style_the_chars_of_their_range() {
+        let red = Color::rgb(255, 0, 0);
+        // the range counts chars, not bytes
+        let text = text("\u{151}\u{151}abcd", vec![TextSpan::new(2..5).bold().color(red).build()]);
+        let (styles, char_styles) = resolve_styles(&text);
+        assert_eq!(char_styles, vec![0, 0, 1, 1, 1, 0]);
+
+        let styled = &styles[1];
+        assert_eq!((styled.font_weight, styled.color), (FontWeight::BOLD, red));
+        // the properties the span leaves unset are inherited
+        assert_eq!((&styled.font_family, styled.font_size), (&text.font_family, text.font_size));
+    }
+
+    #[test]
+    fn later_spans_win_and_decorations_add_up() {
+        let (red, blue) = (Color::rgb(255, 0, 0), Color::rgb(0, 0, 255));
+        let text = text("abcdef", vec![
+            TextSpan::new(0..4).color(red).underline().build(),
+            TextSpan::new(2..6).color(blue).strikethrough().build(),
+        ]);
+        let (styles, char_styles) = resolve_styles(&text);
+        let style = |index: usize| {
+            let style = &styles[char_styles[index]];
+            (style.color, style.underline, style.strikethrough)
+        };
+        assert_eq!(style(0), (red, true, false));
+        assert_eq!(style(2), (blue, true, true));
+        assert_eq!(style(5), (blue, false, true));
+        assert_eq!(styles.len(), 4);
+    }
+
+    #[test]
+    fn equal_styles_are_shared_and_ranges_beyond_the_text_are_ignored() {
+        let text = text("abcde", vec![
+            TextSpan::new(0..1).bold().build(),
+            TextSpan::new(3..4).bold().build(),
+            TextSpan::new(10..20).italic().build(),
+        ]);
+        let (styles, char_styles) = resolve_styles(&text);
+        assert_eq!(char_styles, vec![1, 0, 0, 1, 0]);
+        assert_eq!(styles.len(), 2);
+    }
+
+    #[test]
+    fn paragraph_segments_follow_the_spans() {
+        let yellow = Color::rgb(255, 255, 0);
+        let text = text("one two three", vec![TextSpan::new(4..7).background(yellow).underline().build()]);
+        let paragraph = Paragraph::layout(&mut FontRegistry::new(), &text, None, None);
+        let segments = &paragraph.lines[0].segments;
+
+        assert_eq!(segments.iter().map(|segment| segment.glyphs.clone()).collect::<Vec<_>>(), vec![0..4, 4..7, 7..13]);
+        let styled = &segments[1];
+        assert_eq!(paragraph.styles[styled.style].background, Some(yellow));
+        assert_eq!(styled.decorations.len(), 1);
+        assert!(segments[0].decorations.is_empty() && segments[2].decorations.is_empty());
+        assert!(segments[0].right <= styled.left && styled.right <= segments[2].left);
+    }
+}
@@ -0,0 +1,271 @@
+use std::time::Instant;
+use crate::{Clock, Constraints, OffscreenBackend, Scheduler, Shell, WindowComponent, init_world, run_frame};
+use orbclient::{Event, ResizeEvent};
+use specs::{Entity, World, WorldExt};
+use tiny_skia::Pixmap;
+#[cfg(test)]
+use crate::{EntityTree, Label};
+
+/// Runs the same event, layout and render pipeline as the Shell of a Window, but without a display server.
+/// The frames are rendered into an offscreen pixmap, which makes it usable in tests and on build machines.
+///
+/// The shell has a clock of its own, which jumps to the next timer instead of sleeping,
+/// so the timers and the blinking caret are due at exact times, however slow the machine is.
+pub struct HeadlessShell {
+    backend: OffscreenBackend,
+    frame_times: Vec<Instant>,
+    root: Entity,
+    wakes: usize,
+    world: World,
+}
+
+impl HeadlessShell {
+    /// Creates a window-less shell with the given size.
+    /// The UI is built by the same kind of closure accepted by WindowBuilder::ui().
+    pub fn new<F: Fn(&mut World) -> Entity>(width: u32, height: u32, ui: F) -> HeadlessShell {
+        let window = WindowComponent {
+            id: 0,
+            title: String::new(),
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        let constraints = Constraints {
+            min_width: 0,
+            min_height: 0,
+            max_width: width,
+            max_height: height,
+        };
+        let (world, root) = init_world(window, constraints, Scheduler::with_clock(Clock::manual()), Some(&ui));
+
+        HeadlessShell {
+            backend: OffscreenBackend::new(),
+            frame_times: Vec::new(),
+            root,
+            wakes: 0,
+            world,
+        }
+    }
+
+    /// Returns the entity standing for the window, the root of the EntityTree.
+    pub fn root(&self) -> Entity {
+        self.root
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Feeds an orbclient event to the shell, as if it came from a real window.
+    /// The event is processed at the next call to render().
+    pub fn on_event(&mut self, event: Event) {
+        Shell::on_event(event, &self.world);
+    }
+
+    /// Resizes the window-less surface.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.on_event(ResizeEvent { width, height }.to_event());
+    }
+
+    /// Dispatches the pending events, lays out the widgets and renders a frame.
+    pub fn render(&mut self) -> &Pixmap {
+        self.run_frame();
+        self.backend.frame().expect("The RenderingSystem did not present any frame!")
+    }
+
+    /// Returns the time of the clock of the shell.
+    pub fn now(&self) -> Instant {
+        self.world.read_resource::<Scheduler>().now()
+    }
+
+    /// Runs the event loop until the clock of the shell reaches the deadline, the same way as the Shell of a Window does:
+    /// a frame is processed only when something is due, the shell sleeps until the next timer otherwise.
+    /// Sleeping moves the clock at once, the messages sent from other threads before are processed first.
+    pub fn run_until(&mut self, deadline: Instant) {
+        while self.now() < deadline {
+            if self.world.read_resource::<Scheduler>().is_due() {
+                self.run_frame();
+                continue;
+            }
+            let scheduler = self.world.read_resource::<Scheduler>();
+            let wake = scheduler.next_frame().map_or(deadline, |next_frame| next_frame.min(deadline));
+            scheduler.wait(wake);
+            self.wakes += 1;
+        }
+    }
+
+    /// Returns the number of frames processed since the shell was created.
+    pub fn frame_count(&self) -> usize {
+        self.frame_times.len()
+    }
+
+    /// Returns the time of the clock of the shell at each frame processed.
+    pub fn frame_times(&self) -> &[Instant] {
+        &self.frame_times
+    }
+
+    /// Returns how many times run_until() slept and woke up again.
+    pub fn wake_count(&self) -> usize {
+        self.wakes
+    }
+
+    /// Returns the last rendered frame.
+    pub fn frame(&self) -> Option<&Pixmap> {
+        self.backend.frame()
+    }
+
+    fn run_frame(&mut self) {
+        self.frame_times.push(self.now());
+        run_frame(&mut self.world, &mut self.backend);
+    }
+}
+
+#[cfg(test)]
+impl HeadlessShell {
+    /// Creates a shell showing the widgets built by the closure at their own positions,
+    /// appended in the returned order to an empty background filling the window.
+    pub(crate) fn with_widgets<F: Fn(&mut World) -> Vec<Entity>>(width: u32, height: u32, widgets: F) -> HeadlessShell {
+        HeadlessShell::new(width, height, move |world| {
+            let widgets = widgets(world);
+            let background = Label::new(String::new()).build(world);
+            let mut tree = world.write_resource::<EntityTree>();
+            tree.add_node(background);
+            for widget in widgets {
+                tree.add_node(widget);
+                tree.append_child(background, widget);
+            }
+            background
+        })
+    }
+
+    /// Returns the background created by with_widgets().
+    pub(crate) fn background(&self) -> Entity {
+        self.world.read_resource::<EntityTree>().child_of(self.root).unwrap()
+    }
+
+    /// Returns the widgets built for with_widgets(), in the order they were appended.
+    pub(crate) fn widgets(&self) -> Vec<Entity> {
+        let tree = self.world.read_resource::<EntityTree>();
+        tree.try_children_of(self.background()).unwrap().filter_map(|node| tree.entitiy_of(node)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::{Center, FontRegistry, HeadlessShell, Label, LayoutCache, Scheduler, TextBox, TextComponent, set_focus};
+    use orbclient::{K_RIGHT, KeyEvent};
+    use specs::{Join, World, WorldExt};
+
+    const ROBOTO: &[u8] = include_bytes!("../assets/fonts/Roboto-Medium.ttf");
+    const SOURCE_SANS: &[u8] = include_bytes!("../assets/fonts/SourceSansPro-Regular-Tiny.ttf");
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn idle_shell_processes_no_frames() {
+        let mut shell = HeadlessShell::new(200, 100, |world| Label::new("idle".to_string()).build(world));
+        // a proxy kept for later messages does not keep the shell awake
+        let _proxy = shell.world().read_resource::<Scheduler>().proxy();
+        let start = shell.now();
+        shell.run_until(start + millis(60_000));
+        // the first frame, then a single sleep until the deadline
+        assert_eq!(shell.frame_times(), [start]);
+        assert_eq!(shell.wake_count(), 1);
+        assert_eq!(shell.now(), start + millis(60_000));
+    }
+
+    #[test]
+    fn message_wakes_the_shell() {
+        let mut shell = HeadlessShell::new(200, 100, |world| Label::new("before".to_string()).build(world));
+        let start = shell.now();
+        shell.run_until(start + millis(100));
+        let proxy = shell.world().read_resource::<Scheduler>().proxy();
+        std::thread::spawn(move || {
+            proxy.send(|world| {
+                for text in (&mut world.write_storage::<TextComponent>()).join() {
+                    text.text = "after".to_string();
+                }
+            });
+        })
+        .join()
+        .unwrap();
+        shell.run_until(start + millis(300));
+
+        // the first frame, then the frame running the message as soon as the shell runs again
+        assert_eq!(shell.frame_times(), [start, start + millis(100)]);
+        let texts = shell.world().read_storage::<TextComponent>();
+        assert!((&texts).join().all(|text| text.text == "after"));
+    }
+
+    #[test]
+    fn blinking_caret_wakes_the_shell() {
+        let mut shell = HeadlessShell::new(200, 100, |world| {
+            let text_box = TextBox::new().build(world);
+            set_focus(Some(text_box), world);
+            text_box
+        });
+        let start = shell.now();
+        shell.run_until(start + millis(1200));
+        // the first frame shows the caret, then it disappears and appears again
+        assert_eq!(shell.frame_times(), [start, start + millis(500), start + millis(1000)]);
+    }
+
+    #[test]
+    fn moving_the_caret_restarts_the_blinking() {
+        let mut shell = HeadlessShell::new(200, 100, |world| {
+            let text_box = TextBox::new().text("text".to_string()).build(world);
+            set_focus(Some(text_box), world);
+            text_box
+        });
+        let start = shell.now();
+        shell.run_until(start + millis(700));
+        shell.on_event(KeyEvent { character: '\0', scancode: K_RIGHT, pressed: true }.to_event());
+        shell.run_until(start + millis(1500));
+        // the key moves the caret at 700, it blinks at 1200 instead of 1000,
+        // but the timer set for 1000 before the caret moved still wakes the shell
+        let blinks = [start, start + millis(500), start + millis(1000), start + millis(1200)];
+        assert_eq!(shell.frame_times(), [blinks[0], blinks[1], start + millis(700), blinks[2], blinks[3]]);
+    }
+
+    fn custom_font_label(world: &mut World) -> specs::Entity {
+        let label = Label::new("AaBbCc".to_string())
+            .font_family("Custom".to_string())
+            .font_size(32)
+            .build(world);
+        Center::new().child(label).build(world)
+    }
+
+    fn register(shell: &mut HeadlessShell, font: &[u8]) {
+        let world = shell.world_mut();
+        world.write_resource::<FontRegistry>().register("Custom", font.to_vec()).unwrap();
+        world.write_resource::<LayoutCache>().invalidate();
+        // the registry is not tracked, so the text is touched to repaint it
+        for _ in (&mut world.write_storage::<TextComponent>()).join() {}
+    }
+
+    fn rendered_with(font: &[u8]) -> Vec<u8> {
+        let mut shell = HeadlessShell::new(200, 100, custom_font_label);
+        register(&mut shell, font);
+        shell.render().data().to_vec()
+    }
+
+    #[test]
+    fn replaced_font_is_rendered() {
+        let mut shell = HeadlessShell::new(200, 100, custom_font_label);
+        // the dropped fonts may leave their address to the next one, so the family is replaced back and forth
+        for font in [ROBOTO, SOURCE_SANS, ROBOTO, SOURCE_SANS] {
+            register(&mut shell, font);
+            let frame = shell.render().data().to_vec();
+            assert!(frame == rendered_with(font));
+        }
+        assert_ne!(rendered_with(ROBOTO), rendered_with(SOURCE_SANS));
+    }
+}
@@ -1,14 +1,24 @@
 mod application;
+mod backend;
 mod component;
+mod event;
+mod font;
+mod headless;
 mod shell;
+mod snapshot;
 mod system;
 mod tree;
 mod util;
 mod widget;
 
 pub use self::application::*;
+pub use self::backend::*;
 pub use self::component::*;
+pub use self::event::*;
+pub use self::font::*;
+pub use self::headless::*;
 pub use self::shell::*;
+pub use self::snapshot::*;
 pub use self::system::*;
 pub use self::tree::*;
 pub use self::util::*;
@@ -1,5 +1,5 @@
-use std::{cell::{Ref, RefCell}, rc::Rc};
-use crate::{Constraints, EntityTree, LayoutComponent, RenderingSystem, Window, WindowComponent, WindowLayout, layout_system};
+use std::{cell::RefCell, ptr, rc::Rc, sync::Arc};
+use crate::{Alarm, Constraints, DamageTracker, EntityTree, EventHandlerComponent, EventQueue, FocusComponent, FocusManager, FontRegistry, GlyphCache, ImageComponent, InputState, LayoutCache, LayoutComponent, MarginComponent, MouseButton, PaddingComponent, PreferredSizeComponent, RelativePositionComponent, RenderBackend, RenderingSystem, Scheduler, TextBoxComponent, TextComponent, Waker, WidgetEvent, Window, WindowBackend, WindowComponent, WindowLayout, event_system, focusable_ancestor, hit_test, layout_system, schedule_caret_blink, set_focus};
 use orbclient::{Event, EventOption, Renderer, ResizeEvent};
 use specs::{Builder, Entity, RunNow, World, WorldExt};
 
@@ -15,77 +15,213 @@ impl Shell {
     }
 
     /// Handles events coming from orbclient.
-    /// Each event is consumed by its respective system.
-    pub fn on_event(event: Event) {
+    /// Input events are translated to widget events and queued for the event system,
+    /// window events are consumed by their respective system.
+    pub fn on_event(event: Event, world: &World) {
+        world.write_resource::<Scheduler>().request_frame();
         match event.to_option() {
             // TODO: implement clipboard & drag and drop operations in the future
-            EventOption::Button(_) => {}
+            EventOption::Button(button_event) => {
+                let changes = {
+                    let mut input = world.write_resource::<InputState>();
+                    let changes = [
+                        (MouseButton::Left, input.left_button, button_event.left),
+                        (MouseButton::Middle, input.middle_button, button_event.middle),
+                        (MouseButton::Right, input.right_button, button_event.right),
+                    ];
+                    input.left_button = button_event.left;
+                    input.middle_button = button_event.middle;
+                    input.right_button = button_event.right;
+                    changes
+                };
+                let (x, y) = {
+                    let input = world.read_resource::<InputState>();
+                    (input.mouse_x, input.mouse_y)
+                };
+                let target = hit_test(world, x, y);
+
+                // pressing a button moves the focus to the clicked widget, or takes it away
+                // when the widget cannot receive the keyboard
+                if changes.iter().any(|(_, was_pressed, pressed)| !was_pressed && *pressed) {
+                    set_focus(target.and_then(|target| focusable_ancestor(target, world)), world);
+                }
+
+                let mut queue = world.write_resource::<EventQueue>();
+                for (button, was_pressed, pressed) in changes.iter() {
+                    if !was_pressed && *pressed {
+                        queue_pointer_event(&mut queue, target, WidgetEvent::MouseDown { x, y, button: *button });
+                    } else if *was_pressed && !pressed {
+                        queue_pointer_event(&mut queue, target, WidgetEvent::MouseUp { x, y, button: *button });
+                    }
+                }
+            }
             EventOption::Clipboard(_) => {}
             EventOption::ClipboardUpdate(_) => {}
             EventOption::Drop(_) => {}
             EventOption::Focus(_) => {}
             EventOption::Hover(_) => {}
-            EventOption::Key(_) => {}
-            EventOption::Mouse(_) => {}
+            EventOption::Key(key_event) => {
+                let mut input = world.write_resource::<InputState>();
+                match key_event.scancode {
+                    orbclient::K_LEFT_SHIFT | orbclient::K_RIGHT_SHIFT => input.shift = key_event.pressed,
+                    orbclient::K_CTRL => input.ctrl = key_event.pressed,
+                    orbclient::K_ALT => input.alt = key_event.pressed,
+                    _ => {}
+                }
+                let event = if key_event.pressed {
+                    WidgetEvent::KeyDown { character: key_event.character, scancode: key_event.scancode }
+                } else {
+                    WidgetEvent::KeyUp { character: key_event.character, scancode: key_event.scancode }
+                };
+                let focused = world.read_resource::<FocusManager>().focused();
+                queue_keyboard_event(&mut world.write_resource::<EventQueue>(), focused, event);
+            }
+            EventOption::Mouse(mouse_event) => {
+                {
+                    let mut input = world.write_resource::<InputState>();
+                    input.mouse_x = mouse_event.x;
+                    input.mouse_y = mouse_event.y;
+                }
+                let target = hit_test(world, mouse_event.x, mouse_event.y);
+                let previous = std::mem::replace(&mut world.write_resource::<InputState>().hovered, target);
+                let mut queue = world.write_resource::<EventQueue>();
+                if previous != target {
+                    if let Some(previous) = previous {
+                        queue.push_to(previous, WidgetEvent::MouseLeave);
+                    }
+                    if let Some(target) = target {
+                        queue.push_to(target, WidgetEvent::MouseEnter);
+                    }
+                }
+                queue_pointer_event(&mut queue, target, WidgetEvent::MouseMove { x: mouse_event.x, y: mouse_event.y });
+            }
             EventOption::MouseRelative(_) => {}
             EventOption::Move(_) => {}
             EventOption::None => {}
             EventOption::Quit(_) => {}
-            EventOption::Resize(_) => {}
+            EventOption::Resize(resize_event) => {
+                let root = world.read_resource::<EntityTree>().root();
+                if let Some(root) = root {
+                    sync_window_size(root, world, resize_event);
+                }
+            }
             EventOption::Screen(_) => {}
-            EventOption::Scroll(_) => {}
-            EventOption::TextInput(_) => {}
+            EventOption::Scroll(scroll_event) => {
+                let (x, y) = {
+                    let input = world.read_resource::<InputState>();
+                    (input.mouse_x, input.mouse_y)
+                };
+                let target = hit_test(world, x, y);
+                let mut queue = world.write_resource::<EventQueue>();
+                queue_pointer_event(&mut queue, target, WidgetEvent::Scroll { x: scroll_event.x, y: scroll_event.y });
+            }
+            EventOption::TextInput(text_event) => {
+                let focused = world.read_resource::<FocusManager>().focused();
+                let mut queue = world.write_resource::<EventQueue>();
+                queue_keyboard_event(&mut queue, focused, WidgetEvent::TextInput { character: text_event.character });
+            }
             EventOption::Unknown(_) => {}
         }
     }
 
     pub fn run(&mut self) {
-        let mut world = World::new();
-        let mut tree = EntityTree::new();
-        let root;
-        //let root_id;
         // TODO: workaround the Rc<RefCell>> mess
-        {
-            let wb = self.window.borrow();
-            root = entity_for_window(wb, &mut world);
-            tree.add_node(root);
-            tree.set_root(root);
-            world.insert(tree);
-        }
-
-        if let Some(ui_builder) = self.window.borrow().ui() {
-            let child_of_root = ui_builder(&mut world);
-            let mut tree = world.write_resource::<EntityTree>();
-            tree.add_node(child_of_root);
-            tree.append_child(root, child_of_root);
-        }
+        let (mut world, _root) = {
+            let window = self.window.borrow();
+            let window_component = WindowComponent {
+                id: window.inner().id(),
+                title: window.inner().title(),
+                x: window.inner().x(),
+                y: window.inner().y(),
+                width: window.inner().width(),
+                height: window.inner().height(),
+            };
+            let constraints = Constraints {
+                min_width: window.min_width(),
+                min_height: window.min_height(),
+                max_width: window.max_width(),
+                max_height: window.max_height(),
+            };
+            init_world(window_component, constraints, Scheduler::new(), window.ui().as_deref())
+        };
 
-        world.maintain();
-
-        let mut render_system = RenderingSystem::new(self.window.clone(), &world);
+        let mut backend = WindowBackend::new(self.window.clone());
+        let waker = window_waker(self.window.borrow().inner());
+        world.read_resource::<Scheduler>().set_waker(waker.clone());
+        let alarm = Alarm::new(waker);
 
         'event_loop: loop {
+            let (due, next_frame) = {
+                let scheduler = world.read_resource::<Scheduler>();
+                (scheduler.is_due(), scheduler.next_frame())
+            };
+            // unless a frame is due, the shell sleeps until the window receives an event,
+            // the messages and the alarm of the next timer wake it by pushing an event to the window
+            alarm.set(next_frame);
+            self.window.borrow_mut().inner_mut().set_async(due);
             for event in self.window.borrow_mut().inner_mut().events() {
-                match event.to_option() {
-                    EventOption::Quit(_) => {
-                        break 'event_loop;
-                    }
-                    EventOption::Key(key_event) => println!("key event: {}", key_event.character),
-                    EventOption::Mouse(_) => {}
-                    EventOption::Resize(resize_event) => {
-                        sync_window_size(root, &world, resize_event);
-                    }
-                    _ => {}
+                if let EventOption::Quit(_) = event.to_option() {
+                    break 'event_loop;
                 }
+                Shell::on_event(event, &world);
             }
 
-            layout_system(&world);
-            render_system.run_now(&world);
-
+            if world.read_resource::<Scheduler>().is_due() {
+                run_frame(&mut world, &mut backend);
+            }
         }
     }
 }
 
+/// Returns a waker pushing an empty user event to the window from any thread.
+fn window_waker(window: &orbclient::Window) -> Waker {
+    let sender = window.event_sender();
+    Arc::new(move || {
+        let event = sdl2::event::Event::User {
+            timestamp: 0,
+            window_id: 0,
+            type_: sdl2::sys::SDL_EventType::SDL_USEREVENT as u32,
+            code: 0,
+            data1: ptr::null_mut(),
+            data2: ptr::null_mut(),
+        };
+        // the push only fails when the event queue is full, which wakes the shell anyway
+        let _ = sender.push_event(event);
+    })
+}
+
+/// Processes a frame: runs the messages sent from other threads, dispatches the queued events,
+/// lays out the widgets and repaints the damaged parts of the window.
+pub(crate) fn run_frame<B: RenderBackend>(world: &mut World, backend: &mut B) {
+    let messages = world.write_resource::<Scheduler>().begin_frame();
+    for message in messages {
+        message(world);
+    }
+    world.maintain();
+
+    event_system(world);
+    layout_system(world);
+    RenderingSystem::new(backend, world).run_now(world);
+    schedule_caret_blink(world);
+}
+
+/// Pointer events are delivered to the widget under the cursor,
+/// or to the root when the cursor is outside of every widget.
+fn queue_pointer_event(queue: &mut EventQueue, target: Option<Entity>, event: WidgetEvent) {
+    match target {
+        Some(target) => queue.push_to(target, event),
+        None => queue.push(event),
+    }
+}
+
+/// Keyboard events are delivered to the focused widget, or to the root when no widget has the focus.
+fn queue_keyboard_event(queue: &mut EventQueue, focused: Option<Entity>, event: WidgetEvent) {
+    match focused {
+        Some(focused) => queue.push_to(focused, event),
+        None => queue.push(event),
+    }
+}
+
 fn sync_window_size(window_id: Entity, world: &World, event: ResizeEvent) {
     // TODO: add min and max size checking
     // updates the WindowComponent's size in the ECS world: PixMap needs it to be resize itself in the RenderingSystem
@@ -110,27 +246,54 @@ fn sync_window_size(window_id: Entity, world: &World, event: ResizeEvent) {
     }
 }
 
-fn entity_for_window(window: Ref<Window>, world: &mut World) -> Entity {
+/// Creates the ECS world of a window: registers the components, inserts the resources,
+/// creates the root entity of the window and builds the UI under it.
+pub(crate) fn init_world(window: WindowComponent, constraints: Constraints, scheduler: Scheduler, ui: Option<&dyn Fn(&mut World) -> Entity>) -> (World, Entity) {
+    let mut world = World::new();
+    let mut tree = EntityTree::new();
+    let root = entity_for_window(window, constraints, &mut world);
+    tree.add_node(root);
+    tree.set_root(root);
+    world.insert(tree);
+    world.insert(EventQueue::new());
+    world.insert(InputState::default());
+    world.insert(FocusManager::new());
+    world.insert(scheduler);
+    world.insert(FontRegistry::new());
+    world.insert(GlyphCache::new());
+    let damage = DamageTracker::new(&mut world);
+    world.insert(damage);
+    let layout_cache = LayoutCache::new(&mut world);
+    world.insert(layout_cache);
+
+    if let Some(ui_builder) = ui {
+        let child_of_root = ui_builder(&mut world);
+        let mut tree = world.write_resource::<EntityTree>();
+        tree.add_node(child_of_root);
+        tree.append_child(root, child_of_root);
+    }
+
+    world.maintain();
+    (world, root)
+}
+
+fn entity_for_window(window_component: WindowComponent, constraints: Constraints, world: &mut World) -> Entity {
+    world.register::<EventHandlerComponent>();
+    world.register::<FocusComponent>();
+    world.register::<ImageComponent>();
     world.register::<LayoutComponent>();
+    world.register::<MarginComponent>();
+    world.register::<PaddingComponent>();
+    world.register::<PreferredSizeComponent>();
+    world.register::<RelativePositionComponent>();
+    world.register::<TextBoxComponent>();
+    world.register::<TextComponent>();
     world.register::<WindowComponent>();
-    
+
     let layout = LayoutComponent {
-        constraints: Constraints {
-            min_width: window.min_width(),
-            min_height: window.min_height(),
-            max_width: window.max_width(),
-            max_height: window.max_height(),
-        },
+        constraints,
         object: Box::new(WindowLayout{})
     };
-    let window_component = WindowComponent {
-        id: window.inner().id(),
-        title: window.inner().title(),
-        x: window.inner().x(),
-        y: window.inner().y(),
-        width: window.inner().width(),
-        height: window.inner().height(),
-    };
     world.create_entity()
         .with(window_component)
         .with(layout)
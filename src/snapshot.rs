@@ -0,0 +1,262 @@
+use std::{env, fmt, path::{Path, PathBuf}};
+use crate::HeadlessShell;
+use specs::{Entity, World};
+use tiny_skia::{Pixmap, PremultipliedColorU8};
+
+/// When this environment variable is set, the golden images are (re)written instead of being compared.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "MYGUI_UPDATE_SNAPSHOTS";
+
+/// Golden-image snapshot testing for UIs.
+///
+/// Renders a UI offscreen and compares the frame against a stored PNG image.
+/// On failure the rendered frame and a diff image are written next to the golden image
+/// with `.actual.png` and `.diff.png` suffixes.
+///
+/// ```no_run
+/// use mygui::{Label, Snapshot};
+///
+/// Snapshot::new(200, 100)
+///     .tolerance(2)
+///     .assert_matches("tests/snapshots/label.png", |world| {
+///         Label::new("sample text".to_string()).build(world)
+///     });
+/// ```
+pub struct Snapshot {
+    width: u32,
+    height: u32,
+    tolerance: u8,
+}
+
+/// The reasons a snapshot comparison can fail.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The golden image does not exist. The rendered frame has been saved to `actual`.
+    MissingGolden { golden: PathBuf, actual: PathBuf },
+    /// The golden image cannot be read or the images cannot be written.
+    Io(String),
+    /// The golden image and the rendered frame have different dimensions.
+    SizeMismatch { expected: (u32, u32), actual: (u32, u32) },
+    /// Some pixels differ more than the tolerance allows.
+    Mismatch { pixels: usize, diff: PathBuf },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::MissingGolden { golden, actual } => write!(
+                f,
+                "golden image {} not found, the rendered frame is saved to {}; set {} to accept it",
+                golden.display(), actual.display(), UPDATE_SNAPSHOTS_ENV
+            ),
+            SnapshotError::Io(msg) => write!(f, "{}", msg),
+            SnapshotError::SizeMismatch { expected, actual } => write!(
+                f,
+                "size mismatch: expected {}x{}, rendered {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            SnapshotError::Mismatch { pixels, diff } => write!(
+                f,
+                "{} pixels differ from the golden image, see {}",
+                pixels, diff.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl Snapshot {
+    /// Creates a snapshot of a window with the given size.
+    pub fn new(width: u32, height: u32) -> Snapshot {
+        Snapshot {
+            width,
+            height,
+            tolerance: 0,
+        }
+    }
+
+    /// Sets the largest difference allowed between the same color channels of two pixels.
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Renders the UI built by the closure into a pixmap.
+    pub fn render<F: Fn(&mut World) -> Entity>(&self, ui: F) -> Pixmap {
+        let mut shell = HeadlessShell::new(self.width, self.height, ui);
+        shell.render().clone()
+    }
+
+    /// Renders the UI built by the closure and compares it against the golden image.
+    pub fn compare<P: AsRef<Path>, F: Fn(&mut World) -> Entity>(&self, golden: P, ui: F) -> Result<(), SnapshotError> {
+        let golden = golden.as_ref();
+        let actual = self.render(ui);
+
+        if env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            return save(&actual, golden);
+        }
+
+        if !golden.exists() {
+            let actual_path = with_suffix(golden, "actual");
+            save(&actual, &actual_path)?;
+            return Err(SnapshotError::MissingGolden { golden: golden.to_path_buf(), actual: actual_path });
+        }
+
+        let expected = Pixmap::load_png(golden)
+            .map_err(|err| SnapshotError::Io(format!("cannot read {}: {}", golden.display(), err)))?;
+        if (expected.width(), expected.height()) != (actual.width(), actual.height()) {
+            save(&actual, &with_suffix(golden, "actual"))?;
+            return Err(SnapshotError::SizeMismatch {
+                expected: (expected.width(), expected.height()),
+                actual: (actual.width(), actual.height()),
+            });
+        }
+
+        let (diff, mismatches) = diff_images(&expected, &actual, self.tolerance);
+        if mismatches > 0 {
+            let diff_path = with_suffix(golden, "diff");
+            save(&actual, &with_suffix(golden, "actual"))?;
+            save(&diff, &diff_path)?;
+            return Err(SnapshotError::Mismatch { pixels: mismatches, diff: diff_path });
+        }
+        Ok(())
+    }
+
+    /// Like compare(), but panics when the rendered frame does not match the golden image.
+    pub fn assert_matches<P: AsRef<Path>, F: Fn(&mut World) -> Entity>(&self, golden: P, ui: F) {
+        if let Err(err) = self.compare(golden, ui) {
+            panic!("snapshot mismatch: {}", err);
+        }
+    }
+}
+
+/// Compares two images pixel by pixel.
+/// Returns a diff image, where the mismatching pixels are red over a faded copy of the actual image,
+/// and the number of mismatching pixels.
+/// Images of different sizes are compared over the area covered by either of them,
+/// the pixels covered by only one of the images are mismatches.
+pub fn diff_images(expected: &Pixmap, actual: &Pixmap, tolerance: u8) -> (Pixmap, usize) {
+    let width = expected.width().max(actual.width());
+    let height = expected.height().max(actual.height());
+    let mut diff = Pixmap::new(width, height).expect("Cannot create diff image!");
+    let mut mismatches = 0;
+
+    for y in 0..height {
+        for x in 0..width {
+            let differs = match (pixel(expected, x, y), pixel(actual, x, y)) {
+                (Some(e), Some(a)) => {
+                    let channels = [(e.red(), a.red()), (e.green(), a.green()), (e.blue(), a.blue()), (e.alpha(), a.alpha())];
+                    channels.iter().any(|(e, a)| (*e as i16 - *a as i16).unsigned_abs() > tolerance as u16)
+                }
+                _ => true,
+            };
+
+            diff.pixels_mut()[(y * width + x) as usize] = match pixel(actual, x, y) {
+                Some(a) if !differs => {
+                    // premultiplied channels stay valid when all of them are scaled down equally
+                    PremultipliedColorU8::from_rgba(a.red() / 4, a.green() / 4, a.blue() / 4, a.alpha() / 4).unwrap()
+                }
+                _ => {
+                    mismatches += 1;
+                    PremultipliedColorU8::from_rgba(255, 0, 0, 255).unwrap()
+                }
+            };
+        }
+    }
+    (diff, mismatches)
+}
+
+fn pixel(pixmap: &Pixmap, x: u32, y: u32) -> Option<PremultipliedColorU8> {
+    if x < pixmap.width() && y < pixmap.height() {
+        Some(pixmap.pixels()[(y * pixmap.width() + x) as usize])
+    } else {
+        None
+    }
+}
+
+fn save(pixmap: &Pixmap, path: &Path) -> Result<(), SnapshotError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|err| SnapshotError::Io(format!("cannot create {}: {}", dir.display(), err)))?;
+    }
+    pixmap.save_png(path)
+        .map_err(|err| SnapshotError::Io(format!("cannot write {}: {}", path.display(), err)))
+}
+
+/// Turns `dir/name.png` to `dir/name.<suffix>.png`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use crate::{Border, Center, Label, Shape, Snapshot, TextWrap, Thickness, diff_images};
+    use orbclient::Color;
+    use tiny_skia::Pixmap;
+
+    fn filled(width: u32, height: u32, gray: u8) -> Pixmap {
+        let mut pixmap = Pixmap::new(width, height).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(gray, gray, gray, 255));
+        pixmap
+    }
+
+    fn golden(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("snapshots").join(name)
+    }
+
+    #[test]
+    fn differences_within_the_tolerance_match() {
+        let (_, mismatches) = diff_images(&filled(4, 3, 100), &filled(4, 3, 102), 2);
+        assert_eq!(mismatches, 0);
+        let (_, mismatches) = diff_images(&filled(4, 3, 100), &filled(4, 3, 103), 2);
+        assert_eq!(mismatches, 12);
+    }
+
+    #[test]
+    fn images_of_different_sizes_mismatch() {
+        let (diff, mismatches) = diff_images(&filled(4, 3, 100), &filled(2, 5, 100), 0);
+        assert_eq!((diff.width(), diff.height()), (4, 5));
+        // the common 2x3 area matches
+        assert_eq!(mismatches, 4 * 5 - 2 * 3);
+    }
+
+    #[test]
+    fn label_matches_golden_image() {
+        Snapshot::new(120, 40).tolerance(2).assert_matches(golden("label.png"), |world| {
+            let label = Label::new("Label".to_string())
+                .background(Color::rgb(40, 40, 40))
+                .padding(Thickness::symmetric(6, 2))
+                .build(world);
+            Center::new().child(label).build(world)
+        });
+    }
+
+    #[test]
+    fn bordered_shape_matches_golden_image() {
+        Snapshot::new(120, 80).tolerance(2).assert_matches(golden("bordered_shape.png"), |world| {
+            let border = Border::new().width(3.0).radius(10.0).color(Color::rgb(20, 60, 160)).build();
+            let label = Label::new(String::new())
+                .shape(Shape::Rectangle)
+                .background(Color::rgb(230, 180, 40))
+                .border(border)
+                .size(80, 50)
+                .build(world);
+            Center::new().child(label).build(world)
+        });
+    }
+
+    #[test]
+    fn wrapped_text_matches_golden_image() {
+        Snapshot::new(120, 100).tolerance(2).assert_matches(golden("wrapped_text.png"), |world| {
+            let label = Label::new("The quick brown fox jumps over the lazy dog".to_string())
+                .wrap(TextWrap::Word)
+                .text_color(Color::rgb(0, 0, 0))
+                .background(Color::rgb(255, 255, 255))
+                .size(100, 90)
+                .build(world);
+            Center::new().child(label).build(world)
+        });
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use specs::{BitSet, Component, Entity, Join, ReadStorage, ReaderId, Tracked, World, WorldExt, storage::ComponentEvent};
+use tiny_skia::{Pixmap, Rect};
+use crate::{FocusManager, FontRegistry, ImageComponent, PaddingComponent, Paragraph, PositionComponent, RenderComponent, Scheduler, SizeComponent, TextBoxComponent, TextComponent};
+use super::render::{shape_bounds, text_area, text_box_viewport};
+
+/// The damaged areas repainted one by one. More areas are merged into a single one.
+const MAX_DAMAGED_AREAS: usize = 8;
+
+/// A resource tracking the parts of the window which have to be repainted.
+///
+/// Every widget whose PositionComponent, SizeComponent, RenderComponent, TextComponent, ImageComponent,
+/// TextBoxComponent or PaddingComponent has been changed since the last frame damages the area it covered in the last frame
+/// and the area it covers now. The RenderingSystem repaints the damaged areas only, into the frame it kept
+/// from the last run, and does not present any frame when nothing has been damaged.
+///
+/// Changes the tracker cannot see, e.g. a font registered under the name of a font in use, need a call to invalidate().
+pub struct DamageTracker {
+    frame: Option<Pixmap>,
+    // the whole window is repainted at the next frame
+    full: bool,
+    damage: Vec<Rect>,
+    // the bounds of the widgets and the areas they covered, as they were painted
+    painted: HashMap<Entity, (Option<Rect>, Rect)>,
+    focus_ring: Option<Rect>,
+    caret: Option<(Entity, bool)>,
+    readers: Readers,
+}
+
+struct Readers {
+    positions: ReaderId<ComponentEvent>,
+    sizes: ReaderId<ComponentEvent>,
+    renders: ReaderId<ComponentEvent>,
+    texts: ReaderId<ComponentEvent>,
+    images: ReaderId<ComponentEvent>,
+    text_boxes: ReaderId<ComponentEvent>,
+    paddings: ReaderId<ComponentEvent>,
+}
+
+impl DamageTracker {
+    /// Starts tracking the changes of the components of the world. The first frame is always painted in full.
+    pub fn new(world: &mut World) -> DamageTracker {
+        let readers = Readers {
+            positions: register_reader::<PositionComponent>(world),
+            sizes: register_reader::<SizeComponent>(world),
+            renders: register_reader::<RenderComponent>(world),
+            texts: register_reader::<TextComponent>(world),
+            images: register_reader::<ImageComponent>(world),
+            text_boxes: register_reader::<TextBoxComponent>(world),
+            paddings: register_reader::<PaddingComponent>(world),
+        };
+
+        DamageTracker {
+            frame: None,
+            full: true,
+            damage: Vec::new(),
+            painted: HashMap::new(),
+            focus_ring: None,
+            caret: None,
+            readers,
+        }
+    }
+
+    /// Makes the next frame repaint the whole window.
+    pub fn invalidate(&mut self) {
+        self.full = true;
+    }
+
+    /// Makes the next frame repaint the given area of the window.
+    pub fn damage(&mut self, area: Rect) {
+        self.damage.push(area);
+    }
+
+    /// Tells whether the area a widget covered when it was last painted overlaps the given area.
+    /// Widgets which have not been painted yet may overlap anything.
+    pub(crate) fn overlaps(&self, entity: Entity, area: &Rect) -> bool {
+        match self.painted.get(&entity) {
+            Some((_, painted)) => intersects(painted, area),
+            None => true,
+        }
+    }
+
+    /// Takes the frame kept from the last run, or a new blank frame when the window has been resized.
+    pub(crate) fn take_frame(&mut self, width: u32, height: u32) -> Option<Pixmap> {
+        match self.frame.take() {
+            Some(frame) if frame.width() == width && frame.height() == height => Some(frame),
+            _ => Pixmap::new(width, height),
+        }
+    }
+
+    /// Keeps the frame for the next run.
+    pub(crate) fn keep_frame(&mut self, frame: Pixmap) {
+        self.frame = Some(frame);
+    }
+
+    /// Collects the changes since the last frame and returns the areas to repaint,
+    /// clipped to the window and rounded out to whole pixels.
+    pub(crate) fn collect(&mut self, world: &World, width: u32, height: u32) -> Vec<Rect> {
+        let window = match Rect::from_xywh(0.0, 0.0, width as f32, height as f32) {
+            Some(window) if width > 0 && height > 0 => window,
+            _ => return Vec::new(),
+        };
+        let resized = match &self.frame {
+            Some(frame) => frame.width() != width || frame.height() != height,
+            None => true,
+        };
+        if resized {
+            self.full = true;
+        }
+
+        let positions = world.read_storage::<PositionComponent>();
+        let sizes = world.read_storage::<SizeComponent>();
+        let renders = world.read_storage::<RenderComponent>();
+        let texts = world.read_storage::<TextComponent>();
+        let images = world.read_storage::<ImageComponent>();
+        let text_boxes = world.read_storage::<TextBoxComponent>();
+        let paddings = world.read_storage::<PaddingComponent>();
+
+        // the layouts write the bounds of the widgets in every frame, so moved widgets are only repainted
+        // when their bounds are really different, any other change repaints the widget
+        let mut changed = BitSet::new();
+        let mut moved = BitSet::new();
+        let mut removed = BitSet::new();
+        read_events(&positions, &mut self.readers.positions, &mut moved, &mut removed);
+        read_events(&sizes, &mut self.readers.sizes, &mut moved, &mut removed);
+        read_events(&renders, &mut self.readers.renders, &mut changed, &mut removed);
+        read_events(&texts, &mut self.readers.texts, &mut changed, &mut removed);
+        read_events(&images, &mut self.readers.images, &mut changed, &mut removed);
+        read_events(&text_boxes, &mut self.readers.text_boxes, &mut changed, &mut removed);
+        read_events(&paddings, &mut self.readers.paddings, &mut changed, &mut removed);
+
+        // the caret blinks and the focus ring moves without any component being changed
+        let focus = *world.read_resource::<FocusManager>();
+        let focused = focus.focused();
+        let now = world.read_resource::<Scheduler>().now();
+        let caret = focused.and_then(|entity| text_boxes.get(entity).map(|text_box| (entity, text_box.caret_visible(now))));
+        if caret != self.caret {
+            for (entity, _) in self.caret.iter().chain(caret.iter()) {
+                changed.add(entity.id());
+            }
+            self.caret = caret;
+        }
+        let focus_ring = focused
+            .and_then(|entity| widget_bounds(positions.get(entity), sizes.get(entity)))
+            .and_then(|bounds| outset(&bounds, focus.ring_width + 1.0));
+        if focus_ring != self.focus_ring {
+            self.damage.extend(self.focus_ring.iter().chain(focus_ring.iter()));
+            self.focus_ring = focus_ring;
+        }
+
+        // the widgets which lost their components are not painted anymore
+        let gone: Vec<Entity> = self.painted.keys().filter(|entity| removed.contains(entity.id())).copied().collect();
+        for entity in gone {
+            if let Some((_, painted)) = self.painted.remove(&entity) {
+                self.damage.push(painted);
+            }
+        }
+
+        let mut fonts = world.write_resource::<FontRegistry>();
+        let mut measure = |entity: Entity| {
+            let text = texts.get(entity).map(|text| (text, paddings.get(entity)));
+            painted_area(positions.get(entity)?, sizes.get(entity), renders.get(entity), text, text_boxes.contains(entity), &mut fonts)
+        };
+
+        if self.full {
+            self.full = false;
+            self.damage.clear();
+            self.painted.clear();
+            for (entity, _) in (&world.entities(), &positions).join() {
+                if let Some(area) = measure(entity) {
+                    self.painted.insert(entity, (widget_bounds(positions.get(entity), sizes.get(entity)), area));
+                }
+            }
+            return vec![window];
+        }
+
+        let mut candidates = changed.clone();
+        for id in (&moved).join() {
+            candidates.add(id);
+        }
+        for (entity, _) in (&world.entities(), &candidates).join() {
+            let bounds = widget_bounds(positions.get(entity), sizes.get(entity));
+            let unchanged = !changed.contains(entity.id())
+                && self.painted.get(&entity).map(|(painted_bounds, _)| *painted_bounds) == Some(bounds);
+            if unchanged {
+                continue;
+            }
+            if let Some((_, painted)) = self.painted.remove(&entity) {
+                self.damage.push(painted);
+            }
+            if let Some(area) = measure(entity) {
+                self.damage.push(area);
+                self.painted.insert(entity, (bounds, area));
+            }
+        }
+
+        // the areas are rounded to whole pixels before they are joined, so the joined areas never share a pixel
+        let damage: Vec<Rect> = self.damage.drain(..).filter_map(|area| clip(&area, &window)).collect();
+        merge(damage)
+    }
+}
+
+/// Registers the component and a reader of the changes of its storage.
+pub(crate) fn register_reader<T>(world: &mut World) -> ReaderId<ComponentEvent>
+where
+    T: Component,
+    T::Storage: Tracked + Default,
+{
+    world.register::<T>();
+    world.write_storage::<T>().register_reader()
+}
+
+/// Sorts the events of a storage: modifications go to `modified`, removals go to `removed`.
+/// Insertions and removals change the way a widget is drawn, so they are put in `modified` too.
+fn read_events<T>(storage: &ReadStorage<T>, reader: &mut ReaderId<ComponentEvent>, modified: &mut BitSet, removed: &mut BitSet)
+where
+    T: Component,
+    T::Storage: Tracked,
+{
+    for event in storage.channel().read(reader) {
+        match event {
+            ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                modified.add(*id);
+            }
+            ComponentEvent::Removed(id) => {
+                modified.add(*id);
+                removed.add(*id);
+            }
+        }
+    }
+}
+
+fn widget_bounds(position: Option<&PositionComponent>, size: Option<&SizeComponent>) -> Option<Rect> {
+    let position = position?;
+    let (width, height) = size.map_or((0, 0), |size| (size.width, size.height));
+    Rect::from_xywh(position.x as f32, position.y as f32, width as f32, height as f32)
+}
+
+/// Returns the area a widget covers when it is drawn, the overflowing text included.
+/// The text comes with the padding it is inset by. Widgets drawing nothing cover nothing.
+fn painted_area(
+    position: &PositionComponent,
+    size: Option<&SizeComponent>,
+    render: Option<&RenderComponent>,
+    text: Option<(&TextComponent, Option<&PaddingComponent>)>,
+    text_box: bool,
+    fonts: &mut FontRegistry,
+) -> Option<Rect> {
+    let bounds = widget_bounds(Some(position), size)?;
+    let mut area: Option<Rect> = None;
+    let mut include = |rect: Option<Rect>| {
+        if let Some(rect) = rect {
+            area = Some(area.map_or(rect, |area| union(&area, &rect)));
+        }
+    };
+
+    if let Some(render) = render {
+        include(Some(bounds));
+        include(shape_bounds(render, bounds));
+    }
+    if let Some((text, padding)) = text {
+        // the text, the selection and the caret of a text box are cut off at its padding
+        if text_box {
+            include(text_box_viewport(position, size, padding));
+        } else {
+            let (x, y, width, height) = text_area(position, size, padding);
+            let paragraph = Paragraph::layout(fonts, text, width, height);
+            for line in paragraph.lines.iter() {
+                for glyph in line.glyphs.iter() {
+                    include(glyph.pixel_bounding_box().and_then(|pixels| Rect::from_ltrb(
+                        x + pixels.min.x as f32,
+                        y + pixels.min.y as f32,
+                        x + pixels.max.x as f32,
+                        y + pixels.max.y as f32,
+                    )));
+                }
+                for segment in line.segments.iter() {
+                    include(Rect::from_ltrb(x + segment.left, y + line.top, x + segment.right, y + line.top + line.height));
+                }
+            }
+        }
+    }
+
+    // antialiasing may touch the pixels around the shapes
+    area.and_then(|area| outset(&area, 1.0))
+}
+
+fn intersects(a: &Rect, b: &Rect) -> bool {
+    a.left() < b.right() && b.left() < a.right() && a.top() < b.bottom() && b.top() < a.bottom()
+}
+
+fn union(a: &Rect, b: &Rect) -> Rect {
+    Rect::from_ltrb(a.left().min(b.left()), a.top().min(b.top()), a.right().max(b.right()), a.bottom().max(b.bottom()))
+        .unwrap_or(*a)
+}
+
+fn outset(rect: &Rect, by: f32) -> Option<Rect> {
+    Rect::from_ltrb(rect.left() - by, rect.top() - by, rect.right() + by, rect.bottom() + by)
+}
+
+/// Clips the area to the window and rounds it out to whole pixels.
+fn clip(area: &Rect, window: &Rect) -> Option<Rect> {
+    let clipped = Rect::from_ltrb(
+        area.left().max(window.left()).floor(),
+        area.top().max(window.top()).floor(),
+        area.right().min(window.right()).ceil(),
+        area.bottom().min(window.bottom()).ceil(),
+    )?;
+    if clipped.width() > 0.0 && clipped.height() > 0.0 {
+        Some(clipped)
+    } else {
+        None
+    }
+}
+
+/// Joins the overlapping areas, so no pixel is painted twice. Too many areas are joined into one.
+fn merge(mut areas: Vec<Rect>) -> Vec<Rect> {
+    let mut merged: Vec<Rect> = Vec::with_capacity(areas.len());
+    while let Some(mut area) = areas.pop() {
+        // a joined area may overlap the areas which did not overlap its parts
+        while let Some(index) = merged.iter().position(|other| intersects(other, &area)) {
+            area = union(&area, &merged.swap_remove(index));
+        }
+        merged.push(area);
+    }
+    if merged.len() > MAX_DAMAGED_AREAS {
+        let first = merged[0];
+        return vec![merged.iter().fold(first, |all, area| union(&all, area))];
+    }
+    merged
+}
+
+#[cfg(test)]
+mod test {
+    use specs::{World, WorldExt};
+    use tiny_skia::Rect;
+    use crate::{FocusManager, FontRegistry, Scheduler};
+    use super::{DamageTracker, MAX_DAMAGED_AREAS, clip, intersects, merge};
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert(FocusManager::new());
+        world.insert(FontRegistry::new());
+        world.insert(Scheduler::new());
+        world
+    }
+
+    // the frame is kept as the RenderingSystem keeps it, otherwise every frame is a new window size
+    fn first_frame(tracker: &mut DamageTracker, world: &World) -> Vec<Rect> {
+        let areas = tracker.collect(world, 100, 50);
+        let frame = tracker.take_frame(100, 50).unwrap();
+        tracker.keep_frame(frame);
+        areas
+    }
+
+    fn rect(left: f32, top: f32, right: f32, bottom: f32) -> Rect {
+        Rect::from_ltrb(left, top, right, bottom).unwrap()
+    }
+
+    #[test]
+    fn merge_joins_overlapping_areas() {
+        let merged = merge(vec![rect(0.0, 0.0, 10.0, 10.0), rect(5.0, 5.0, 20.0, 20.0), rect(30.0, 0.0, 40.0, 10.0)]);
+        assert_eq!(merged.len(), 2);
+        assert!(merged.contains(&rect(0.0, 0.0, 20.0, 20.0)));
+        assert!(merged.contains(&rect(30.0, 0.0, 40.0, 10.0)));
+    }
+
+    #[test]
+    fn merge_joins_areas_overlapping_a_joined_area() {
+        // the third area only overlaps the union of the first two
+        let merged = merge(vec![rect(0.0, 0.0, 10.0, 10.0), rect(20.0, 0.0, 30.0, 10.0), rect(5.0, 5.0, 25.0, 15.0)]);
+        assert_eq!(merged, vec![rect(0.0, 0.0, 30.0, 15.0)]);
+    }
+
+    #[test]
+    fn merge_joins_too_many_areas_into_one() {
+        let areas = (0..=MAX_DAMAGED_AREAS).map(|i| rect(i as f32 * 10.0, 0.0, i as f32 * 10.0 + 5.0, 5.0)).collect();
+        let last = MAX_DAMAGED_AREAS as f32 * 10.0 + 5.0;
+        assert_eq!(merge(areas), vec![rect(0.0, 0.0, last, 5.0)]);
+    }
+
+    #[test]
+    fn clip_rounds_out_and_clips_to_the_window() {
+        let window = rect(0.0, 0.0, 100.0, 50.0);
+        assert_eq!(clip(&rect(10.4, 10.6, 20.2, 20.8), &window), Some(rect(10.0, 10.0, 21.0, 21.0)));
+        assert_eq!(clip(&rect(-10.0, -5.0, 120.0, 60.0), &window), Some(window));
+        assert_eq!(clip(&rect(90.5, 40.0, 130.0, 45.0), &window), Some(rect(90.0, 40.0, 100.0, 45.0)));
+        assert_eq!(clip(&rect(110.0, 10.0, 120.0, 20.0), &window), None);
+    }
+
+    #[test]
+    fn nothing_is_repainted_without_damage() {
+        let mut world = world();
+        let mut tracker = DamageTracker::new(&mut world);
+        assert_eq!(first_frame(&mut tracker, &world), vec![rect(0.0, 0.0, 100.0, 50.0)]);
+        assert!(tracker.collect(&world, 100, 50).is_empty());
+    }
+
+    #[test]
+    fn repainted_areas_do_not_share_pixels() {
+        let mut world = world();
+        let mut tracker = DamageTracker::new(&mut world);
+        first_frame(&mut tracker, &world);
+
+        // the areas do not overlap, but their rounded pixels do
+        tracker.damage(rect(10.0, 10.0, 20.5, 20.0));
+        tracker.damage(rect(20.6, 10.0, 30.0, 20.0));
+        tracker.damage(rect(90.0, 40.0, 120.0, 60.0));
+        let areas = tracker.collect(&world, 100, 50);
+        assert_eq!(areas.len(), 2);
+        assert!(areas.contains(&rect(10.0, 10.0, 30.0, 20.0)));
+        assert!(areas.contains(&rect(90.0, 40.0, 100.0, 50.0)));
+        for (i, a) in areas.iter().enumerate() {
+            assert!(areas[i + 1..].iter().all(|b| !intersects(a, b)));
+        }
+    }
+}
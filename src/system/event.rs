@@ -0,0 +1,188 @@
+use std::rc::Rc;
+use specs::{Entity, World, WorldExt};
+use crate::{EntityTree, EventHandler, EventHandlerComponent, EventPhase, EventQueue, EventResult, InputState, WidgetEvent, focus_next};
+
+/// Delivers the queued widget events to their targets.
+///
+/// Every event travels along the EntityTree: first from the root down to the target (capture phase),
+/// then from the target back up to the root (bubble phase).
+/// A handler returning EventResult::Handled stops the event from travelling further.
+pub fn event_system(world: &World) {
+    loop {
+        // the queue must not be borrowed while the handlers run, they are free to queue new events
+        let queued = world.write_resource::<EventQueue>().pop();
+        match queued {
+            Some(queued) => {
+                let target = queued.target.or_else(|| world.read_resource::<EntityTree>().root());
+                if let Some(target) = target {
+                    if dispatch(target, &queued.event, world) == EventResult::Ignored {
+                        default_action(&queued.event, world);
+                    }
+                }
+            }
+            None => break,
+        }
+    }
+}
+
+/// Performs the built-in reaction to an event no widget has handled.
+fn default_action(event: &WidgetEvent, world: &World) {
+    if let WidgetEvent::KeyDown { scancode: orbclient::K_TAB, .. } = event {
+        let backwards = world.read_resource::<InputState>().shift;
+        focus_next(backwards, world);
+    }
+}
+
+/// Routes a single event to the target entity through the capture and bubble phases.
+/// Returns EventResult::Handled if any handler on the route consumed the event.
+pub fn dispatch(target: Entity, event: &WidgetEvent, world: &World) -> EventResult {
+    let path = world.read_resource::<EntityTree>().path_to(target);
+
+    for widget in path.iter() {
+        if invoke_handlers(*widget, event, EventPhase::Capture, world) == EventResult::Handled {
+            return EventResult::Handled;
+        }
+    }
+
+    for widget in path.iter().rev() {
+        if invoke_handlers(*widget, event, EventPhase::Bubble, world) == EventResult::Handled {
+            return EventResult::Handled;
+        }
+    }
+
+    EventResult::Ignored
+}
+
+fn invoke_handlers(widget: Entity, event: &WidgetEvent, phase: EventPhase, world: &World) -> EventResult {
+    // cloning the handlers out of the storage, so they can access any component while running
+    let handlers: Vec<Rc<EventHandler>> = {
+        let storage = world.read_storage::<EventHandlerComponent>();
+        match storage.get(widget) {
+            Some(component) => component.handlers(phase).to_vec(),
+            None => return EventResult::Ignored,
+        }
+    };
+
+    for handler in handlers.iter() {
+        if handler(widget, event, world) == EventResult::Handled {
+            return EventResult::Handled;
+        }
+    }
+    EventResult::Ignored
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+    use crate::{EntityTree, EventHandlerComponent, EventPhase, EventQueue, EventResult, InputState, WidgetEvent, dispatch, event_system};
+    use specs::{Builder, Entity, World, WorldExt};
+
+    type Log = Rc<RefCell<Vec<(&'static str, EventPhase)>>>;
+
+    const CLICK: WidgetEvent = WidgetEvent::MouseDown { x: 0, y: 0, button: crate::MouseButton::Left };
+
+    /// Creates a widget recording its name and the phase into the log when it gets an event,
+    /// and handling the event in the given phase.
+    fn widget(world: &mut World, name: &'static str, log: &Log, handles: Option<EventPhase>) -> Entity {
+        let handler = |phase: EventPhase| {
+            let log = log.clone();
+            move |_: Entity, _: &WidgetEvent, _: &World| {
+                log.borrow_mut().push((name, phase));
+                if handles == Some(phase) { EventResult::Handled } else { EventResult::Ignored }
+            }
+        };
+        let handlers = EventHandlerComponent::new()
+            .on_capture(handler(EventPhase::Capture))
+            .on_bubble(handler(EventPhase::Bubble));
+        world.create_entity().with(handlers).build()
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<EventHandlerComponent>();
+        world.insert(EntityTree::new());
+        world.insert(EventQueue::new());
+        world.insert(InputState::default());
+        world
+    }
+
+    /// Builds root > parent > (target, sibling) with the parent handling the events in the given phase.
+    fn tree(world: &mut World, log: &Log, parent_handles: Option<EventPhase>) -> (Entity, Entity, Entity) {
+        let root = widget(world, "root", log, None);
+        let parent = widget(world, "parent", log, parent_handles);
+        let target = widget(world, "target", log, None);
+        let sibling = widget(world, "sibling", log, None);
+
+        let mut tree = world.write_resource::<EntityTree>();
+        tree.set_root(root);
+        for (parent, child) in [(root, parent), (parent, target), (parent, sibling)].iter() {
+            tree.add_node(*child);
+            tree.append_child(*parent, *child);
+        }
+        (root, parent, target)
+    }
+
+    #[test]
+    fn events_are_captured_down_and_bubble_up() {
+        let (mut world, log) = (world(), Log::default());
+        let (_, _, target) = tree(&mut world, &log, None);
+
+        assert_eq!(dispatch(target, &CLICK, &world), EventResult::Ignored);
+        assert_eq!(*log.borrow(), vec![
+            ("root", EventPhase::Capture),
+            ("parent", EventPhase::Capture),
+            ("target", EventPhase::Capture),
+            ("target", EventPhase::Bubble),
+            ("parent", EventPhase::Bubble),
+            ("root", EventPhase::Bubble),
+        ]);
+    }
+
+    #[test]
+    fn handled_event_stops_while_captured() {
+        let (mut world, log) = (world(), Log::default());
+        let (_, _, target) = tree(&mut world, &log, Some(EventPhase::Capture));
+
+        assert_eq!(dispatch(target, &CLICK, &world), EventResult::Handled);
+        assert_eq!(*log.borrow(), vec![("root", EventPhase::Capture), ("parent", EventPhase::Capture)]);
+    }
+
+    #[test]
+    fn handled_event_stops_while_bubbling() {
+        let (mut world, log) = (world(), Log::default());
+        let (_, _, target) = tree(&mut world, &log, Some(EventPhase::Bubble));
+
+        assert_eq!(dispatch(target, &CLICK, &world), EventResult::Handled);
+        assert_eq!(log.borrow().last(), Some(&("parent", EventPhase::Bubble)));
+        assert_eq!(log.borrow().len(), 5);
+    }
+
+    #[test]
+    fn events_to_entities_outside_of_the_tree_are_dropped() {
+        let (mut world, log) = (world(), Log::default());
+        tree(&mut world, &log, None);
+        let detached = widget(&mut world, "detached", &log, None);
+
+        assert_eq!(dispatch(detached, &CLICK, &world), EventResult::Ignored);
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn queued_events_reach_their_targets() {
+        let (mut world, log) = (world(), Log::default());
+        let (_, _, target) = tree(&mut world, &log, None);
+        {
+            let mut queue = world.write_resource::<EventQueue>();
+            // events without a target go to the root
+            queue.push(CLICK);
+            queue.push_to(target, WidgetEvent::FocusGained);
+        }
+
+        event_system(&world);
+        assert!(world.read_resource::<EventQueue>().is_empty());
+        let log = log.borrow();
+        assert_eq!(log[..2], [("root", EventPhase::Capture), ("root", EventPhase::Bubble)]);
+        assert_eq!(log[4], ("target", EventPhase::Capture));
+        assert_eq!(log.len(), 8);
+    }
+}
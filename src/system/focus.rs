@@ -0,0 +1,173 @@
+use specs::{Entity, World, WorldExt};
+use crate::{EntityTree, EventQueue, FocusComponent, FocusManager, WidgetEvent};
+
+/// Moves the keyboard focus to the given widget, or clears it when None is given.
+/// The widget losing the focus receives a FocusLost event, the one gaining it a FocusGained event.
+pub fn set_focus(widget: Option<Entity>, world: &World) {
+    let previous = std::mem::replace(&mut world.write_resource::<FocusManager>().focused, widget);
+    if previous == widget {
+        return;
+    }
+
+    let mut queue = world.write_resource::<EventQueue>();
+    if let Some(previous) = previous {
+        queue.push_to(previous, WidgetEvent::FocusLost);
+    }
+    if let Some(widget) = widget {
+        queue.push_to(widget, WidgetEvent::FocusGained);
+    }
+}
+
+/// Moves the keyboard focus to the next widget in the Tab order, or to the previous one when `backwards` is true.
+/// The traversal wraps around at both ends.
+pub fn focus_next(backwards: bool, world: &World) {
+    let order = tab_order(world);
+    if order.is_empty() {
+        return;
+    }
+
+    let focused = world.read_resource::<FocusManager>().focused;
+    let current = focused.and_then(|focused| order.iter().position(|widget| *widget == focused));
+    let next = match (current, backwards) {
+        (None, false) => 0,
+        (None, true) => order.len() - 1,
+        (Some(index), false) => (index + 1) % order.len(),
+        (Some(index), true) => (index + order.len() - 1) % order.len(),
+    };
+    set_focus(Some(order[next]), world);
+}
+
+/// Returns the closest focusable widget on the route from the given widget up to the root.
+pub fn focusable_ancestor(widget: Entity, world: &World) -> Option<Entity> {
+    let path = world.read_resource::<EntityTree>().path_to(widget);
+    let focus = world.read_storage::<FocusComponent>();
+    path.into_iter().rev().find(|widget| matches!(focus.get(*widget), Some(f) if f.focusable))
+}
+
+/// Returns the widgets reachable by Tab, in traversal order.
+pub fn tab_order(world: &World) -> Vec<Entity> {
+    let tree = world.read_resource::<EntityTree>();
+    let focus = world.read_storage::<FocusComponent>();
+    if tree.root().is_none() {
+        return vec![];
+    }
+
+    let mut order: Vec<(i32, Entity)> = tree.children()
+        .filter_map(|node| tree.entitiy_of(node))
+        .filter_map(|widget| match focus.get(widget) {
+            Some(f) if f.focusable && f.tab_index >= 0 => Some((f.tab_index, widget)),
+            _ => None,
+        })
+        .collect();
+    // the sort is stable, so widgets with equal index keep their tree order
+    order.sort_by_key(|(tab_index, _)| (*tab_index == 0, *tab_index));
+    order.into_iter().map(|(_, widget)| widget).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use orbclient::{ButtonEvent, KeyEvent, MouseEvent};
+    use specs::{Entity, World, WorldExt};
+    use crate::{Button, EntityTree, EventQueue, FocusManager, HeadlessShell, Label, WidgetEvent, set_focus, tab_order};
+
+    /// Builds a row of buttons 40 pixels apart, each 30x30 pixels, with the tab indices 0, 2, 1 and -1,
+    /// a disabled button with the index 0, and a label on top of the first button.
+    fn buttons(world: &mut World) -> Vec<Entity> {
+        let mut widgets: Vec<Entity> = [0, 2, 1, -1].iter()
+            .enumerate()
+            .map(|(number, tab_index)| Button::new(String::new()).tab_index(*tab_index).position(number as u32 * 40, 0).size(30, 30).build(world))
+            .collect();
+        widgets.push(Button::new(String::new()).enabled(false).position(160, 0).size(30, 30).build(world));
+        let caption = Label::new(String::new()).position(5, 5).size(10, 10).build(world);
+
+        let mut tree = world.write_resource::<EntityTree>();
+        tree.add_node(widgets[0]);
+        tree.add_node(caption);
+        tree.append_child(widgets[0], caption);
+        widgets
+    }
+
+    fn focused(shell: &HeadlessShell) -> Option<Entity> {
+        shell.world().read_resource::<FocusManager>().focused()
+    }
+
+    fn press_key(shell: &mut HeadlessShell, scancode: u8, pressed: bool) {
+        shell.on_event(KeyEvent { character: '\0', scancode, pressed }.to_event());
+        shell.render();
+    }
+
+    fn click(shell: &mut HeadlessShell, x: i32, y: i32) {
+        shell.on_event(MouseEvent { x, y }.to_event());
+        shell.on_event(ButtonEvent { left: true, middle: false, right: false }.to_event());
+        shell.on_event(ButtonEvent { left: false, middle: false, right: false }.to_event());
+        shell.render();
+    }
+
+    #[test]
+    fn positive_indices_come_first_then_the_tree_order() {
+        let mut shell = HeadlessShell::with_widgets(200, 50, buttons);
+        shell.render();
+        let buttons = shell.widgets();
+        assert_eq!(tab_order(shell.world()), vec![buttons[2], buttons[1], buttons[0]]);
+    }
+
+    #[test]
+    fn tab_moves_the_focus_around() {
+        let mut shell = HeadlessShell::with_widgets(200, 50, buttons);
+        shell.render();
+        let buttons = shell.widgets();
+
+        let mut visited = Vec::new();
+        for _ in 0..4 {
+            press_key(&mut shell, orbclient::K_TAB, true);
+            visited.push(focused(&shell).unwrap());
+        }
+        assert_eq!(visited, vec![buttons[2], buttons[1], buttons[0], buttons[2]]);
+
+        press_key(&mut shell, orbclient::K_LEFT_SHIFT, true);
+        press_key(&mut shell, orbclient::K_TAB, true);
+        assert_eq!(focused(&shell), Some(buttons[0]));
+        press_key(&mut shell, orbclient::K_TAB, true);
+        assert_eq!(focused(&shell), Some(buttons[1]));
+    }
+
+    #[test]
+    fn mouse_down_moves_the_focus_to_the_clicked_widget() {
+        let mut shell = HeadlessShell::with_widgets(200, 50, buttons);
+        shell.render();
+        let buttons = shell.widgets();
+
+        // widgets out of the Tab order can still be clicked
+        click(&mut shell, 135, 15);
+        assert_eq!(focused(&shell), Some(buttons[3]));
+        // the label is not focusable, the button holding it is
+        click(&mut shell, 10, 10);
+        assert_eq!(focused(&shell), Some(buttons[0]));
+        // disabled buttons and the background take the focus away
+        click(&mut shell, 175, 15);
+        assert_eq!(focused(&shell), None);
+        click(&mut shell, 100, 40);
+        assert_eq!(focused(&shell), None);
+    }
+
+    #[test]
+    fn the_widgets_are_notified_of_the_focus_change() {
+        let mut shell = HeadlessShell::with_widgets(200, 50, buttons);
+        shell.render();
+        let buttons = shell.widgets();
+
+        set_focus(Some(buttons[0]), shell.world());
+        set_focus(Some(buttons[0]), shell.world());
+        set_focus(Some(buttons[1]), shell.world());
+        let mut queue = shell.world().write_resource::<EventQueue>();
+        let mut events = Vec::new();
+        while let Some(queued) = queue.pop() {
+            events.push((queued.target, queued.event));
+        }
+        assert_eq!(events, vec![
+            (Some(buttons[0]), WidgetEvent::FocusGained),
+            (Some(buttons[0]), WidgetEvent::FocusLost),
+            (Some(buttons[1]), WidgetEvent::FocusGained),
+        ]);
+    }
+}
@@ -0,0 +1,108 @@
+use specs::{Entity, World, WorldExt};
+use crate::{EntityTree, PositionComponent, RenderComponent, Shape, SizeComponent, WindowComponent, paint_order, shape_contains};
+use tiny_skia::Rect;
+
+/// Returns the topmost entity under the given window coordinate.
+///
+/// The widgets are tested in the reverse of the order the RenderingSystem paints them in,
+/// so the widget drawn on top of the others at the coordinate is hit.
+/// Entities without a PositionComponent and a SizeComponent cannot be hit, but their children can.
+/// When no widget contains the point but it lies within the window, the root is returned.
+pub fn hit_test(world: &World, x: i32, y: i32) -> Option<Entity> {
+    let root = world.read_resource::<EntityTree>().root()?;
+
+    let hit = paint_order(world).into_iter()
+        .rev()
+        .find(|widget| *widget != root && contains_point(world, *widget, x, y));
+    if hit.is_some() {
+        return hit;
+    }
+
+    let windows = world.read_storage::<WindowComponent>();
+    match windows.get(root) {
+        Some(window) if x >= 0 && y >= 0 && (x as u32) < window.width && (y as u32) < window.height => Some(root),
+        _ => None,
+    }
+}
+
+/// Tells whether the given window coordinate lies within the entity's shape, as it is painted.
+/// Rectangles are hit inside their bounds less the corners rounded by their border,
+/// circles within their radius around the center of their bounds.
+pub fn contains_point(world: &World, entity: Entity, x: i32, y: i32) -> bool {
+    let positions = world.read_storage::<PositionComponent>();
+    let sizes = world.read_storage::<SizeComponent>();
+    let renders = world.read_storage::<RenderComponent>();
+
+    let bounds = match (positions.get(entity), sizes.get(entity)) {
+        (Some(position), Some(size)) => Rect::from_xywh(position.x as f32, position.y as f32, size.width as f32, size.height as f32),
+        _ => None,
+    };
+    let bounds = match bounds {
+        Some(bounds) => bounds,
+        None => return false,
+    };
+    // the center of the pixel decides whether it is inside an antialiased edge
+    let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+    match renders.get(entity) {
+        Some(render) => shape_contains(render.shape, render.border.as_ref(), bounds, x, y),
+        None => shape_contains(Shape::Rectangle, None, bounds, x, y),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Border, HeadlessShell, Label, hit_test};
+    use orbclient::Color;
+    use specs::{Entity, World};
+
+    /// Builds widgets at fixed positions: two overlapping squares, the one built first appended last, and a rounded square.
+    fn overlapping_widgets(world: &mut World) -> Vec<Entity> {
+        let first = Label::new(String::new()).background(Color::rgb(255, 0, 0)).position(10, 10).size(50, 50).build(world);
+        let second = Label::new(String::new()).background(Color::rgb(0, 0, 255)).position(30, 30).size(50, 50).build(world);
+        let rounded = Label::new(String::new())
+            .background(Color::rgb(0, 255, 0))
+            .border(Border::new().color(Color::rgb(0, 255, 0)).radius(20.0).build())
+            .position(100, 10)
+            .size(60, 60)
+            .build(world);
+        vec![second, first, rounded]
+    }
+
+    #[test]
+    fn topmost_painted_widget_is_hit() {
+        let mut shell = HeadlessShell::with_widgets(200, 100, overlapping_widgets);
+        let pixel = shell.render().pixel(40, 40).unwrap();
+        let widgets = shell.widgets();
+        let (second, first) = (widgets[0], widgets[1]);
+
+        // the widget appended last is painted on top, whatever its entity id is
+        assert_eq!((pixel.red(), pixel.blue()), (255, 0));
+        assert_eq!(hit_test(shell.world(), 40, 40), Some(first));
+        assert_eq!(hit_test(shell.world(), 70, 70), Some(second));
+        assert_eq!(hit_test(shell.world(), 15, 15), Some(first));
+    }
+
+    #[test]
+    fn rounded_corners_are_not_hit() {
+        let mut shell = HeadlessShell::with_widgets(200, 100, overlapping_widgets);
+        shell.render();
+        let (background, rounded) = (shell.background(), shell.widgets()[2]);
+
+        assert_eq!(hit_test(shell.world(), 101, 11), Some(background));
+        assert_eq!(hit_test(shell.world(), 158, 68), Some(background));
+        assert_eq!(hit_test(shell.world(), 130, 11), Some(rounded));
+        assert_eq!(hit_test(shell.world(), 106, 16), Some(rounded));
+    }
+
+    #[test]
+    fn points_outside_of_the_window_miss() {
+        let mut shell = HeadlessShell::with_widgets(200, 100, overlapping_widgets);
+        shell.render();
+        let background = shell.background();
+
+        assert_eq!(hit_test(shell.world(), 190, 90), Some(background));
+        assert_eq!(hit_test(shell.world(), -1, 50), None);
+        assert_eq!(hit_test(shell.world(), 50, 100), None);
+        assert_eq!(hit_test(shell.world(), 500, 500), None);
+    }
+}
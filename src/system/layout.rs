@@ -1,14 +1,144 @@
-use specs::{World, WorldExt};
-use crate::{EntityTree, LayoutComponent};
+use specs::{Entity, World, WorldExt};
+use crate::{EntityTree, LayoutCache, LayoutComponent, PositionComponent, RelativePositionComponent, arrange_widget, measure_widget, padding_of};
 
+/// Measures and arranges the widget tree from the root,
+/// then derives the position of every placed widget in the window from its position in its parent.
+/// With a LayoutCache in the world, only the widgets affected by the changes since the last frame are laid out again.
 pub fn layout_system(world: &World) {
-    let layouts = world.read_storage::<LayoutComponent>();
+    let root = world.read_resource::<EntityTree>().root();
+
+    if let Some(root) = root {
+        if let Some(mut cache) = world.try_fetch_mut::<LayoutCache>() {
+            cache.update(world);
+        }
+
+        let window_constraints = world.read_storage::<LayoutComponent>()
+            .get(root)
+            .expect("Root widget LayoutComponent not found !")
+            .constraints;
+        let desired_child_size = measure_widget(root, &window_constraints, world);
+        arrange_widget(root, &desired_child_size, world);
+
+        // the positions change only when something has been arranged
+        let arranged = match world.try_fetch_mut::<LayoutCache>() {
+            Some(mut cache) => cache.take_arranged(),
+            None => true,
+        };
+        if arranged {
+            update_positions(root, PositionComponent::default(), world);
+        }
+    }
+}
+
+/// Sets the position of the descendants of a widget at the given position.
+/// Widgets not placed by a layout keep their position, and their children are placed relative to it.
+fn update_positions(widget: Entity, position: PositionComponent, world: &World) {
+    // the children are placed inside the padding
+    let padding = padding_of(widget, world);
+    let origin = PositionComponent { x: position.x.saturating_add(padding.left), y: position.y.saturating_add(padding.top) };
     let tree = world.read_resource::<EntityTree>();
+    let children = match tree.try_children_of(widget) {
+        Some(children) => children.filter_map(|node| tree.entitiy_of(node)).collect(),
+        None => vec![],
+    };
+    drop(tree);
+
+    for child in children {
+        let relative = world.read_storage::<RelativePositionComponent>().get(child).copied();
+        let mut positions = world.write_storage::<PositionComponent>();
+        let position = match relative {
+            Some(relative) => {
+                let position = PositionComponent { x: origin.x.saturating_add(relative.x), y: origin.y.saturating_add(relative.y) };
+                // a position written unchanged would still be reported as modified, even through get_mut()
+                let changed = match positions.get(child) {
+                    Some(current) => current.x != position.x || current.y != position.y,
+                    None => true,
+                };
+                if changed {
+                    positions.insert(child, position).expect("Cannot position a dead entity!");
+                }
+                position
+            }
+            None => positions.get(child).copied().unwrap_or(origin),
+        };
+        drop(positions);
+        update_positions(child, position, world);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, rc::Rc};
+    use crate::{Alignment, Center, GridItemComponent, GridLength, Grid, HBox, HeadlessShell, Label, LayoutCache, PositionComponent, SizeComponent, VBox, register_reader};
+    use specs::{Entity, World, WorldExt};
+
+    fn leaf(world: &mut World, width: usize, height: usize) -> Entity {
+        Label::new(String::new()).size(width, height).build(world)
+    }
+
+    fn bounds_of(shell: &HeadlessShell, widget: Entity) -> (u32, u32, usize, usize) {
+        let positions = shell.world().read_storage::<PositionComponent>();
+        let sizes = shell.world().read_storage::<SizeComponent>();
+        let (position, size) = (positions.get(widget).unwrap(), sizes.get(widget).unwrap());
+        (position.x, position.y, size.width, size.height)
+    }
+
+    /// Builds a Center holding a VBox of a leaf and an HBox, the HBox holding a leaf and a Grid with a leaf in its second column.
+    fn nested_ui(leaves: Rc<Cell<Option<(Entity, Entity, Entity)>>>) -> impl Fn(&mut World) -> Entity {
+        move |world| {
+            let top = leaf(world, 40, 20);
+            let side = leaf(world, 20, 30);
+            let cell = leaf(world, 10, 10);
+            let centered = GridItemComponent {
+                horizontal_alignment: Alignment::Center,
+                vertical_alignment: Alignment::Center,
+                ..GridItemComponent::new(0, 1)
+            };
+            let grid = Grid::new()
+                .column(GridLength::Fixed(30))
+                .column(GridLength::Fixed(30))
+                .row(GridLength::Fixed(30))
+                .child_with(cell, centered)
+                .build(world);
+            let row = HBox::new().spacing(5).child(side).child(grid).build(world);
+            let column = VBox::new().spacing(10).child(top).child(row).build(world);
+            leaves.set(Some((top, side, cell)));
+            Center::new().child(column).build(world)
+        }
+    }
+
+    #[test]
+    fn nested_widgets_are_placed_in_the_window() {
+        let leaves = Rc::new(Cell::new(None));
+        let mut shell = HeadlessShell::new(200, 200, nested_ui(leaves.clone()));
+        let (top, side, cell) = leaves.get().unwrap();
+
+        // the VBox is 85x60, centered in the window
+        for _ in 0..2 {
+            shell.render();
+            assert_eq!(bounds_of(&shell, top), (57, 70, 40, 20));
+            assert_eq!(bounds_of(&shell, side), (57, 100, 20, 30));
+            assert_eq!(bounds_of(&shell, cell), (57 + 20 + 5 + 30 + 10, 100 + 10, 10, 10));
+        }
+
+        // moving the VBox moves every descendant
+        shell.resize(300, 100);
+        shell.render();
+        assert_eq!(bounds_of(&shell, top), (107, 20, 40, 20));
+        assert_eq!(bounds_of(&shell, side), (107, 50, 20, 30));
+        assert_eq!(bounds_of(&shell, cell), (107 + 20 + 5 + 30 + 10, 50 + 10, 10, 10));
+    }
+
+    #[test]
+    fn positions_laid_out_again_unchanged_are_not_modified() {
+        let leaves = Rc::new(Cell::new(None));
+        let mut shell = HeadlessShell::new(200, 200, nested_ui(leaves));
+        shell.render();
 
-    if let Some(root) = tree.root() {
-        let root_layout = layouts.get(root).expect("Root widget LayoutComponent not found !");
-        let window_constraints = root_layout.constraints;
-        let desired_child_size = root_layout.object.measure(root, &window_constraints, world);
-        root_layout.object.arrange(root, &desired_child_size, world);
+        let mut reader = register_reader::<PositionComponent>(shell.world_mut());
+        shell.world_mut().write_resource::<LayoutCache>().invalidate();
+        shell.render();
+        let positions = shell.world().read_storage::<PositionComponent>();
+        assert_eq!(positions.channel().read(&mut reader).count(), 0);
     }
-}
\ No newline at end of file
+}
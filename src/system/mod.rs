@@ -1,5 +1,13 @@
+mod damage;
+mod event;
+mod focus;
+mod hit_test;
 mod layout;
 mod render;
 
+pub use self::damage::*;
+pub use self::event::*;
+pub use self::focus::*;
+pub use self::hit_test::*;
 pub use self::layout::{layout_system};
 pub use self::render::*;
\ No newline at end of file
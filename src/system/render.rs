@@ -1,75 +1,38 @@
-use crate::{EntityTree, PositionComponent, RenderComponent, Shape, SizeComponent, TextComponent, Window, WindowComponent};
-use orbclient::Renderer;
-use rusttype::{OutlineBuilder, Point, PositionedGlyph, Scale, point};
-use specs::{Join, ReadStorage, System, World, WorldExt};
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use crate::{Border, CornerRadius, DamageTracker, EntityTree, FocusManager, FontRegistry, GlyphCache, ImageComponent, ImageFit, ImageSampling, PaddingComponent, Paragraph, PositionComponent, RenderBackend, RenderComponent, Scheduler, Shape, SizeComponent, TextBoxComponent, TextComponent, WindowComponent, caret_offsets, line_height};
+use specs::{Entity, ReadStorage, System, World, WorldExt};
 use tiny_skia::*;
 
-pub const DEFAULT_FONT_FAMILY: &'static[u8] = include_bytes!("../../assets/fonts/Roboto-Medium.ttf");
-
-struct GlyphTracer {
-    path_builder: PathBuilder,
-    position: Point<f32>
-}
-
-impl GlyphTracer {
-    #[inline(always)]
-    fn map_point(&self, x: f32, y: f32) -> (f32, f32) {
-        (self.position.x + x, self.position.y + y)
-    }
-}
-
-impl OutlineBuilder for GlyphTracer {
-    fn move_to(&mut self, x: f32, y: f32) {
-        let (x, y) = self.map_point(x, y);
-        self.path_builder.move_to(x, y);
-    }
-
-    fn line_to(&mut self, x: f32, y: f32) {
-        let (x, y) = self.map_point(x, y);
-        self.path_builder.line_to(x, y);
-    }
-
-    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
-        let (x, y) = self.map_point(x, y);
-        let (x1, y1) = self.map_point(x1, y1);
-        self.path_builder.quad_to(x1, y1, x, y);
-    }
-
-    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
-        let (x, y) = self.map_point(x, y);
-        let (x1, y1) = self.map_point(x1, y1);
-        let (x2, y2) = self.map_point(x2, y2);
-        self.path_builder.cubic_to(x1, y1, x2, y2, x, y);
-    }
-
-    fn close(&mut self) {
-        self.path_builder.close();
-    }
-}
-
 /// Renders the visual representation of entities to the screen that has the following composition:
 /// * PositionComponent
 /// * RenderComponent
 /// * SizeComponent
-pub struct RenderingSystem<'w> {
-    window: Rc<RefCell<Window>>,
+///
+/// The widgets of the EntityTree are painted one after the other, parents before their children
+/// and earlier siblings before later ones, so hit_test() finds the widget drawn on top.
+///
+/// When the world has a DamageTracker, only the damaged areas of the last frame are repainted,
+/// and no frame is handed over when nothing has changed.
+/// The finished frames are handed over to a RenderBackend.
+pub struct RenderingSystem<'w, B: RenderBackend> {
+    backend: B,
     world: &'w World,
-    fonts: HashMap<String, rusttype::Font<'static>>
 }
 
-impl<'w> RenderingSystem<'w> {
-    pub fn new(window: Rc<RefCell<Window>>, world: &World) -> RenderingSystem {
-        let mut fonts: HashMap<String, rusttype::Font> = HashMap::new();
-        let result = rusttype::Font::try_from_bytes(DEFAULT_FONT_FAMILY);
-        if let Some(f) = result {
-            fonts.insert("Roboto-Medium".to_string(), f);
-        }
-        RenderingSystem { window, world, fonts }
+impl<'w, B: RenderBackend> RenderingSystem<'w, B> {
+    pub fn new(backend: B, world: &'w World) -> RenderingSystem<'w, B> {
+        RenderingSystem { backend, world }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
     }
 }
 
-impl<'s, 'w> System<'s> for RenderingSystem<'w> {
+impl<'s, 'w, B: RenderBackend> System<'s> for RenderingSystem<'w, B> {
     type SystemData = (
         ReadStorage<'s, PositionComponent>,
         ReadStorage<'s, RenderComponent>,
@@ -78,54 +41,49 @@ impl<'s, 'w> System<'s> for RenderingSystem<'w> {
 
     fn run(&mut self, (pos, render, size): Self::SystemData) {
         let (width, height) = self.get_window_comp_size();
-        let mut pixmap = Pixmap::new(width, height).unwrap();
+        let mut tracker = self.world.try_fetch_mut::<DamageTracker>();
+        let (frame, areas) = match tracker.as_mut() {
+            Some(tracker) => {
+                let areas = tracker.collect(self.world, width, height);
+                (tracker.take_frame(width, height), areas)
+            }
+            // without a tracker every frame is painted from scratch
+            None => (Pixmap::new(width, height), Rect::from_xywh(0.0, 0.0, width as f32, height as f32).into_iter().collect()),
+        };
+        let mut pixmap = match frame {
+            Some(pixmap) => pixmap,
+            None => return,
+        };
+        if areas.is_empty() {
+            if let Some(tracker) = tracker.as_mut() {
+                tracker.keep_frame(pixmap);
+            }
+            return;
+        }
+        let repaint = Repaint::new(&mut pixmap, areas, tracker.as_deref());
 
-        for (pos, render, size) in (&pos, &render, &size).join() {
-            match render.shape {
-                Shape::Circle(_radius) => {
-                    todo!("todo: circle shape rendering");
-                }
-                Shape::Rectangle => {
-                    let rect = Rect::from_xywh(
-                        pos.x as f32,
-                        pos.y as f32,
-                        size.width as f32,
-                        size.height as f32,
-                    )
-                    .unwrap();
-                    let mut brush = Paint::default();
-                    brush.anti_alias = true;
-                    
-                    if let Some(bg_color) = render.background {
-                        let tinyskia_color = tiny_skia::Color::from_rgba8(bg_color.r(), bg_color.g(), bg_color.b(), bg_color.a());
-                        brush.set_color(tinyskia_color);
-                    } else {
-                        // transparent background
-                        brush.set_color(tiny_skia::Color::from_rgba8(0, 0, 0, 0,));
-                    }
-                    pixmap.fill_rect(rect, &brush, Transform::identity(), None);
-
-                    // draw border around widget rectangle
-                    // TODO: implement rounded border
-                    if let Some(border) = render.border {
-                        let path = PathBuilder::from_rect(rect);
-                        let tinyskia_color = tiny_skia::Color::from_rgba8(border.color.r(), border.color.g(), border.color.b(), border.color.a());
-                        brush.set_color(tinyskia_color);
-                        let mut stroke = Stroke::default();
-                        stroke.width = border.top;
-                        pixmap.stroke_path(&path, &brush, &stroke, Transform::identity(), None);
-                    }
-                }
+        // each widget is painted whole before the widgets on top of it
+        for widget in paint_order(self.world) {
+            if !repaint.covers(widget) {
+                continue;
+            }
+            if let (Some(pos), Some(render), Some(size)) = (pos.get(widget), render.get(widget), size.get(widget)) {
+                self.render_shape(&mut pixmap, widget, pos, render, size, &repaint);
             }
+            self.render_text_box_selection(&mut pixmap, widget, &repaint);
+            self.render_text(&mut pixmap, widget, &repaint);
+            self.render_text_box_caret(&mut pixmap, widget, &repaint);
         }
-        
-        self.render_text(&mut pixmap);
+        self.render_focus_ring(&mut pixmap, &repaint);
 
-        self.swap_frame_buffer(pixmap.data_mut());
+        self.backend.present(&mut pixmap);
+        if let Some(tracker) = tracker.as_mut() {
+            tracker.keep_frame(pixmap);
+        }
     }
 }
 
-impl<'w> RenderingSystem<'w> {
+impl<'w, B: RenderBackend> RenderingSystem<'w, B> {
     fn get_window_comp_size(&self) -> (u32, u32) {
         let tree = self.world.fetch::<EntityTree>();
         let window = tree.root()
@@ -135,72 +93,711 @@ impl<'w> RenderingSystem<'w> {
         (window_component.width, window_component.height)
     }
 
-    fn render_text(&self, pixmap: &mut Pixmap) {
+    /// Draws the background, the image and the border of a widget.
+    fn render_shape(&self, pixmap: &mut Pixmap, entity: Entity, pos: &PositionComponent, render: &RenderComponent, size: &SizeComponent, repaint: &Repaint) {
+        let images = self.world.read_storage::<ImageComponent>();
+        let paddings = self.world.read_storage::<PaddingComponent>();
+        let (image, padding) = (images.get(entity), paddings.get(entity));
+        let rect = Rect::from_xywh(
+            pos.x as f32,
+            pos.y as f32,
+            size.width as f32,
+            size.height as f32,
+        );
+        // widgets with zero size have nothing to draw
+        let outline = match rect.and_then(|rect| Outline::new(render.shape, rect, render.border.as_ref())) {
+            Some(outline) => outline,
+            None => return,
+        };
+        let mut brush = Paint::default();
+        brush.anti_alias = true;
+
+        if let Some(bg_color) = render.background {
+            if let Some(path) = outline.path() {
+                let tinyskia_color = tiny_skia::Color::from_rgba8(bg_color.r(), bg_color.g(), bg_color.b(), bg_color.a());
+                brush.set_color(tinyskia_color);
+                pixmap.fill_path(&path, &brush, FillRule::Winding, Transform::identity(), repaint.clip());
+            }
+        }
+
+        if let Some(image) = image {
+            // the image is fitted inside the border and the padding
+            let area = match (render.border, padding) {
+                (None, None) => Some(outline),
+                (border, padding) => {
+                    let mut inset = border.unwrap_or(Border { left: 0.0, top: 0.0, right: 0.0, bottom: 0.0, ..Border::default() });
+                    if let Some(padding) = padding {
+                        inset.left += padding.padding.left as f32;
+                        inset.top += padding.padding.top as f32;
+                        inset.right += padding.padding.right as f32;
+                        inset.bottom += padding.padding.bottom as f32;
+                    }
+                    outline.inset(&inset)
+                }
+            };
+            if let Some(area) = area {
+                render_image(pixmap, image, &area, repaint);
+            }
+        }
+
+        // the border is the area between the outline and the outline shrunk by the border widths,
+        // so it stays within the bounds of the widget and each side can have its own width
+        if let Some(border) = render.border {
+            let mut pb = PathBuilder::new();
+            outline.push_to(&mut pb);
+            if let Some(inner) = outline.inset(&border) {
+                inner.push_to(&mut pb);
+            }
+            if let Some(path) = pb.finish() {
+                let tinyskia_color = tiny_skia::Color::from_rgba8(border.color.r(), border.color.g(), border.color.b(), border.color.a());
+                brush.set_color(tinyskia_color);
+                pixmap.fill_path(&path, &brush, FillRule::EvenOdd, Transform::identity(), repaint.clip());
+            }
+        }
+    }
+
+    fn render_text(&self, pixmap: &mut Pixmap, entity: Entity, repaint: &Repaint) {
         let positions = self.world.read_storage::<PositionComponent>();
         let texts = self.world.read_storage::<TextComponent>();
+        let sizes = self.world.read_storage::<SizeComponent>();
+        let paddings = self.world.read_storage::<PaddingComponent>();
+        let (position, text_comp) = match (positions.get(entity), texts.get(entity)) {
+            (Some(position), Some(text_comp)) => (position, text_comp),
+            _ => return,
+        };
+        let (size, padding) = (sizes.get(entity), paddings.get(entity));
 
-        for (position, text_comp) in (&positions, &texts).join() {
-            // text rendering code is based on orbtk
-            // https://github.com/redox-os/orbtk/blob/develop/orbtk_tinyskia/src/tinyskia/font.rs
-            if let Some(font) = self.fonts.get(&text_comp.font_family) {
-                let scale = Scale::uniform(text_comp.font_size as f32);
-
-                // The origin of a line of text is at the baseline (roughly where non-descending letters sit).
-                // We don't want to clip the text, so we shift it down with an offset when laying it out.
-                // v_metrics.ascent is the distance between the baseline and the highest edge of any glyph in
-                // the font. That's enough to guarantee that there's no clipping.
-                let v_metrics = font.v_metrics(scale);
-                let offset = point(0.0, v_metrics.ascent);
-                let glyphs: Vec<PositionedGlyph> = font.layout(text_comp.text.as_str(), scale, offset).collect();
-                let mut glyph_tracer = GlyphTracer {
-                    path_builder: PathBuilder::new(),
-                    position: point(0.0, 0.0)
-                };
-    
-                for g in glyphs.iter() {
-                    let mut gpos = match g.pixel_bounding_box() {
-                        Some(bbox) => rusttype::point(bbox.min.x as f32, bbox.min.y as f32),
-                        None => {
-                            continue;
-                        }
-                    };
-                    gpos.x += position.x as f32;
-                    gpos.y += position.y as f32;
-                    glyph_tracer.position = gpos;
-                    g.build_outline(&mut glyph_tracer);
+        // the text of a TextBox is scrolled to its caret and cut off at its padding
+        let text_boxes = self.world.read_storage::<TextBoxComponent>();
+        let (scroll, text_box_clip) = match text_boxes.get(entity) {
+            Some(text_box) => match repaint.clip_to(pixmap, text_box_viewport(position, size, padding)) {
+                Some(clip) => (text_box.scroll(), Some(clip)),
+                None => return,
+            },
+            None => (0.0, None),
+        };
+        let clip = text_box_clip.as_ref().or_else(|| repaint.clip());
+
+        let mut fonts = self.world.write_resource::<FontRegistry>();
+        let mut glyph_cache = self.world.write_resource::<GlyphCache>();
+        glyph_cache.sync(&fonts);
+
+        // text rendering code is based on orbtk
+        // https://github.com/redox-os/orbtk/blob/develop/orbtk_tinyskia/src/tinyskia/font.rs
+        let (x, y, width, height) = text_area(position, size, padding);
+        let x = x - scroll;
+        let paragraph = Paragraph::layout(&mut fonts, text_comp, width, height);
+
+        for line in paragraph.lines.iter() {
+            // the highlights go below the glyphs of the neighbouring segments too
+            for segment in line.segments.iter() {
+                if let Some(background) = paragraph.styles[segment.style].background {
+                    if let Some(rect) = Rect::from_xywh(x + segment.left, y + line.top, segment.right - segment.left, line.height) {
+                        pixmap.fill_rect(rect, &paint(background), Transform::identity(), clip);
+                    }
                 }
-    
-                let mut brush = Paint::default();
+            }
+
+            for segment in line.segments.iter() {
+                let mut brush = paint(paragraph.styles[segment.style].color);
                 brush.anti_alias = true;
-                let text_color = tiny_skia::Color::from_rgba8(text_comp.text_color.r(), text_comp.text_color.g(), text_comp.text_color.b(), text_comp.text_color.a());
-                brush.set_color(text_color);
-    
-                if let Some(path) = glyph_tracer.path_builder.finish() {
-                    pixmap.fill_path(&path, &brush, FillRule::Winding, Transform::identity(), None);
-                }   
+
+                // the cached outlines are relative to the pixel of the glyph's origin
+                for glyph in line.glyphs[segment.glyphs.clone()].iter() {
+                    if let Some((path, pixel)) = glyph_cache.outline(glyph) {
+                        let transform = Transform::from_translate(pixel.x + x, pixel.y + y);
+                        pixmap.fill_path(path, &brush, FillRule::Winding, transform, clip);
+                    }
+                }
+                for (top, thickness) in segment.decorations.iter() {
+                    if let Some(rect) = Rect::from_xywh(x + segment.left, y + top, segment.right - segment.left, *thickness) {
+                        pixmap.fill_rect(rect, &brush, Transform::identity(), clip);
+                    }
+                }
             }
-            // else {
-            // TODO: load fonts lazily, cross-platform font loader ???
-            //}
         }
     }
 
-    fn swap_frame_buffer(&mut self, bytes: &mut [u8]) {
-        // frame buffer flipping code is borrowed from orbtk
-        // https://github.com/redox-os/orbtk/blob/develop/orbtk_orbclient/src/orbclient/window.rs
-        let len = bytes.len() / std::mem::size_of::<orbclient::Color>();
-        let color_data = unsafe {
-            std::slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut orbclient::Color, len)
+    /// Highlights the selected part of the text of a TextBox.
+    fn render_text_box_selection(&self, pixmap: &mut Pixmap, entity: Entity, repaint: &Repaint) {
+        let positions = self.world.read_storage::<PositionComponent>();
+        let texts = self.world.read_storage::<TextComponent>();
+        let text_boxes = self.world.read_storage::<TextBoxComponent>();
+        let paddings = self.world.read_storage::<PaddingComponent>();
+        let (position, text_comp, text_box) = match (positions.get(entity), texts.get(entity), text_boxes.get(entity)) {
+            (Some(position), Some(text_comp), Some(text_box)) => (position, text_comp, text_box),
+            _ => return,
+        };
+        let (start, end) = text_box.selection();
+        if start == end {
+            return;
+        }
+        let padding = paddings.get(entity);
+        let clip = match repaint.clip_to(pixmap, text_box_viewport(position, self.world.read_storage::<SizeComponent>().get(entity), padding)) {
+            Some(clip) => clip,
+            None => return,
+        };
+        let mut fonts = self.world.write_resource::<FontRegistry>();
+        let (x, y, _, _) = text_area(position, None, padding);
+        let x = x - text_box.scroll();
+        let offsets = caret_offsets(&mut fonts, text_comp);
+        let font = fonts.get(&text_comp.font_family, text_comp.font_weight, text_comp.font_style);
+        let left = x + offsets[start.min(offsets.len() - 1)];
+        let right = x + offsets[end.min(offsets.len() - 1)];
+        let height = line_height(font, text_comp.font_size);
+
+        if let Some(rect) = Rect::from_xywh(left, y, right - left, height) {
+            let mut brush = Paint::default();
+            let color = text_box.selection_color;
+            brush.set_color(tiny_skia::Color::from_rgba8(color.r(), color.g(), color.b(), color.a()));
+            pixmap.fill_rect(rect, &brush, Transform::identity(), Some(&clip));
+        }
+    }
+
+    /// Draws the blinking caret of a TextBox owning the keyboard.
+    fn render_text_box_caret(&self, pixmap: &mut Pixmap, entity: Entity, repaint: &Repaint) {
+        let positions = self.world.read_storage::<PositionComponent>();
+        let texts = self.world.read_storage::<TextComponent>();
+        let text_boxes = self.world.read_storage::<TextBoxComponent>();
+        let paddings = self.world.read_storage::<PaddingComponent>();
+        let (position, text_comp, text_box) = match (positions.get(entity), texts.get(entity), text_boxes.get(entity)) {
+            (Some(position), Some(text_comp), Some(text_box)) => (position, text_comp, text_box),
+            _ => return,
         };
+        let focused = self.world.read_resource::<FocusManager>().focused();
+        if focused != Some(entity) || !text_box.caret_visible(self.world.read_resource::<Scheduler>().now()) {
+            return;
+        }
+        let padding = paddings.get(entity);
+        let clip = match repaint.clip_to(pixmap, text_box_viewport(position, self.world.read_storage::<SizeComponent>().get(entity), padding)) {
+            Some(clip) => clip,
+            None => return,
+        };
+        let mut fonts = self.world.write_resource::<FontRegistry>();
+        let (x, y, _, _) = text_area(position, None, padding);
+        let offsets = caret_offsets(&mut fonts, text_comp);
+        let font = fonts.get(&text_comp.font_family, text_comp.font_weight, text_comp.font_style);
+        let x = x - text_box.scroll() + offsets[text_box.caret.min(offsets.len() - 1)];
+        let height = line_height(font, text_comp.font_size);
 
-        if color_data.len() == self.window.borrow().inner().data().len() {
-            self.window
-                .borrow_mut()
-                .inner_mut()
-                .data_mut()
-                .clone_from_slice(color_data);
+        if let Some(rect) = Rect::from_xywh(x, y, 1.0, height) {
+            let mut brush = Paint::default();
+            let color = text_comp.text_color;
+            brush.set_color(tiny_skia::Color::from_rgba8(color.r(), color.g(), color.b(), color.a()));
+            pixmap.fill_rect(rect, &brush, Transform::identity(), Some(&clip));
         }
+    }
 
-        self.window.borrow_mut().inner_mut().sync();
+    /// Draws a ring just outside the bounds of the focused widget.
+    fn render_focus_ring(&self, pixmap: &mut Pixmap, repaint: &Repaint) {
+        let focus = *self.world.read_resource::<FocusManager>();
+        let focused = match focus.focused() {
+            Some(focused) => focused,
+            None => return,
+        };
+        let positions = self.world.read_storage::<PositionComponent>();
+        let sizes = self.world.read_storage::<SizeComponent>();
+
+        if let (Some(position), Some(size)) = (positions.get(focused), sizes.get(focused)) {
+            // the stroke is centered on the path, so the ring is moved out by its half width
+            let half = focus.ring_width / 2.0;
+            let rect = Rect::from_xywh(
+                position.x as f32 - half,
+                position.y as f32 - half,
+                size.width as f32 + focus.ring_width,
+                size.height as f32 + focus.ring_width,
+            );
+            if let Some(rect) = rect {
+                let path = PathBuilder::from_rect(rect);
+                let mut brush = Paint::default();
+                brush.anti_alias = true;
+                let color = focus.ring_color;
+                brush.set_color(tiny_skia::Color::from_rgba8(color.r(), color.g(), color.b(), color.a()));
+                let mut stroke = Stroke::default();
+                stroke.width = focus.ring_width;
+                pixmap.stroke_path(&path, &brush, &stroke, Transform::identity(), repaint.clip());
+            }
+        }
+    }
+}
+
+/// Draws an image fitted into the area, the parts outside of the area are cut off.
+fn render_image(pixmap: &mut Pixmap, image: &ImageComponent, area: &Outline, repaint: &Repaint) {
+    let bounds = area.bounds();
+    let (width, height) = (image.image.width() as f32, image.image.height() as f32);
+    let (scale_x, scale_y) = match image.fit {
+        ImageFit::Stretch => (bounds.width() / width, bounds.height() / height),
+        ImageFit::Contain => {
+            let scale = (bounds.width() / width).min(bounds.height() / height);
+            (scale, scale)
+        }
+        ImageFit::Cover => {
+            let scale = (bounds.width() / width).max(bounds.height() / height);
+            (scale, scale)
+        }
+        ImageFit::None | ImageFit::Tile => (1.0, 1.0),
+    };
+    let (left, top) = match image.fit {
+        ImageFit::Tile => (bounds.left(), bounds.top()),
+        _ => (
+            bounds.left() + (bounds.width() - width * scale_x) / 2.0,
+            bounds.top() + (bounds.height() - height * scale_y) / 2.0,
+        ),
+    };
+
+    // the pattern repeats the edges of the image beyond its bounds, unless it is tiled, so only the image is filled
+    let target = match image.fit {
+        ImageFit::Tile => Some(bounds),
+        _ => Rect::from_ltrb(
+            bounds.left().max(left),
+            bounds.top().max(top),
+            bounds.right().min(left + width * scale_x),
+            bounds.bottom().min(top + height * scale_y),
+        ),
+    };
+    let target = match target {
+        Some(target) => target,
+        None => return,
+    };
+
+    let mut brush = Paint::default();
+    brush.shader = Pattern::new(
+        image.image.as_ref().as_ref(),
+        if image.fit == ImageFit::Tile { SpreadMode::Repeat } else { SpreadMode::Pad },
+        match image.sampling {
+            ImageSampling::Smooth => FilterQuality::Bicubic,
+            ImageSampling::Nearest => FilterQuality::Nearest,
+        },
+        1.0,
+        Transform::from_row(scale_x, 0.0, 0.0, scale_y, left, top),
+    );
+
+    // the repainted areas are cut out of the target instead of clipping,
+    // as a clip mask cannot be combined with the outline without changing the antialiased pixels
+    let mut clip = None;
+    if !area.is_rect() {
+        let mut mask = ClipMask::new();
+        match area.path().and_then(|path| mask.set_path(pixmap.width(), pixmap.height(), &path, FillRule::Winding, true)) {
+            Some(()) => clip = Some(mask),
+            None => return,
+        }
+    }
+    for repainted in repaint.areas.iter() {
+        if let Some(target) = intersection(&target, repainted) {
+            pixmap.fill_rect(target, &brush, Transform::identity(), clip.as_ref());
+        }
+    }
+}
+
+/// Returns a brush painting with a solid color.
+fn paint(color: orbclient::Color) -> Paint<'static> {
+    let mut brush = Paint::default();
+    brush.set_color(tiny_skia::Color::from_rgba8(color.r(), color.g(), color.b(), color.a()));
+    brush
+}
+
+/// The parts of the frame repainted by a run of the RenderingSystem.
+struct Repaint<'t> {
+    /// Non-overlapping areas with whole pixel bounds.
+    areas: Vec<Rect>,
+    /// Cuts the areas out of the frame, None when the whole frame is repainted.
+    clip: Option<ClipMask>,
+    tracker: Option<&'t DamageTracker>,
+}
+
+impl<'t> Repaint<'t> {
+    /// Clears the areas of the frame.
+    fn new(pixmap: &mut Pixmap, areas: Vec<Rect>, tracker: Option<&'t DamageTracker>) -> Repaint<'t> {
+        let full = areas.iter().any(|area| area.width() >= pixmap.width() as f32 && area.height() >= pixmap.height() as f32);
+        if full {
+            pixmap.fill(tiny_skia::Color::TRANSPARENT);
+            return Repaint { areas, clip: None, tracker };
+        }
+
+        let mut eraser = Paint::default();
+        eraser.blend_mode = BlendMode::Clear;
+        eraser.anti_alias = false;
+        let mut pb = PathBuilder::new();
+        for area in areas.iter() {
+            pixmap.fill_rect(*area, &eraser, Transform::identity(), None);
+            pb.push_rect(area.left(), area.top(), area.width(), area.height());
+        }
+        let mut clip = ClipMask::new();
+        let clip = pb.finish()
+            .and_then(|path| clip.set_path(pixmap.width(), pixmap.height(), &path, FillRule::Winding, false))
+            .map(|_| clip);
+        Repaint { areas, clip, tracker }
+    }
+
+    /// Tells whether the widget has to be drawn, i.e. whether it may cover any of the repainted areas.
+    fn covers(&self, entity: Entity) -> bool {
+        match self.tracker {
+            Some(tracker) if self.clip.is_some() => self.areas.iter().any(|area| tracker.overlaps(entity, area)),
+            _ => true,
+        }
+    }
+
+    fn clip(&self) -> Option<&ClipMask> {
+        self.clip.as_ref()
+    }
+
+    /// Returns a mask cutting the repainted parts of the area out of the frame,
+    /// None when no part of the area is repainted.
+    fn clip_to(&self, pixmap: &Pixmap, area: Option<Rect>) -> Option<ClipMask> {
+        let area = area?;
+        let mut pb = PathBuilder::new();
+        for repainted in self.areas.iter().filter_map(|repainted| intersection(repainted, &area)) {
+            pb.push_rect(repainted.left(), repainted.top(), repainted.width(), repainted.height());
+        }
+        let mut clip = ClipMask::new();
+        clip.set_path(pixmap.width(), pixmap.height(), &pb.finish()?, FillRule::Winding, false)?;
+        Some(clip)
+    }
+}
+
+fn intersection(a: &Rect, b: &Rect) -> Option<Rect> {
+    Rect::from_ltrb(a.left().max(b.left()), a.top().max(b.top()), a.right().min(b.right()), a.bottom().min(b.bottom()))
+}
+
+/// Returns the widgets of the EntityTree in the order they are painted: every widget is painted
+/// before its children, and its children before its later siblings. Widgets outside of the tree are not painted.
+pub(crate) fn paint_order(world: &World) -> Vec<Entity> {
+    let tree = world.read_resource::<EntityTree>();
+    if tree.root().is_none() {
+        return vec![];
+    }
+    tree.children().filter_map(|node| tree.entitiy_of(node)).collect()
+}
+
+/// Returns the top left corner and the size of the area the text of a widget is laid out in: the bounds of the widget
+/// inside its padding. Widgets without a size do not limit their text.
+pub(crate) fn text_area(position: &PositionComponent, size: Option<&SizeComponent>, padding: Option<&PaddingComponent>) -> (f32, f32, Option<f32>, Option<f32>) {
+    let padding = padding.map(|padding| padding.padding).unwrap_or_default();
+    let (width, height) = match size {
+        Some(size) => (
+            Some(size.width.saturating_sub(padding.horizontal() as usize) as f32),
+            Some(size.height.saturating_sub(padding.vertical() as usize) as f32),
+        ),
+        None => (None, None),
+    };
+    (position.x.saturating_add(padding.left) as f32, position.y.saturating_add(padding.top) as f32, width, height)
+}
+
+/// Returns the part of a TextBox its text, selection and caret are visible in: its bounds inside the padding.
+pub(crate) fn text_box_viewport(position: &PositionComponent, size: Option<&SizeComponent>, padding: Option<&PaddingComponent>) -> Option<Rect> {
+    match text_area(position, size, padding) {
+        (x, y, Some(width), Some(height)) => Rect::from_xywh(x, y, width, height),
+        _ => None,
+    }
+}
+
+/// Returns the bounds of the outline of a widget, which may go beyond the bounds of the widget.
+pub(crate) fn shape_bounds(render: &RenderComponent, bounds: Rect) -> Option<Rect> {
+    Outline::new(render.shape, bounds, render.border.as_ref()).map(|outline| outline.bounds())
+}
+
+/// Tells whether a point of the window lies within the outline of a widget with the given bounds.
+pub(crate) fn shape_contains(shape: Shape, border: Option<&Border>, bounds: Rect, x: f32, y: f32) -> bool {
+    match Outline::new(shape, bounds, border) {
+        Some(outline) => outline.contains(x, y),
+        None => false,
+    }
+}
+
+/// Tells whether a point given relative to the center of an ellipse lies within the ellipse.
+fn inside_ellipse(dx: f32, dy: f32, rx: f32, ry: f32) -> bool {
+    if rx <= 0.0 || ry <= 0.0 {
+        return false;
+    }
+    (dx * dx) / (rx * rx) + (dy * dy) / (ry * ry) <= 1.0
+}
+
+/// The magic constant for approximating a quarter of an ellipse with a cubic bezier curve.
+const KAPPA: f32 = 0.552_284_8;
+
+/// The outline of a widget resolved to concrete geometry.
+#[derive(Copy, Clone, Debug)]
+enum Outline {
+    Ellipse(Rect),
+    /// A rectangle with elliptic corners given as (horizontal, vertical) radii, clockwise from the top left corner.
+    RoundedRect(Rect, [(f32, f32); 4]),
+}
+
+impl Outline {
+    /// Fits the shape into the given bounds.
+    /// Plain rectangles get their corners rounded by the radii of their border.
+    fn new(shape: Shape, bounds: Rect, border: Option<&Border>) -> Option<Outline> {
+        let circular = |radius: CornerRadius| [
+            (radius.top_left, radius.top_left),
+            (radius.top_right, radius.top_right),
+            (radius.bottom_right, radius.bottom_right),
+            (radius.bottom_left, radius.bottom_left),
+        ];
+
+        match shape {
+            Shape::Circle(radius) => {
+                let radius = if radius > 0.0 {
+                    radius as f32
+                } else {
+                    bounds.width().min(bounds.height()) / 2.0
+                };
+                let cx = bounds.left() + bounds.width() / 2.0;
+                let cy = bounds.top() + bounds.height() / 2.0;
+                Rect::from_xywh(cx - radius, cy - radius, radius * 2.0, radius * 2.0).map(Outline::Ellipse)
+            }
+            Shape::Ellipse => Some(Outline::Ellipse(bounds)),
+            Shape::Rectangle => {
+                let radius = border.map(|border| border.radii()).unwrap_or_default();
+                Some(Outline::RoundedRect(bounds, circular(radius)))
+            }
+            Shape::RoundedRect(radius) => Some(Outline::RoundedRect(bounds, circular(CornerRadius::uniform(radius as f32)))),
+        }
+    }
+
+    /// Shrinks the outline by the width of each side of the border.
+    /// Returns None when the border covers the whole outline.
+    fn inset(&self, border: &Border) -> Option<Outline> {
+        let shrink = |rect: &Rect| Rect::from_ltrb(
+            rect.left() + border.left,
+            rect.top() + border.top,
+            rect.right() - border.right,
+            rect.bottom() - border.bottom,
+        );
+
+        match self {
+            Outline::Ellipse(rect) => shrink(rect).map(Outline::Ellipse),
+            Outline::RoundedRect(rect, [top_left, top_right, bottom_right, bottom_left]) => {
+                let radii = [
+                    ((top_left.0 - border.left).max(0.0), (top_left.1 - border.top).max(0.0)),
+                    ((top_right.0 - border.right).max(0.0), (top_right.1 - border.top).max(0.0)),
+                    ((bottom_right.0 - border.right).max(0.0), (bottom_right.1 - border.bottom).max(0.0)),
+                    ((bottom_left.0 - border.left).max(0.0), (bottom_left.1 - border.bottom).max(0.0)),
+                ];
+                shrink(rect).map(|rect| Outline::RoundedRect(rect, radii))
+            }
+        }
+    }
+
+    /// Returns the rectangle the outline is inscribed in.
+    fn bounds(&self) -> Rect {
+        match self {
+            Outline::Ellipse(rect) | Outline::RoundedRect(rect, _) => *rect,
+        }
+    }
+
+    /// Tells whether the outline is a rectangle without rounded corners.
+    fn is_rect(&self) -> bool {
+        match self {
+            Outline::Ellipse(_) => false,
+            Outline::RoundedRect(_, radii) => radii.iter().all(|(x, y)| *x <= 0.0 || *y <= 0.0),
+        }
+    }
+
+    /// Tells whether a point lies within the outline, rounded corners included.
+    fn contains(&self, x: f32, y: f32) -> bool {
+        let bounds = self.bounds();
+        if x < bounds.left() || y < bounds.top() || x >= bounds.right() || y >= bounds.bottom() {
+            return false;
+        }
+        match self {
+            Outline::Ellipse(rect) => {
+                let (rx, ry) = (rect.width() / 2.0, rect.height() / 2.0);
+                inside_ellipse(x - rect.left() - rx, y - rect.top() - ry, rx, ry)
+            }
+            Outline::RoundedRect(rect, radii) => {
+                let (left, top, right, bottom) = (rect.left(), rect.top(), rect.right(), rect.bottom());
+                let [tl, tr, br, bl] = fit_radii(rect, radii);
+                // only the points within the square of a rounded corner can be outside
+                let corners = [
+                    (x < left + tl.0 && y < top + tl.1, left + tl.0, top + tl.1, tl),
+                    (x > right - tr.0 && y < top + tr.1, right - tr.0, top + tr.1, tr),
+                    (x > right - br.0 && y > bottom - br.1, right - br.0, bottom - br.1, br),
+                    (x < left + bl.0 && y > bottom - bl.1, left + bl.0, bottom - bl.1, bl),
+                ];
+                corners.iter()
+                    .filter(|(within, _, _, _)| *within)
+                    .all(|(_, cx, cy, (rx, ry))| inside_ellipse(x - cx, y - cy, *rx, *ry))
+            }
+        }
+    }
+
+    fn path(&self) -> Option<Path> {
+        let mut pb = PathBuilder::new();
+        self.push_to(&mut pb);
+        pb.finish()
+    }
+
+    /// Appends the outline to the path builder as a closed contour.
+    fn push_to(&self, pb: &mut PathBuilder) {
+        match self {
+            Outline::Ellipse(rect) => push_ellipse(pb, rect),
+            Outline::RoundedRect(rect, radii) => push_rounded_rect(pb, rect, radii),
+        }
+    }
+}
+
+fn push_ellipse(pb: &mut PathBuilder, bounds: &Rect) {
+    let rx = bounds.width() / 2.0;
+    let ry = bounds.height() / 2.0;
+    let cx = bounds.left() + rx;
+    let cy = bounds.top() + ry;
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+
+    pb.move_to(cx + rx, cy);
+    pb.cubic_to(cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry);
+    pb.cubic_to(cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy);
+    pb.cubic_to(cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry);
+    pb.cubic_to(cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy);
+    pb.close();
+}
+
+/// Scales the radii of the corners down by the same factor, so that adjacent corners do not overlap.
+fn fit_radii(bounds: &Rect, radii: &[(f32, f32); 4]) -> [(f32, f32); 4] {
+    let (width, height) = (bounds.width(), bounds.height());
+    let [top_left, top_right, bottom_right, bottom_left] = *radii;
+
+    let ratio = |length: f32, sum: f32| if sum > length { length / sum } else { 1.0 };
+    let scale = ratio(width, top_left.0 + top_right.0)
+        .min(ratio(width, bottom_left.0 + bottom_right.0))
+        .min(ratio(height, top_left.1 + bottom_left.1))
+        .min(ratio(height, top_right.1 + bottom_right.1));
+    [
+        (top_left.0 * scale, top_left.1 * scale),
+        (top_right.0 * scale, top_right.1 * scale),
+        (bottom_right.0 * scale, bottom_right.1 * scale),
+        (bottom_left.0 * scale, bottom_left.1 * scale),
+    ]
+}
+
+fn push_rounded_rect(pb: &mut PathBuilder, bounds: &Rect, radii: &[(f32, f32); 4]) {
+    let (left, top, right, bottom) = (bounds.left(), bounds.top(), bounds.right(), bounds.bottom());
+    let [tl, tr, br, bl] = fit_radii(bounds, radii);
+
+    pb.move_to(left + tl.0, top);
+    pb.line_to(right - tr.0, top);
+    if tr.0 > 0.0 && tr.1 > 0.0 {
+        pb.cubic_to(right - tr.0 + tr.0 * KAPPA, top, right, top + tr.1 - tr.1 * KAPPA, right, top + tr.1);
+    }
+    pb.line_to(right, bottom - br.1);
+    if br.0 > 0.0 && br.1 > 0.0 {
+        pb.cubic_to(right, bottom - br.1 + br.1 * KAPPA, right - br.0 + br.0 * KAPPA, bottom, right - br.0, bottom);
+    }
+    pb.line_to(left + bl.0, bottom);
+    if bl.0 > 0.0 && bl.1 > 0.0 {
+        pb.cubic_to(left + bl.0 - bl.0 * KAPPA, bottom, left, bottom - bl.1 + bl.1 * KAPPA, left, bottom - bl.1);
+    }
+    pb.line_to(left, top + tl.1);
+    if tl.0 > 0.0 && tl.1 > 0.0 {
+        pb.cubic_to(left, top + tl.1 - tl.1 * KAPPA, left + tl.0 - tl.0 * KAPPA, top, left + tl.0, top);
+    }
+    pb.close();
+}
+
+#[cfg(test)]
+mod test {
+    use orbclient::Color;
+    use specs::{Entity, World};
+    use tiny_skia::Rect;
+    use crate::{Border, BorderBuilder, HeadlessShell, Label, Shape};
+    use super::Outline;
+
+    /// Builds a red widget of the shape at (20, 20) with the size of 60x40 pixels, with an optional border.
+    fn shape_widget(shape: Shape, border: Option<BorderBuilder>) -> impl Fn(&mut World) -> Vec<Entity> {
+        let border = border.map(|border| border.color(Color::rgb(0, 0, 255)).build());
+        move |world: &mut World| {
+            let mut builder = Label::new(String::new()).shape(shape).background(Color::rgb(255, 0, 0)).position(20, 20).size(60, 40);
+            if let Some(border) = border {
+                builder = builder.border(border);
+            }
+            vec![builder.build(world)]
+        }
+    }
+
+    /// Renders the widget and returns a function reading the (red, blue) channels of the pixels.
+    fn render(widget: impl Fn(&mut World) -> Vec<Entity>) -> impl Fn(u32, u32) -> (u8, u8) {
+        let frame = HeadlessShell::with_widgets(100, 80, widget).render().clone();
+        move |x, y| {
+            let pixel = frame.pixel(x, y).unwrap();
+            (pixel.red(), pixel.blue())
+        }
+    }
+
+    fn bounds() -> Rect {
+        Rect::from_xywh(20.0, 20.0, 60.0, 40.0).unwrap()
+    }
+
+    #[test]
+    fn circles_are_centered_in_the_bounds() {
+        let inscribed = Outline::new(Shape::Circle(0.0), bounds(), None).unwrap().bounds();
+        assert_eq!(inscribed, Rect::from_xywh(30.0, 20.0, 40.0, 40.0).unwrap());
+        let sized = Outline::new(Shape::Circle(10.0), bounds(), None).unwrap().bounds();
+        assert_eq!(sized, Rect::from_xywh(40.0, 30.0, 20.0, 20.0).unwrap());
+        assert_eq!(Outline::new(Shape::Ellipse, bounds(), None).unwrap().bounds(), bounds());
+    }
+
+    #[test]
+    fn ellipses_contain_the_points_within_their_curve() {
+        let ellipse = Outline::new(Shape::Ellipse, bounds(), None).unwrap();
+        assert!(ellipse.contains(50.0, 40.0));
+        assert!(ellipse.contains(21.0, 40.0));
+        assert!(ellipse.contains(50.0, 21.0));
+        assert!(!ellipse.contains(22.0, 22.0));
+        assert!(!ellipse.contains(78.0, 58.0));
+        assert!(!ellipse.contains(80.0, 40.0));
+    }
+
+    #[test]
+    fn circle_is_painted_without_the_corners_of_its_bounds() {
+        let pixel = render(shape_widget(Shape::Circle(0.0), None));
+        let red = |x, y| pixel(x, y).0;
+        assert_eq!(red(50, 40), 255);
+        assert_eq!(red(32, 40), 255);
+        assert_eq!(red(50, 22), 255);
+        // the parts of the bounds outside of the circle
+        assert_eq!(red(25, 40), 0);
+        assert_eq!(red(32, 22), 0);
+        assert_eq!(red(68, 58), 0);
+    }
+
+    #[test]
+    fn ellipse_is_painted_with_its_border_along_the_curve() {
+        let pixel = render(shape_widget(Shape::Ellipse, Some(Border::new().width(4.0))));
+        assert_eq!(pixel(50, 40), (255, 0));
+        assert_eq!(pixel(21, 40), (0, 255));
+        assert_eq!(pixel(50, 58), (0, 255));
+        assert_eq!(pixel(26, 40), (255, 0));
+        assert_eq!(pixel(22, 22), (0, 0));
+    }
+
+    #[test]
+    fn border_sides_are_painted_with_their_own_width_inside_the_bounds() {
+        let border = Border::new().left(1.0).top(2.0).right(4.0).bottom(8.0);
+        let pixel = render(shape_widget(Shape::Rectangle, Some(border)));
+        const RED: (u8, u8) = (255, 0);
+        const BLUE: (u8, u8) = (0, 255);
+        // the left, top, right and bottom sides, then the background next to them
+        assert_eq!((pixel(20, 40), pixel(21, 40)), (BLUE, RED));
+        assert_eq!((pixel(50, 20), pixel(50, 21), pixel(50, 22)), (BLUE, BLUE, RED));
+        assert_eq!((pixel(75, 40), pixel(76, 40), pixel(79, 40)), (RED, BLUE, BLUE));
+        assert_eq!((pixel(50, 51), pixel(50, 52), pixel(50, 59)), (RED, BLUE, BLUE));
+        // nothing is painted around the bounds
+        for (x, y) in [(19, 40), (80, 40), (50, 19), (50, 60), (19, 19), (80, 60)] {
+            assert_eq!(pixel(x, y), (0, 0));
+        }
+    }
+
+    #[test]
+    fn rounded_border_is_filled_and_stroked_within_its_corners() {
+        let pixel = render(shape_widget(Shape::Rectangle, Some(Border::new().width(2.0).radius(10.0))));
+        // the straight parts of the sides
+        assert_eq!((pixel(50, 20), pixel(50, 21), pixel(50, 22)), ((0, 255), (0, 255), (255, 0)));
+        assert_eq!((pixel(78, 40), pixel(77, 40)), ((0, 255), (255, 0)));
+        // the corners of the bounds are cut off, the border follows the curve with the background inside it
+        for (x, y) in [(20, 20), (21, 21), (79, 20), (20, 59), (78, 58)] {
+            assert_eq!(pixel(x, y), (0, 0));
+        }
+        assert_eq!(pixel(23, 23).0, 0);
+        assert!(pixel(23, 23).1 > 0);
+        assert_eq!(pixel(26, 26), (255, 0));
     }
 }
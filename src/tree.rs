@@ -5,7 +5,9 @@ use indextree::{Arena, Children, Descendants, NodeError, NodeId};
 pub(crate) struct EntityTree {
     arena: Arena<Entity>,
     root: Option<Entity>,
-    map: HashMap<Entity, NodeId>
+    map: HashMap<Entity, NodeId>,
+    // the parents which got new children since the layout last asked
+    changed: Vec<Entity>
 }
 
 impl EntityTree {
@@ -13,7 +15,8 @@ impl EntityTree {
         EntityTree {
             arena: Arena::new(),
             root: None,
-            map: HashMap::new()
+            map: HashMap::new(),
+            changed: Vec::new()
         }
     }
 
@@ -42,6 +45,7 @@ impl EntityTree {
         let child_node = self.map.get(&child)
             .expect("Cannot append: The child node not found! Add the node to the tree with add_node()!");
         parent_node.append(*child_node, &mut self.arena);
+        self.changed.push(parent);
     }
 
     pub fn count(&self) -> usize {
@@ -105,6 +109,21 @@ impl EntityTree {
         }
     }
 
+    /// Returns the route from the root of the tree down to the given entity, both ends included.
+    /// The route is empty when the entity is not part of the tree.
+    pub fn path_to(&self, widget: Entity) -> Vec<Entity> {
+        let mut path = Vec::new();
+        if let Some(node_idx) = self.map.get(&widget) {
+            for ancestor in node_idx.ancestors(&self.arena) {
+                if let Some(node) = self.arena.get(ancestor) {
+                    path.push(*node.get());
+                }
+            }
+        }
+        path.reverse();
+        path
+    }
+
     pub fn root(&self) -> Option<Entity> {
         self.root
     }
@@ -124,7 +143,16 @@ impl EntityTree {
     }
 
     pub fn try_append_child(&mut self, parent: NodeId, child: NodeId) -> Result<(), NodeError> {
-        parent.checked_append(child, &mut self.arena)
+        parent.checked_append(child, &mut self.arena)?;
+        if let Some(parent) = self.entitiy_of(parent) {
+            self.changed.push(parent);
+        }
+        Ok(())
+    }
+
+    /// Returns the widgets which got new children since the last call.
+    pub(crate) fn take_changed(&mut self) -> Vec<Entity> {
+        std::mem::take(&mut self.changed)
     }
 
     pub fn try_children_of(&self, parent: Entity) -> Option<Children<Entity>> {
@@ -153,10 +181,30 @@ impl Iterator for TreeIter {
     fn 
 }*/
 
+#[cfg(test)]
 mod test {
     use crate::{EntityTree};
     use specs::{Builder, Entity, World, WorldExt};
-    use std::collections::HashMap;
+
+    #[test]
+    fn path_leads_from_the_root_to_the_widget() {
+        let mut world = World::new();
+        let entities: Vec<Entity> = (0..4).map(|_| world.create_entity().build()).collect();
+        let (root, parent, child, detached) = (entities[0], entities[1], entities[2], entities[3]);
+        let mut tree = EntityTree::new();
+        tree.set_root(root);
+        tree.add_node(parent);
+        tree.add_node(child);
+        tree.append_child(root, parent);
+        tree.append_child(parent, child);
+
+        assert_eq!(tree.path_to(child), vec![root, parent, child]);
+        assert_eq!(tree.path_to(root), vec![root]);
+        assert!(tree.path_to(detached).is_empty());
+        // a node without a parent is the start of its own path
+        tree.add_node(detached);
+        assert_eq!(tree.path_to(detached), vec![detached]);
+    }
 
     /*#[test]
     fn test_new() {
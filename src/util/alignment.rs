@@ -0,0 +1,26 @@
+/// Describes how a widget is placed within the space given by its parent along one axis.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Alignment {
+    /// Placed at the left or top edge.
+    #[default]
+    Start,
+    /// Placed at the middle.
+    Center,
+    /// Placed at the right or bottom edge.
+    End,
+    /// Resized to fill the whole space.
+    Stretch,
+}
+
+impl Alignment {
+    /// Returns the offset and the length of an item with the given length placed within the available space.
+    pub fn align(&self, length: u32, available: u32) -> (u32, u32) {
+        let length = length.min(available);
+        match self {
+            Alignment::Start => (0, length),
+            Alignment::Center => ((available - length) / 2, length),
+            Alignment::End => (available - length, length),
+            Alignment::Stretch => (0, available),
+        }
+    }
+}
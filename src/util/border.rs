@@ -1,6 +1,7 @@
 use orbclient::Color;
 
 /// Defines a rectangle border around widgets.
+/// The border is drawn inside the bounds of the widget, each side with its own width.
 #[derive(Copy, Clone, Debug)]
 pub struct Border {
     pub color: orbclient::Color,
@@ -8,13 +9,53 @@ pub struct Border {
     pub top: f32,
     pub right: f32,
     pub bottom: f32,
+    /// Rounds all the four corners with the same radius.
     pub radius: f32,
+    /// Rounds each corner with its own radius instead of the uniform radius when set.
+    pub corner_radius: Option<CornerRadius>,
+}
+
+/// The radii of the rounded corners of a border.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CornerRadius {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_right: f32,
+    pub bottom_left: f32,
+}
+
+impl CornerRadius {
+    /// Creates the same radius for all the four corners.
+    pub fn uniform(radius: f32) -> CornerRadius {
+        CornerRadius {
+            top_left: radius,
+            top_right: radius,
+            bottom_right: radius,
+            bottom_left: radius,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.top_left <= 0.0 && self.top_right <= 0.0 && self.bottom_right <= 0.0 && self.bottom_left <= 0.0
+    }
+}
+
+impl From<f32> for CornerRadius {
+    /// Rounds all the four corners with the same radius.
+    fn from(radius: f32) -> Self {
+        CornerRadius::uniform(radius)
+    }
 }
 
 impl Border {
     pub fn new() -> BorderBuilder {
         BorderBuilder::new()
     }
+
+    /// Returns the radius of each corner: the corner radius when it is set, the uniform radius otherwise.
+    pub fn radii(&self) -> CornerRadius {
+        self.corner_radius.unwrap_or_else(|| CornerRadius::uniform(self.radius))
+    }
 }
 
 impl Default for Border {
@@ -27,6 +68,7 @@ impl Default for Border {
             right: 1.0,
             bottom: 1.0,
             radius: 0.0,
+            corner_radius: None,
         }
     }
 }
@@ -38,6 +80,7 @@ pub struct BorderBuilder {
     right: f32,
     bottom: f32,
     radius: f32,
+    corner_radius: Option<CornerRadius>,
 }
 
 impl BorderBuilder {
@@ -49,6 +92,7 @@ impl BorderBuilder {
             right: 1.0,
             bottom: 1.0,
             radius: 0.0,
+            corner_radius: None,
         }
     }
 
@@ -85,8 +129,21 @@ impl BorderBuilder {
         self
     }
 
+    /// Rounds all the four corners with the same radius.
     pub fn radius(mut self, radius: f32) -> BorderBuilder {
         self.radius = radius;
+        self.corner_radius = None;
+        self
+    }
+
+    /// Rounds each corner with its own radius, clockwise from the top left corner.
+    pub fn corner_radius(mut self, top_left: f32, top_right: f32, bottom_right: f32, bottom_left: f32) -> BorderBuilder {
+        self.corner_radius = Some(CornerRadius {
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        });
         self
     }
 
@@ -98,7 +155,29 @@ impl BorderBuilder {
             right: self.right,
             bottom: self.bottom,
             radius: self.radius,
+            corner_radius: self.corner_radius,
         }
     }
 }
 
+#[cfg(test)]
+mod test {
+    use crate::{Border, CornerRadius};
+
+    #[test]
+    fn uniform_radius_rounds_every_corner() {
+        let border = Border { radius: 4.0, ..Border::default() };
+        assert_eq!(border.radii(), CornerRadius::uniform(4.0));
+        assert_eq!(Border::new().radius(4.0).build().radii(), CornerRadius::from(4.0));
+        assert!(Border::default().radii().is_zero());
+    }
+
+    #[test]
+    fn corners_get_their_own_radius() {
+        let radius = Border::new().corner_radius(1.0, 2.0, 3.0, 4.0).build().radii();
+        assert_eq!(radius, CornerRadius { top_left: 1.0, top_right: 2.0, bottom_right: 3.0, bottom_left: 4.0 });
+        assert!(!radius.is_zero());
+        // the radius set last wins
+        assert_eq!(Border::new().corner_radius(1.0, 2.0, 3.0, 4.0).radius(5.0).build().radii(), CornerRadius::uniform(5.0));
+    }
+}
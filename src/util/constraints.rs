@@ -1,4 +1,6 @@
-#[derive(Copy, Clone, Debug)]
+use crate::Thickness;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Constraints {
     pub min_width: u32,
     pub max_width: u32,
@@ -6,6 +8,18 @@ pub struct Constraints {
     pub max_height: u32
 }
 
+impl Constraints {
+    /// Returns the constraints of the area left inside the given sides.
+    pub fn deflate(&self, thickness: &Thickness) -> Constraints {
+        Constraints {
+            min_width: self.min_width.saturating_sub(thickness.horizontal()),
+            max_width: self.max_width.saturating_sub(thickness.horizontal()),
+            min_height: self.min_height.saturating_sub(thickness.vertical()),
+            max_height: self.max_height.saturating_sub(thickness.vertical())
+        }
+    }
+}
+
 impl Default for Constraints {
     fn default() -> Self {
         Constraints {
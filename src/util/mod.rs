@@ -1,5 +1,9 @@
+mod alignment;
 mod border;
 mod constraints;
+mod thickness;
 
+pub use self::alignment::*;
 pub use self::border::*;
-pub use self::constraints::*;
\ No newline at end of file
+pub use self::constraints::*;
+pub use self::thickness::*;
\ No newline at end of file
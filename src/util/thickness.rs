@@ -0,0 +1,47 @@
+/// The widths of the four sides of a frame in pixels, used for the padding and the margin of widgets.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Thickness {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl Thickness {
+    pub fn new(left: u32, top: u32, right: u32, bottom: u32) -> Thickness {
+        Thickness { left, top, right, bottom }
+    }
+
+    /// Creates the same width for all the four sides.
+    pub fn uniform(width: u32) -> Thickness {
+        Thickness::new(width, width, width, width)
+    }
+
+    /// Creates one width for the left and the right side, and another one for the top and the bottom.
+    pub fn symmetric(horizontal: u32, vertical: u32) -> Thickness {
+        Thickness::new(horizontal, vertical, horizontal, vertical)
+    }
+
+    /// The sum of the left and the right side, saturating at u32::MAX.
+    pub fn horizontal(&self) -> u32 {
+        self.left.saturating_add(self.right)
+    }
+
+    /// The sum of the top and the bottom side, saturating at u32::MAX.
+    pub fn vertical(&self) -> u32 {
+        self.top.saturating_add(self.bottom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Thickness;
+
+    #[test]
+    fn sums_of_the_sides_saturate() {
+        let thickness = Thickness::new(u32::MAX, 2, 1, u32::MAX - 1);
+        assert_eq!(thickness.horizontal(), u32::MAX);
+        assert_eq!(thickness.vertical(), u32::MAX);
+        assert_eq!(Thickness::symmetric(3, 4).horizontal(), 6);
+    }
+}
@@ -0,0 +1,494 @@
+use std::rc::Rc;
+
+use crate::{Border, EventHandlerComponent, EventResult, FocusComponent, FocusManager, FontStyle, FontWeight, MarginComponent, MouseButton, PaddingComponent, PositionComponent, RenderComponent, Shape, SizeComponent, TextAlignment, Thickness, VerticalAlignment, WidgetEvent, component::TextComponent, set_focus};
+use orbclient::Color;
+use specs::{Builder, Component, Entity, VecStorage, World, WorldExt};
+
+const BUTTON_DEFAULT_WIDTH: usize = 100;
+const BUTTON_DEFAULT_HEIGHT: usize = 30;
+
+type ClickHandler = dyn Fn(Entity, &World);
+
+/// The visual states of a button.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ButtonState {
+    Normal,
+    Hover,
+    Pressed,
+    Disabled,
+}
+
+/// Describes how a button looks like in one of its states.
+#[derive(Copy, Clone, Debug)]
+pub struct ButtonStyle {
+    pub background: Option<Color>,
+    pub border: Option<Border>,
+    pub text_color: Color,
+}
+
+/// Holds the state, the per-state styles and the click callback of a Button widget.
+pub struct ButtonComponent {
+    pub state: ButtonState,
+    pub normal: ButtonStyle,
+    pub hover: ButtonStyle,
+    pub pressed: ButtonStyle,
+    pub disabled: ButtonStyle,
+    on_click: Option<Rc<ClickHandler>>,
+}
+
+impl ButtonComponent {
+    pub fn style(&self) -> &ButtonStyle {
+        match self.state {
+            ButtonState::Normal => &self.normal,
+            ButtonState::Hover => &self.hover,
+            ButtonState::Pressed => &self.pressed,
+            ButtonState::Disabled => &self.disabled,
+        }
+    }
+}
+
+impl Component for ButtonComponent {
+    type Storage = VecStorage<Self>;
+}
+
+/// A clickable widget displaying a text.
+pub struct Button {}
+
+impl Button {
+    pub fn new(text: String) -> ButtonBuilder {
+        ButtonBuilder::new().text(text)
+    }
+
+    /// Enables or disables the button. A disabled button does not react to the pointer and cannot be focused,
+    /// disabling the focused button takes the keyboard focus away from it.
+    pub fn set_enabled(button: Entity, enabled: bool, world: &World) {
+        let state = if enabled { ButtonState::Normal } else { ButtonState::Disabled };
+        set_state(button, state, world);
+        if let Some(focus) = world.write_storage::<FocusComponent>().get_mut(button) {
+            focus.focusable = enabled;
+        }
+        if !enabled && world.read_resource::<FocusManager>().focused() == Some(button) {
+            set_focus(None, world);
+        }
+    }
+
+    pub fn is_enabled(button: Entity, world: &World) -> bool {
+        let buttons = world.read_storage::<ButtonComponent>();
+        matches!(buttons.get(button), Some(b) if b.state != ButtonState::Disabled)
+    }
+}
+
+pub struct ButtonBuilder {
+    background: Option<Color>,
+    border: Option<Border>,
+    disabled_background: Option<Color>,
+    disabled_text_color: Color,
+    enabled: bool,
+    font_family: String,
+    font_size: usize,
+    font_style: FontStyle,
+    font_weight: FontWeight,
+    hover_background: Option<Color>,
+    margin: Option<Thickness>,
+    on_click: Option<Rc<ClickHandler>>,
+    padding: Option<Thickness>,
+    position: PositionComponent,
+    pressed_background: Option<Color>,
+    size: SizeComponent,
+    tab_index: i32,
+    text: String,
+    text_color: Color,
+}
+
+impl ButtonBuilder {
+    pub fn new() -> ButtonBuilder {
+        ButtonBuilder {
+            background: Some(Color::rgb(220, 220, 220)),
+            border: Some(Border::default()),
+            disabled_background: Some(Color::rgb(240, 240, 240)),
+            disabled_text_color: Color::rgb(160, 160, 160),
+            enabled: true,
+            font_family: "Roboto-Medium".to_string(),
+            font_size: 12,
+            font_style: FontStyle::default(),
+            font_weight: FontWeight::default(),
+            hover_background: Some(Color::rgb(235, 235, 235)),
+            margin: None,
+            on_click: None,
+            padding: None,
+            position: PositionComponent::default(),
+            pressed_background: Some(Color::rgb(190, 190, 190)),
+            size: SizeComponent {
+                width: BUTTON_DEFAULT_WIDTH,
+                height: BUTTON_DEFAULT_HEIGHT,
+            },
+            tab_index: 0,
+            text: String::new(),
+            text_color: Color::rgba(0, 0, 0, 255),
+        }
+    }
+
+    /// Background color of the button in its normal state.
+    pub fn background(mut self, bg_color: Color) -> ButtonBuilder {
+        self.background = Some(bg_color);
+        self
+    }
+
+    pub fn border(mut self, border: Border) -> ButtonBuilder {
+        self.border = Some(border);
+        self
+    }
+
+    /// Background color of the button while it is disabled.
+    pub fn disabled_background(mut self, bg_color: Color) -> ButtonBuilder {
+        self.disabled_background = Some(bg_color);
+        self
+    }
+
+    /// Text color of the button while it is disabled.
+    pub fn disabled_text_color(mut self, color: Color) -> ButtonBuilder {
+        self.disabled_text_color = color;
+        self
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> ButtonBuilder {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn font_family(mut self, font: String) -> ButtonBuilder {
+        self.font_family = font;
+        self
+    }
+
+    pub fn font_size(mut self, font_size: usize) -> ButtonBuilder {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn font_style(mut self, font_style: FontStyle) -> ButtonBuilder {
+        self.font_style = font_style;
+        self
+    }
+
+    /// Sets the weight of the font, e.g. FontWeight::BOLD. Fonts registered by name ignore it.
+    pub fn font_weight(mut self, font_weight: FontWeight) -> ButtonBuilder {
+        self.font_weight = font_weight;
+        self
+    }
+
+    /// Background color of the button while the pointer is over it.
+    pub fn hover_background(mut self, bg_color: Color) -> ButtonBuilder {
+        self.hover_background = Some(bg_color);
+        self
+    }
+
+    /// Sets the space around the button, kept free by the layout of its parent.
+    pub fn margin(mut self, margin: Thickness) -> ButtonBuilder {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Sets the callback invoked when the button is clicked with the left mouse button.
+    pub fn on_click<F: 'static + Fn(Entity, &World)>(mut self, handler: F) -> ButtonBuilder {
+        self.on_click = Some(Rc::new(handler));
+        self
+    }
+
+    /// Sets the space between the bounds of the button and the area its caption is centered in.
+    pub fn padding(mut self, padding: Thickness) -> ButtonBuilder {
+        self.padding = Some(padding);
+        self
+    }
+
+    pub fn position(mut self, x: u32, y: u32) -> ButtonBuilder {
+        self.position = PositionComponent { x, y };
+        self
+    }
+
+    /// Background color of the button while it is held down.
+    pub fn pressed_background(mut self, bg_color: Color) -> ButtonBuilder {
+        self.pressed_background = Some(bg_color);
+        self
+    }
+
+    pub fn size(mut self, width: usize, height: usize) -> ButtonBuilder {
+        self.size = SizeComponent { width, height };
+        self
+    }
+
+    /// Sets the position of the button in the Tab traversal order. See FocusComponent for details.
+    pub fn tab_index(mut self, tab_index: i32) -> ButtonBuilder {
+        self.tab_index = tab_index;
+        self
+    }
+
+    pub fn text(mut self, text: String) -> Self {
+        self.text = text;
+        self
+    }
+
+    pub fn text_color(mut self, color: Color) -> ButtonBuilder {
+        self.text_color = color;
+        self
+    }
+
+    pub fn build(self, world: &mut World) -> Entity {
+        world.register::<ButtonComponent>();
+        world.register::<EventHandlerComponent>();
+        world.register::<FocusComponent>();
+        world.register::<MarginComponent>();
+        world.register::<PaddingComponent>();
+        world.register::<PositionComponent>();
+        world.register::<RenderComponent>();
+        world.register::<TextComponent>();
+        world.register::<SizeComponent>();
+
+        let style = |background| ButtonStyle {
+            background,
+            border: self.border,
+            text_color: self.text_color,
+        };
+        let button = ButtonComponent {
+            state: if self.enabled { ButtonState::Normal } else { ButtonState::Disabled },
+            normal: style(self.background),
+            hover: style(self.hover_background),
+            pressed: style(self.pressed_background),
+            disabled: ButtonStyle {
+                background: self.disabled_background,
+                border: self.border,
+                text_color: self.disabled_text_color,
+            },
+            on_click: self.on_click,
+        };
+        let current_style = *button.style();
+
+        let render_component = RenderComponent {
+            background: current_style.background,
+            border: current_style.border,
+            shape: Shape::Rectangle,
+        };
+        let text = TextComponent {
+            font_family: self.font_family,
+            font_size: self.font_size,
+            font_style: self.font_style,
+            font_weight: self.font_weight,
+            text: self.text,
+            text_color: current_style.text_color,
+            text_alignment: TextAlignment::Center,
+            vertical_alignment: VerticalAlignment::Center,
+            ..TextComponent::default()
+        };
+
+        world
+            .create_entity()
+            .with(self.position)
+            .with(render_component)
+            .with(self.size)
+            .with(text)
+            .with(button)
+            .with(FocusComponent { focusable: self.enabled, tab_index: self.tab_index })
+            .with(EventHandlerComponent::new().on_bubble(handle_event))
+            .maybe_with(self.margin.map(|margin| MarginComponent { margin }))
+            .maybe_with(self.padding.map(|padding| PaddingComponent { padding }))
+            .build()
+    }
+}
+
+impl Default for ButtonBuilder {
+    fn default() -> Self {
+        ButtonBuilder::new()
+    }
+}
+
+fn handle_event(button: Entity, event: &WidgetEvent, world: &World) -> EventResult {
+    let state = match world.read_storage::<ButtonComponent>().get(button) {
+        Some(component) => component.state,
+        None => return EventResult::Ignored,
+    };
+    if state == ButtonState::Disabled {
+        return EventResult::Ignored;
+    }
+
+    match event {
+        WidgetEvent::MouseEnter => set_state(button, ButtonState::Hover, world),
+        WidgetEvent::MouseLeave => set_state(button, ButtonState::Normal, world),
+        WidgetEvent::MouseDown { button: MouseButton::Left, .. } => set_state(button, ButtonState::Pressed, world),
+        WidgetEvent::MouseUp { button: MouseButton::Left, .. } => {
+            if state != ButtonState::Pressed {
+                return EventResult::Ignored;
+            }
+            set_state(button, ButtonState::Hover, world);
+            click(button, world);
+        }
+        // a focused button can be clicked with the keyboard too
+        WidgetEvent::KeyDown { scancode: orbclient::K_ENTER, .. } | WidgetEvent::KeyDown { scancode: orbclient::K_SPACE, .. } => {
+            click(button, world);
+        }
+        _ => return EventResult::Ignored,
+    }
+    EventResult::Handled
+}
+
+fn click(button: Entity, world: &World) {
+    // the storage must be released before calling back, the callback may access the button
+    let on_click = world.read_storage::<ButtonComponent>().get(button).and_then(|b| b.on_click.clone());
+    if let Some(on_click) = on_click {
+        on_click(button, world);
+    }
+}
+
+/// Switches the button to the given state and applies the state's style to its visual components.
+fn set_state(button: Entity, state: ButtonState, world: &World) {
+    let mut buttons = world.write_storage::<ButtonComponent>();
+    let component = match buttons.get_mut(button) {
+        Some(component) => component,
+        None => return,
+    };
+    component.state = state;
+    let style = *component.style();
+
+    if let Some(render) = world.write_storage::<RenderComponent>().get_mut(button) {
+        render.background = style.background;
+        render.border = style.border;
+    }
+    if let Some(text) = world.write_storage::<TextComponent>().get_mut(button) {
+        text.text_color = style.text_color;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, rc::Rc};
+    use orbclient::{ButtonEvent, Color, KeyEvent, MouseEvent};
+    use specs::{Entity, World, WorldExt};
+    use crate::{Button, ButtonComponent, ButtonState, FocusManager, HeadlessShell, set_focus};
+
+    /// Builds a button at (10, 10) with the size of 100x30 pixels, counting its clicks.
+    fn button_widget(enabled: bool, clicks: Rc<Cell<u32>>) -> impl Fn(&mut World) -> Vec<Entity> {
+        move |world: &mut World| {
+            let clicks = clicks.clone();
+            let button = Button::new("OK".to_string())
+                .background(Color::rgb(200, 0, 0))
+                .hover_background(Color::rgb(0, 200, 0))
+                .pressed_background(Color::rgb(0, 0, 200))
+                .enabled(enabled)
+                .position(10, 10)
+                .on_click(move |_, _| clicks.set(clicks.get() + 1))
+                .build(world);
+            vec![button]
+        }
+    }
+
+    fn state(shell: &HeadlessShell) -> ButtonState {
+        shell.world().read_storage::<ButtonComponent>().get(shell.widgets()[0]).unwrap().state
+    }
+
+    fn move_to(shell: &mut HeadlessShell, x: i32, y: i32) {
+        shell.on_event(MouseEvent { x, y }.to_event());
+        shell.render();
+    }
+
+    fn left_button(shell: &mut HeadlessShell, pressed: bool) {
+        shell.on_event(ButtonEvent { left: pressed, middle: false, right: false }.to_event());
+        shell.render();
+    }
+
+    #[test]
+    fn states_follow_the_pointer_and_a_click_calls_back() {
+        let clicks = Rc::new(Cell::new(0));
+        let mut shell = HeadlessShell::with_widgets(200, 100, button_widget(true, clicks.clone()));
+        let pixel = shell.render().pixel(50, 25).unwrap();
+        assert_eq!((state(&shell), pixel.red()), (ButtonState::Normal, 200));
+
+        move_to(&mut shell, 50, 25);
+        assert_eq!(state(&shell), ButtonState::Hover);
+        assert_eq!(shell.render().pixel(50, 25).unwrap().green(), 200);
+
+        left_button(&mut shell, true);
+        assert_eq!(state(&shell), ButtonState::Pressed);
+        assert_eq!(shell.render().pixel(50, 25).unwrap().blue(), 200);
+        assert_eq!(clicks.get(), 0);
+
+        left_button(&mut shell, false);
+        assert_eq!(state(&shell), ButtonState::Hover);
+        assert_eq!(clicks.get(), 1);
+
+        move_to(&mut shell, 150, 80);
+        assert_eq!(state(&shell), ButtonState::Normal);
+    }
+
+    #[test]
+    fn releasing_outside_of_the_button_does_not_click() {
+        let clicks = Rc::new(Cell::new(0));
+        let mut shell = HeadlessShell::with_widgets(200, 100, button_widget(true, clicks.clone()));
+        shell.render();
+
+        move_to(&mut shell, 50, 25);
+        left_button(&mut shell, true);
+        move_to(&mut shell, 150, 80);
+        assert_eq!(state(&shell), ButtonState::Normal);
+        left_button(&mut shell, false);
+        assert_eq!(clicks.get(), 0);
+    }
+
+    #[test]
+    fn focused_button_is_clicked_with_enter_and_space() {
+        let clicks = Rc::new(Cell::new(0));
+        let mut shell = HeadlessShell::with_widgets(200, 100, button_widget(true, clicks.clone()));
+        shell.render();
+
+        move_to(&mut shell, 50, 25);
+        left_button(&mut shell, true);
+        left_button(&mut shell, false);
+        assert_eq!(shell.world().read_resource::<FocusManager>().focused(), Some(shell.widgets()[0]));
+        for scancode in [orbclient::K_ENTER, orbclient::K_SPACE].iter() {
+            shell.on_event(KeyEvent { character: '\0', scancode: *scancode, pressed: true }.to_event());
+            shell.render();
+        }
+        assert_eq!(clicks.get(), 3);
+    }
+
+    #[test]
+    fn disabled_button_ignores_the_pointer_until_enabled() {
+        let clicks = Rc::new(Cell::new(0));
+        let mut shell = HeadlessShell::with_widgets(200, 100, button_widget(false, clicks.clone()));
+        shell.render();
+        let button = shell.widgets()[0];
+
+        move_to(&mut shell, 50, 25);
+        left_button(&mut shell, true);
+        left_button(&mut shell, false);
+        assert_eq!(state(&shell), ButtonState::Disabled);
+        assert_eq!(clicks.get(), 0);
+        assert!(!Button::is_enabled(button, shell.world()));
+        assert_eq!(shell.world().read_resource::<FocusManager>().focused(), None);
+
+        Button::set_enabled(button, true, shell.world());
+        assert_eq!(state(&shell), ButtonState::Normal);
+        left_button(&mut shell, true);
+        left_button(&mut shell, false);
+        assert_eq!(clicks.get(), 1);
+        assert_eq!(shell.world().read_resource::<FocusManager>().focused(), Some(button));
+    }
+
+    #[test]
+    fn disabling_the_focused_button_takes_the_focus_away() {
+        let clicks = Rc::new(Cell::new(0));
+        let mut shell = HeadlessShell::with_widgets(200, 100, button_widget(true, clicks.clone()));
+        shell.render();
+        let button = shell.widgets()[0];
+        set_focus(Some(button), shell.world());
+        shell.render();
+
+        Button::set_enabled(button, false, shell.world());
+        assert_eq!(shell.world().read_resource::<FocusManager>().focused(), None);
+        shell.on_event(KeyEvent { character: '\0', scancode: orbclient::K_ENTER, pressed: true }.to_event());
+        shell.render();
+        assert_eq!(clicks.get(), 0);
+
+        // enabling the button again does not give the focus back
+        Button::set_enabled(button, true, shell.world());
+        assert_eq!(shell.world().read_resource::<FocusManager>().focused(), None);
+    }
+}
@@ -0,0 +1,240 @@
+use std::{path::Path, sync::Arc};
+use crate::{Border, ImageComponent, ImageFit, ImageSampling, MarginComponent, PaddingComponent, PositionComponent, RenderComponent, Shape, SizeComponent, Thickness, decode_image, load_image};
+use orbclient::Color;
+use specs::{Builder, Entity, World, WorldExt};
+use tiny_skia::Pixmap;
+
+pub struct Image {}
+
+impl Image {
+    /// Shows the given pixels. An `Arc<Pixmap>` lets many widgets share the same image.
+    pub fn new<I: Into<Arc<Pixmap>>>(image: I) -> ImageBuilder {
+        ImageBuilder::new(image.into())
+    }
+
+    /// Decodes a PNG or a JPEG image.
+    pub fn from_bytes(data: &[u8]) -> Result<ImageBuilder, String> {
+        decode_image(data).map(Image::new)
+    }
+
+    /// Reads and decodes a PNG or a JPEG image file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ImageBuilder, String> {
+        load_image(path).map(Image::new)
+    }
+}
+
+pub struct ImageBuilder {
+    background: Option<Color>,
+    border: Option<Border>,
+    fit: ImageFit,
+    image: Arc<Pixmap>,
+    margin: Option<Thickness>,
+    padding: Option<Thickness>,
+    position: PositionComponent,
+    sampling: ImageSampling,
+    shape: Shape,
+    size: Option<SizeComponent>,
+}
+
+impl ImageBuilder {
+    pub fn new(image: Arc<Pixmap>) -> ImageBuilder {
+        ImageBuilder {
+            background: None,
+            border: None,
+            fit: ImageFit::default(),
+            image,
+            margin: None,
+            padding: None,
+            position: PositionComponent { x: 0, y: 0 },
+            sampling: ImageSampling::default(),
+            shape: Shape::Rectangle,
+            size: None,
+        }
+    }
+
+    /// Sets the color shown where the image does not cover the widget, and behind its transparent parts.
+    pub fn background(mut self, color: Color) -> ImageBuilder {
+        self.background = Some(color);
+        self
+    }
+
+    pub fn border(mut self, border: Border) -> ImageBuilder {
+        self.border = Some(border);
+        self
+    }
+
+    pub fn fit(mut self, fit: ImageFit) -> ImageBuilder {
+        self.fit = fit;
+        self
+    }
+
+    /// Sets the space around the widget, kept free by the layout of its parent.
+    pub fn margin(mut self, margin: Thickness) -> ImageBuilder {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Sets the space between the border and the area the image is fitted into.
+    pub fn padding(mut self, padding: Thickness) -> ImageBuilder {
+        self.padding = Some(padding);
+        self
+    }
+
+    pub fn position(mut self, x: u32, y: u32) -> ImageBuilder {
+        self.position = PositionComponent { x, y };
+        self
+    }
+
+    pub fn sampling(mut self, sampling: ImageSampling) -> ImageBuilder {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Sets the outline the image is cut to, e.g. a circle for avatars.
+    pub fn shape(mut self, shape: Shape) -> ImageBuilder {
+        self.shape = shape;
+        self
+    }
+
+    /// Sets the size of the widget. Images take the size of their pixels and their padding by default.
+    pub fn size(mut self, width: usize, height: usize) -> ImageBuilder {
+        self.size = Some(SizeComponent { width, height });
+        self
+    }
+
+    pub fn build(self, world: &mut World) -> Entity {
+        world.register::<ImageComponent>();
+        world.register::<MarginComponent>();
+        world.register::<PaddingComponent>();
+        world.register::<PositionComponent>();
+        world.register::<RenderComponent>();
+        world.register::<SizeComponent>();
+
+        let padding = self.padding.unwrap_or_default();
+        let size = self.size.unwrap_or(SizeComponent {
+            width: (self.image.width() as usize).saturating_add(padding.horizontal() as usize),
+            height: (self.image.height() as usize).saturating_add(padding.vertical() as usize),
+        });
+        world
+            .create_entity()
+            .with(self.position)
+            .with(RenderComponent {
+                background: self.background,
+                border: self.border,
+                shape: self.shape,
+            })
+            .with(size)
+            .with(ImageComponent {
+                image: self.image,
+                fit: self.fit,
+                sampling: self.sampling,
+            })
+            .maybe_with(self.margin.map(|margin| MarginComponent { margin }))
+            .maybe_with(self.padding.map(|padding| PaddingComponent { padding }))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use specs::{World, WorldExt};
+    use tiny_skia::{ColorU8, Pixmap};
+    use crate::{HeadlessShell, Image, ImageFit, ImageSampling, SizeComponent, Thickness};
+
+    const RED: (u8, u8, u8) = (255, 0, 0);
+    const BLUE: (u8, u8, u8) = (0, 0, 255);
+
+    /// Returns a 3x1 image with two red pixels and a blue one, which tells apart where it was scaled from.
+    fn red_red_blue() -> Pixmap {
+        let mut pixmap = Pixmap::new(3, 1).unwrap();
+        let red = ColorU8::from_rgba(255, 0, 0, 255).premultiply();
+        pixmap.pixels_mut().copy_from_slice(&[red, red, ColorU8::from_rgba(0, 0, 255, 255).premultiply()]);
+        pixmap
+    }
+
+    /// Renders the image fitted into a 31x41 widget at (20, 20), with odd sizes to center unscaled pixels exactly.
+    fn render(fit: ImageFit) -> impl Fn(u32, u32) -> (u8, u8, u8) {
+        let mut shell = HeadlessShell::with_widgets(80, 80, move |world: &mut World| {
+            vec![Image::new(red_red_blue()).fit(fit).sampling(ImageSampling::Nearest).position(20, 20).size(31, 41).build(world)]
+        });
+        let frame = shell.render().clone();
+        move |x, y| {
+            let pixel = frame.pixel(x, y).unwrap();
+            (pixel.red(), pixel.green(), pixel.blue())
+        }
+    }
+
+    #[test]
+    fn images_start_with_the_size_of_their_pixels() {
+        let mut world = World::new();
+        let image = Image::new(red_red_blue()).build(&mut world);
+        let padded = Image::new(red_red_blue()).padding(Thickness::new(1, 2, 3, 4)).build(&mut world);
+        let sized = Image::new(red_red_blue()).size(10, 10).build(&mut world);
+
+        let sizes = world.read_storage::<SizeComponent>();
+        let size = |image| sizes.get(image).map(|size| (size.width, size.height));
+        assert_eq!(size(image), Some((3, 1)));
+        assert_eq!(size(padded), Some((7, 7)));
+        assert_eq!(size(sized), Some((10, 10)));
+    }
+
+    #[test]
+    fn images_are_decoded_from_bytes_and_files() {
+        assert!(Image::from_bytes(&red_red_blue().encode_png().unwrap()).is_ok());
+        assert!(Image::from_bytes(b"GIF89a").is_err());
+        assert!(Image::from_file("no/such/image.png").is_err());
+    }
+
+    #[test]
+    fn stretched_image_fills_the_widget() {
+        let pixel = render(ImageFit::Stretch);
+        assert_eq!(pixel(21, 21), RED);
+        assert_eq!(pixel(38, 59), RED);
+        assert_eq!(pixel(43, 21), BLUE);
+        assert_eq!(pixel(50, 59), BLUE);
+    }
+
+    #[test]
+    fn contained_image_keeps_its_aspect_ratio() {
+        let pixel = render(ImageFit::Contain);
+        assert_eq!(pixel(21, 40), RED);
+        assert_eq!(pixel(50, 40), BLUE);
+        // the widget is taller than the scaled image, which is centered vertically
+        assert!(![RED, BLUE].contains(&pixel(21, 25)));
+        assert!(![RED, BLUE].contains(&pixel(50, 55)));
+    }
+
+    #[test]
+    fn covering_image_is_cut_off_at_the_widget() {
+        let pixel = render(ImageFit::Cover);
+        // scaled to the height of the widget, only the center of the two red pixels is left
+        assert_eq!(pixel(21, 21), RED);
+        assert_eq!(pixel(50, 40), RED);
+        assert_eq!(pixel(50, 59), RED);
+    }
+
+    #[test]
+    fn unscaled_image_is_centered() {
+        let pixel = render(ImageFit::None);
+        assert_eq!(pixel(34, 40), RED);
+        assert_eq!(pixel(35, 40), RED);
+        assert_eq!(pixel(36, 40), BLUE);
+        assert!(![RED, BLUE].contains(&pixel(33, 40)));
+        assert!(![RED, BLUE].contains(&pixel(37, 40)));
+        assert!(![RED, BLUE].contains(&pixel(34, 39)));
+        assert!(![RED, BLUE].contains(&pixel(34, 41)));
+    }
+
+    #[test]
+    fn tiled_image_repeats_from_the_top_left_corner() {
+        let pixel = render(ImageFit::Tile);
+        for y in [20, 40, 60] {
+            assert_eq!(pixel(20, y), RED);
+            assert_eq!(pixel(21, y), RED);
+            assert_eq!(pixel(22, y), BLUE);
+            assert_eq!(pixel(49, y), BLUE);
+            assert_eq!(pixel(50, y), RED);
+        }
+        assert!(![RED, BLUE].contains(&pixel(51, 40)));
+    }
+}
@@ -1,12 +1,9 @@
 use std::usize;
 
-use crate::{Border, PositionComponent, RenderComponent, Shape, SizeComponent, component::TextComponent};
+use crate::{Border, Constraints, FontRegistry, FontStyle, FontWeight, LayoutComponent, MarginComponent, PaddingComponent, PositionComponent, RenderComponent, Shape, SizeComponent, TextAlignment, TextDirection, TextSpan, TextLayout, TextWrap, Thickness, VerticalAlignment, component::TextComponent, measure_text};
 use orbclient::Color;
 use specs::{Builder, Entity, World, WorldExt};
 
-const LABEL_DEFAULT_WIDTH: usize = 150;
-const LABEL_DEFAULT_HEIGHT: usize = 55;
-
 pub struct Label {}
 
 impl Label {
@@ -18,12 +15,25 @@ impl Label {
 pub struct LabelBuilder {
     background: Option<Color>,
     border: Option<Border>,
+    direction: TextDirection,
+    ellipsis: bool,
     font_family: String,
     font_size: usize,
+    font_style: FontStyle,
+    font_weight: FontWeight,
+    line_height: f32,
+    margin: Option<Thickness>,
+    max_lines: Option<usize>,
+    padding: Option<Thickness>,
     position: PositionComponent,
-    size: SizeComponent,
+    shape: Shape,
+    size: Option<SizeComponent>,
+    spans: Vec<TextSpan>,
     text: String,
-    text_color: orbclient::Color
+    text_alignment: TextAlignment,
+    text_color: orbclient::Color,
+    vertical_alignment: VerticalAlignment,
+    wrap: TextWrap,
 }
 
 impl LabelBuilder {
@@ -31,15 +41,25 @@ impl LabelBuilder {
         LabelBuilder {
             background: None,
             border: None,
+            direction: TextDirection::default(),
+            ellipsis: false,
             font_family: "Roboto-Medium".to_string(),
             font_size: 12,
+            font_style: FontStyle::default(),
+            font_weight: FontWeight::default(),
+            line_height: 1.0,
+            margin: None,
+            max_lines: None,
+            padding: None,
             position: PositionComponent::default(),
-            size: SizeComponent {
-                width: LABEL_DEFAULT_WIDTH,
-                height: LABEL_DEFAULT_HEIGHT,
-            },
+            shape: Shape::Rectangle,
+            size: None,
+            spans: Vec::new(),
             text: String::new(),
-            text_color: orbclient::Color::rgba(255, 255, 255, 255)
+            text_alignment: TextAlignment::default(),
+            text_color: orbclient::Color::rgba(255, 255, 255, 255),
+            vertical_alignment: VerticalAlignment::default(),
+            wrap: TextWrap::Word,
         }
     }
 
@@ -53,6 +73,18 @@ impl LabelBuilder {
         self
     }
 
+    /// Sets the base direction of the text, it is detected from the text by default.
+    pub fn direction(mut self, direction: TextDirection) -> LabelBuilder {
+        self.direction = direction;
+        self
+    }
+
+    /// Ends the text with an ellipsis when it does not fit in the label or in max_lines.
+    pub fn ellipsis(mut self, ellipsis: bool) -> LabelBuilder {
+        self.ellipsis = ellipsis;
+        self
+    }
+
     pub fn font_family(mut self, font: String) -> LabelBuilder {
         self.font_family = font;
         self
@@ -63,13 +95,60 @@ impl LabelBuilder {
         self
     }
 
+    pub fn font_style(mut self, font_style: FontStyle) -> LabelBuilder {
+        self.font_style = font_style;
+        self
+    }
+
+    /// Sets the weight of the font, e.g. FontWeight::BOLD. Fonts registered by name ignore it.
+    pub fn font_weight(mut self, font_weight: FontWeight) -> LabelBuilder {
+        self.font_weight = font_weight;
+        self
+    }
+
+    /// Sets the height of the lines as a multiple of the height of the font.
+    pub fn line_height(mut self, line_height: f32) -> LabelBuilder {
+        self.line_height = line_height;
+        self
+    }
+
+    /// Sets the space around the label, kept free by the layout of its parent.
+    pub fn margin(mut self, margin: Thickness) -> LabelBuilder {
+        self.margin = Some(margin);
+        self
+    }
+
+    pub fn max_lines(mut self, max_lines: usize) -> LabelBuilder {
+        self.max_lines = Some(max_lines);
+        self
+    }
+
+    /// Sets the space between the bounds of the label and its text.
+    pub fn padding(mut self, padding: Thickness) -> LabelBuilder {
+        self.padding = Some(padding);
+        self
+    }
+
     pub fn position(mut self, x: u32, y: u32) -> LabelBuilder {
         self.position = PositionComponent { x, y };
         self
     }
 
+    /// Sets the outline of the label's background and border.
+    pub fn shape(mut self, shape: Shape) -> LabelBuilder {
+        self.shape = shape;
+        self
+    }
+
+    /// Gives the label a fixed size. Without it, the label is sized to its text.
     pub fn size(mut self, width: usize, height: usize) -> LabelBuilder {
-        self.size = SizeComponent { width, height };
+        self.size = Some(SizeComponent { width, height });
+        self
+    }
+
+    /// Draws a part of the text in a style of its own, e.g. bold, underlined or highlighted.
+    pub fn span(mut self, span: TextSpan) -> LabelBuilder {
+        self.spans.push(span);
         self
     }
 
@@ -78,12 +157,31 @@ impl LabelBuilder {
         self
     }
 
+    pub fn text_alignment(mut self, alignment: TextAlignment) -> LabelBuilder {
+        self.text_alignment = alignment;
+        self
+    }
+
     pub fn text_color(mut self, color: orbclient::Color) -> LabelBuilder {
         self.text_color = color;
         self
     }
 
+    pub fn vertical_alignment(mut self, alignment: VerticalAlignment) -> LabelBuilder {
+        self.vertical_alignment = alignment;
+        self
+    }
+
+    /// Sets how the text is broken into lines. Labels wrap between words by default.
+    pub fn wrap(mut self, wrap: TextWrap) -> LabelBuilder {
+        self.wrap = wrap;
+        self
+    }
+
     pub fn build(self, world: &mut World) -> Entity {
+        world.register::<LayoutComponent>();
+        world.register::<MarginComponent>();
+        world.register::<PaddingComponent>();
         world.register::<PositionComponent>();
         world.register::<RenderComponent>();
         world.register::<TextComponent>();
@@ -92,21 +190,131 @@ impl LabelBuilder {
         let render_component = RenderComponent {
             background: self.background,
             border: self.border,
-            shape: Shape::Rectangle,
+            shape: self.shape,
         };
         let text = TextComponent {
             font_family: self.font_family.to_string(),
             font_size: self.font_size,
+            font_style: self.font_style,
+            font_weight: self.font_weight,
             text: self.text,
-            text_color: self.text_color
+            text_color: self.text_color,
+            spans: self.spans,
+            wrap: self.wrap,
+            text_alignment: self.text_alignment,
+            vertical_alignment: self.vertical_alignment,
+            line_height: self.line_height,
+            max_lines: self.max_lines,
+            ellipsis: self.ellipsis,
+            direction: self.direction,
         };
 
+        if let Some(size) = self.size {
+            return world
+                .create_entity()
+                .with(self.position)
+                .with(render_component)
+                .with(size)
+                .with(text)
+                .maybe_with(self.margin.map(|margin| MarginComponent { margin }))
+                .maybe_with(self.padding.map(|padding| PaddingComponent { padding }))
+                .build();
+        }
+
+        // the label starts with the size of its text, the layout adjusts it to the constraints later
+        if !world.has_value::<FontRegistry>() {
+            world.insert(FontRegistry::new());
+        }
+        let (width, height) = measure_text(&mut world.write_resource::<FontRegistry>(), &text, None);
+        let padding = self.padding.unwrap_or_default();
         world
             .create_entity()
             .with(self.position)
             .with(render_component)
-            .with(self.size)
+            .with(SizeComponent {
+                width: (width.ceil() as usize).saturating_add(padding.horizontal() as usize),
+                height: (height.ceil() as usize).saturating_add(padding.vertical() as usize),
+            })
             .with(text)
+            .maybe_with(self.margin.map(|margin| MarginComponent { margin }))
+            .maybe_with(self.padding.map(|padding| PaddingComponent { padding }))
+            .with(LayoutComponent { constraints: Constraints::default(), object: Box::new(TextLayout {}) })
             .build()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use specs::{Entity, World, WorldExt};
+    use crate::{Center, EntityTree, FontRegistry, HeadlessShell, Label, SizeComponent, TextComponent, TextWrap, Thickness, measure_text};
+
+    const TEXT: &str = "hello world and more words";
+
+    fn size(world: &World, label: Entity) -> (usize, usize) {
+        let size = world.read_storage::<SizeComponent>().get(label).copied().unwrap();
+        (size.width, size.height)
+    }
+
+    /// Returns the size of the text of the label on a single line.
+    fn text_size(world: &World, label: Entity) -> (usize, usize) {
+        let (width, height) = measure_text(&mut world.write_resource::<FontRegistry>(), world.read_storage::<TextComponent>().get(label).unwrap(), None);
+        (width.ceil() as usize, height.ceil() as usize)
+    }
+
+    /// Lays out the label built by the closure in the center of a window of the given width.
+    fn centered(width: u32, label: fn(&mut World) -> Entity) -> (HeadlessShell, Entity) {
+        let mut shell = HeadlessShell::new(width, 200, move |world| {
+            let label = label(world);
+            Center::new().child(label).build(world)
+        });
+        shell.render();
+        let label = {
+            let tree = shell.world().read_resource::<EntityTree>();
+            tree.child_of(tree.child_of(shell.root()).unwrap()).unwrap()
+        };
+        (shell, label)
+    }
+
+    #[test]
+    fn labels_start_with_the_size_of_their_text() {
+        let mut world = World::new();
+        let label = Label::new(TEXT.to_string()).build(&mut world);
+        let padded = Label::new(TEXT.to_string()).padding(Thickness::new(1, 2, 3, 4)).build(&mut world);
+        let sized = Label::new(TEXT.to_string()).size(10, 10).build(&mut world);
+
+        let (width, height) = text_size(&world, label);
+        assert!(width > 0 && height > 0);
+        assert_eq!(size(&world, label), (width, height));
+        assert_eq!(size(&world, padded), (width + 4, height + 6));
+        assert_eq!(size(&world, sized), (10, 10));
+    }
+
+    #[test]
+    fn label_fits_its_text_in_a_wide_parent() {
+        let (shell, label) = centered(400, |world| Label::new(TEXT.to_string()).build(world));
+        assert_eq!(size(shell.world(), label), text_size(shell.world(), label));
+    }
+
+    #[test]
+    fn label_wraps_its_text_in_a_narrow_parent() {
+        let (shell, label) = centered(60, |world| Label::new(TEXT.to_string()).build(world));
+        let (width, height) = size(shell.world(), label);
+        let (_, line_height) = text_size(shell.world(), label);
+        assert!(width <= 60);
+        assert!(height >= 3 * line_height - 1);
+    }
+
+    #[test]
+    fn unwrapped_label_is_cut_to_the_parent() {
+        let (shell, label) = centered(60, |world| Label::new(TEXT.to_string()).wrap(TextWrap::NoWrap).build(world));
+        let (_, line_height) = text_size(shell.world(), label);
+        assert_eq!(size(shell.world(), label), (60, line_height));
+    }
+
+    #[test]
+    fn padding_is_added_around_the_text() {
+        let (shell, label) = centered(400, |world| Label::new(TEXT.to_string()).padding(Thickness::new(1, 2, 3, 4)).build(world));
+        let (width, height) = text_size(shell.world(), label);
+        assert_eq!(size(shell.world(), label), (width + 4, height + 6));
+    }
+}
@@ -0,0 +1,301 @@
+use specs::{Builder, Component, Entity, VecStorage, World, WorldExt};
+use crate::{Alignment, Constraints, DesiredSize, EntityTree, Layout, LayoutComponent, MarginComponent, PaddingComponent, PositionComponent, RelativePositionComponent, Thickness, measure_widget, place_widget};
+
+/// The direction in which a box layout stacks its children.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+/// Attached properties of a child inside an HBox or a VBox.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BoxItemComponent {
+    /// Placement of the child on the cross axis of the box (vertically in an HBox, horizontally in a VBox).
+    pub alignment: Alignment,
+    /// When true, the child grows along the main axis to share the space left unused by the other children.
+    pub stretch: bool,
+}
+
+impl Component for BoxItemComponent {
+    type Storage = VecStorage<Self>;
+}
+
+/// A layout widget that places its children next to each other from left to right.
+pub struct HBox {}
+
+impl HBox {
+    pub fn new() -> BoxBuilder {
+        BoxBuilder::new(Orientation::Horizontal)
+    }
+}
+
+/// A layout widget that places its children below each other from top to bottom.
+pub struct VBox {}
+
+impl VBox {
+    pub fn new() -> BoxBuilder {
+        BoxBuilder::new(Orientation::Vertical)
+    }
+}
+
+pub struct BoxBuilder {
+    children: Vec<(Entity, BoxItemComponent)>,
+    margin: Option<Thickness>,
+    orientation: Orientation,
+    padding: Option<Thickness>,
+    spacing: u32,
+}
+
+impl BoxBuilder {
+    fn new(orientation: Orientation) -> Self {
+        BoxBuilder {
+            children: vec![],
+            margin: None,
+            orientation,
+            padding: None,
+            spacing: 0,
+        }
+    }
+
+    /// Appends a child aligned to the start of the cross axis.
+    pub fn child(mut self, child: Entity) -> Self {
+        self.children.push((child, BoxItemComponent::default()));
+        self
+    }
+
+    /// Appends a child with custom alignment and stretching.
+    pub fn child_with(mut self, child: Entity, item: BoxItemComponent) -> Self {
+        self.children.push((child, item));
+        self
+    }
+
+    /// Sets the space around the box, kept free by the layout of its parent.
+    pub fn margin(mut self, margin: Thickness) -> Self {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Sets the space between the bounds of the box and its children.
+    pub fn padding(mut self, padding: Thickness) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
+    /// Sets the gap between two adjacent children in pixels.
+    pub fn spacing(mut self, spacing: u32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn build(self, world: &mut World) -> Entity {
+        world.register::<BoxItemComponent>();
+        world.register::<LayoutComponent>();
+        world.register::<MarginComponent>();
+        world.register::<PaddingComponent>();
+        world.register::<PositionComponent>();
+        world.register::<RelativePositionComponent>();
+
+        let layout = LayoutComponent {
+            constraints: Constraints::default(),
+            object: Box::new(BoxLayout {
+                orientation: self.orientation,
+                spacing: self.spacing,
+            }),
+        };
+
+        let widget = world.create_entity()
+            .with(layout)
+            .with(PositionComponent::default())
+            .maybe_with(self.margin.map(|margin| MarginComponent { margin }))
+            .maybe_with(self.padding.map(|padding| PaddingComponent { padding }))
+            .build();
+
+        {
+            let mut items = world.write_storage::<BoxItemComponent>();
+            for (child, item) in self.children.iter() {
+                items.insert(*child, *item).expect("Cannot attach box properties to a dead entity!");
+            }
+        }
+
+        let mut tree = world.write_resource::<EntityTree>();
+        tree.add_node(widget);
+        for (child, _) in self.children {
+            tree.add_node(child);
+            tree.append_child(widget, child);
+        }
+
+        widget
+    }
+}
+
+/// Stacks the children of a widget along one axis.
+pub struct BoxLayout {
+    pub orientation: Orientation,
+    pub spacing: u32,
+}
+
+impl BoxLayout {
+    /// Splits a (width, height) pair to (main axis, cross axis) lengths.
+    fn split(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.orientation {
+            Orientation::Horizontal => (width, height),
+            Orientation::Vertical => (height, width),
+        }
+    }
+
+    /// Joins main and cross axis lengths to a (width, height) pair.
+    fn join(&self, main: u32, cross: u32) -> (u32, u32) {
+        self.split(main, cross)
+    }
+
+    fn children(widget: Entity, world: &World) -> Vec<Entity> {
+        let tree = world.read_resource::<EntityTree>();
+        match tree.try_children_of(widget) {
+            Some(children) => children.filter_map(|node| tree.entitiy_of(node)).collect(),
+            None => vec![],
+        }
+    }
+
+    fn measure_children(&self, children: &[Entity], max_width: u32, max_height: u32, world: &World) -> Vec<DesiredSize> {
+        let constraints = Constraints {
+            min_width: 0,
+            max_width,
+            min_height: 0,
+            max_height,
+        };
+        children.iter().map(|child| measure_widget(*child, &constraints, world)).collect()
+    }
+
+    fn total_spacing(&self, count: usize) -> u32 {
+        self.spacing * (count.saturating_sub(1) as u32)
+    }
+}
+
+impl Layout for BoxLayout {
+    fn arrange(&self, widget: Entity, desired_size: &DesiredSize, world: &World) {
+        let children = BoxLayout::children(widget, world);
+        let desired = self.measure_children(&children, desired_size.width, desired_size.height, world);
+        let (available_main, available_cross) = self.split(desired_size.width, desired_size.height);
+
+        let items: Vec<BoxItemComponent> = {
+            let storage = world.read_storage::<BoxItemComponent>();
+            children.iter().map(|child| storage.get(*child).copied().unwrap_or_default()).collect()
+        };
+        let used_main: u32 = desired.iter().map(|size| self.split(size.width, size.height).0).sum::<u32>()
+            + self.total_spacing(children.len());
+        let stretched = items.iter().filter(|item| item.stretch).count() as u32;
+        let mut free_main = available_main.saturating_sub(used_main);
+
+        let mut offset = 0;
+        let mut stretched_left = stretched;
+
+        for ((child, size), item) in children.iter().zip(desired.iter()).zip(items.iter()) {
+            let (mut main, cross) = self.split(size.width, size.height);
+            if item.stretch {
+                // the last stretched child takes the remainder of the division
+                let share = free_main / stretched_left;
+                main += share;
+                free_main -= share;
+                stretched_left -= 1;
+            }
+            let (cross_offset, cross) = item.alignment.align(cross, available_cross);
+            let (x, y) = self.join(offset, cross_offset);
+            let (width, height) = self.join(main, cross);
+
+            place_widget(*child, x, y, &DesiredSize { width, height }, world);
+
+            offset += main + self.spacing;
+        }
+    }
+
+    fn measure(&self, widget: Entity, constraints: &Constraints, world: &World) -> DesiredSize {
+        let children = BoxLayout::children(widget, world);
+        let desired = self.measure_children(&children, constraints.max_width, constraints.max_height, world);
+
+        let mut main = self.total_spacing(children.len());
+        let mut cross = 0;
+        for size in desired.iter() {
+            let (child_main, child_cross) = self.split(size.width, size.height);
+            main += child_main;
+            cross = cross.max(child_cross);
+        }
+
+        let (width, height) = self.join(main, cross);
+        DesiredSize {
+            width: width.max(constraints.min_width).min(constraints.max_width),
+            height: height.max(constraints.min_height).min(constraints.max_height)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Alignment, BoxItemComponent, EntityTree, HBox, HeadlessShell, Label, SizeComponent, VBox};
+    use specs::{Entity, WorldExt};
+
+    const STRETCH: BoxItemComponent = BoxItemComponent { alignment: Alignment::Stretch, stretch: true };
+
+    /// Returns the children of the box filling the window.
+    fn children(shell: &HeadlessShell) -> Vec<Entity> {
+        let tree = shell.world().read_resource::<EntityTree>();
+        let widget = tree.child_of(shell.root()).unwrap();
+        tree.try_children_of(widget).unwrap().filter_map(|node| tree.entitiy_of(node)).collect()
+    }
+
+    fn size_of(shell: &HeadlessShell, widget: Entity) -> (usize, usize) {
+        let sizes = shell.world().read_storage::<SizeComponent>();
+        let size = sizes.get(widget).unwrap();
+        (size.width, size.height)
+    }
+
+    #[test]
+    fn stretched_children_shrink_with_the_hbox() {
+        let mut shell = HeadlessShell::new(200, 50, |world| {
+            let stretched = Label::new("a".to_string()).size(30, 20).build(world);
+            let fixed = Label::new("b".to_string()).size(40, 20).build(world);
+            HBox::new().child_with(stretched, STRETCH).child(fixed).build(world)
+        });
+        let (stretched, fixed) = (children(&shell)[0], children(&shell)[1]);
+
+        shell.render();
+        assert_eq!(size_of(&shell, stretched), (160, 50));
+        shell.resize(300, 80);
+        shell.render();
+        assert_eq!(size_of(&shell, stretched), (260, 80));
+        shell.resize(100, 30);
+        shell.render();
+        assert_eq!(size_of(&shell, stretched), (60, 30));
+        // without free space the child gets its desired width back
+        shell.resize(50, 30);
+        shell.render();
+        assert_eq!(size_of(&shell, stretched), (30, 30));
+        assert_eq!(size_of(&shell, fixed), (40, 20));
+    }
+
+    #[test]
+    fn stretched_children_shrink_with_the_vbox() {
+        let mut shell = HeadlessShell::new(100, 200, |world| {
+            let leaf = Label::new("a".to_string()).size(30, 20).build(world);
+            // a nested box is measured from its own children, its child fills it to show its size
+            let nested = HBox::new().child_with(leaf, STRETCH).build(world);
+            let fixed = Label::new("b".to_string()).size(40, 40).build(world);
+            VBox::new().child_with(nested, STRETCH).child(fixed).build(world)
+        });
+        let (nested, fixed) = (children(&shell)[0], children(&shell)[1]);
+        let leaf = shell.world().read_resource::<EntityTree>().child_of(nested).unwrap();
+
+        shell.render();
+        assert_eq!(size_of(&shell, leaf), (100, 160));
+        shell.resize(120, 300);
+        shell.render();
+        assert_eq!(size_of(&shell, leaf), (120, 260));
+        shell.resize(80, 100);
+        shell.render();
+        assert_eq!(size_of(&shell, leaf), (80, 60));
+        shell.resize(80, 50);
+        shell.render();
+        assert_eq!(size_of(&shell, leaf), (80, 20));
+        assert_eq!(size_of(&shell, fixed), (40, 40));
+    }
+}
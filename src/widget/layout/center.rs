@@ -1,11 +1,13 @@
 use specs::{Builder, Entity, World, WorldExt};
-use crate::{Constraints, DesiredSize, EntityTree, Layout, LayoutComponent, PositionComponent, SizeComponent};
+use crate::{Constraints, DesiredSize, EntityTree, Layout, LayoutComponent, MarginComponent, PaddingComponent, PositionComponent, RelativePositionComponent, Thickness, measure_widget, place_widget};
 
 /// A layout widget that centers its child wihtin itself.
 pub struct Center {}
 
 pub struct CenterBuilder {
-    child: Option<Entity>
+    child: Option<Entity>,
+    margin: Option<Thickness>,
+    padding: Option<Thickness>,
 }
 
 impl Center {
@@ -18,6 +20,8 @@ impl CenterBuilder {
     fn new() -> Self {
         CenterBuilder {
             child: None,
+            margin: None,
+            padding: None,
         }
     }
 
@@ -26,9 +30,24 @@ impl CenterBuilder {
         self
     }
 
+    /// Sets the space around the widget, kept free by the layout of its parent.
+    pub fn margin(mut self, margin: Thickness) -> Self {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Sets the space between the bounds of the widget and the area its child is centered in.
+    pub fn padding(mut self, padding: Thickness) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
     pub fn build(self, world: &mut World) -> Entity {
         world.register::<LayoutComponent>();
-        
+        world.register::<MarginComponent>();
+        world.register::<PaddingComponent>();
+        world.register::<RelativePositionComponent>();
+
         let layout = LayoutComponent {
             constraints: Constraints {
                 min_height: 0,
@@ -44,6 +63,8 @@ impl CenterBuilder {
         .with(PositionComponent {
             x:0, y:0
         })
+        .maybe_with(self.margin.map(|margin| MarginComponent { margin }))
+        .maybe_with(self.padding.map(|padding| PaddingComponent { padding }))
         .build();
 
         if let Some(child) = self.child {
@@ -61,27 +82,28 @@ pub struct CenterLayout {}
 
 impl Layout for CenterLayout {
     fn arrange(&self, widget: Entity, desired_size: &DesiredSize, world: &World) {
-        let tree = world.read_resource::<EntityTree>();
-        let sizes = world.read_storage::<SizeComponent>();
-
-        if let Some(child) = tree.child_of(widget) {
-            let mut position = world.write_component::<PositionComponent>();
-            if let Some(mut pos) = position.get_mut(child) {
-                if let Some(child_size) = sizes.get(child) {
-                    pos.x = (desired_size.width - child_size.width as u32) / 2;
-                    pos.y = (desired_size.height - child_size.height as u32) / 2;
-                }
-                println!("desired size: {:?}", desired_size);
-                println!("new coords: {}-{}", pos.x, pos.y);
-            }
+        let child = world.read_resource::<EntityTree>().child_of(widget);
+        if let Some(child) = child {
+            let size = measure_widget(child, &Constraints {
+                min_width: 0,
+                max_width: desired_size.width,
+                min_height: 0,
+                max_height: desired_size.height,
+            }, world);
+            let x = desired_size.width.saturating_sub(size.width) / 2;
+            let y = desired_size.height.saturating_sub(size.height) / 2;
+            place_widget(child, x, y, &size, world);
         }
     }
 
     fn measure(&self, _entity: Entity, constraints: &Constraints, _world: &World) -> DesiredSize {
         DesiredSize {
-            dirty: false,
             width: constraints.max_width,
             height: constraints.max_height
         }
     }
-}
\ No newline at end of file
+
+    fn sized_by_children(&self) -> bool {
+        false
+    }
+}
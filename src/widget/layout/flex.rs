@@ -0,0 +1,567 @@
+use specs::{Builder, Component, Entity, VecStorage, World, WorldExt};
+use crate::{Alignment, Constraints, DesiredSize, EntityTree, Layout, LayoutComponent, MarginComponent, Orientation, PaddingComponent, PositionComponent, RelativePositionComponent, Thickness, measure_widget, place_widget};
+
+/// How the children of a Flex line share the space left on the main axis.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum JustifyContent {
+    /// Packed at the start of the line.
+    #[default]
+    Start,
+    /// Packed at the end of the line.
+    End,
+    /// Packed at the middle of the line.
+    Center,
+    /// The first child at the start, the last child at the end, the space shared between the children.
+    SpaceBetween,
+    /// Every child gets the same space on both of its sides.
+    SpaceAround,
+    /// The space is shared equally before, between and after the children.
+    SpaceEvenly,
+}
+
+/// Attached properties of a child inside a Flex.
+#[derive(Copy, Clone, Debug)]
+pub struct FlexItemComponent {
+    /// The share of the free space of the line the child grows by. Zero keeps the child at its basis.
+    pub flex_grow: f32,
+    /// The share of the missing space of the line the child shrinks by, weighted by its basis.
+    pub flex_shrink: f32,
+    /// The length of the child on the main axis before growing or shrinking. None takes the length it desires.
+    pub flex_basis: Option<u32>,
+    /// The length the child does not shrink below on the main axis.
+    pub min_length: Option<u32>,
+    /// The length the child does not grow beyond on the main axis.
+    pub max_length: Option<u32>,
+    /// Placement of the child on the cross axis of its line, overriding the alignment of the Flex.
+    pub align_self: Option<Alignment>,
+}
+
+impl Default for FlexItemComponent {
+    fn default() -> Self {
+        FlexItemComponent {
+            flex_grow: 0.0,
+            flex_shrink: 1.0,
+            flex_basis: None,
+            min_length: None,
+            max_length: None,
+            align_self: None,
+        }
+    }
+}
+
+impl FlexItemComponent {
+    /// Clamps a main axis length between the limits of the child. The minimum wins over the maximum.
+    fn limit(&self, length: u32) -> u32 {
+        let length = match self.max_length {
+            Some(max_length) => length.min(max_length),
+            None => length,
+        };
+        match self.min_length {
+            Some(min_length) => length.max(min_length),
+            None => length,
+        }
+    }
+}
+
+impl Component for FlexItemComponent {
+    type Storage = VecStorage<Self>;
+}
+
+/// A responsive layout widget that places its children in lines, growing and shrinking them to fill the lines.
+pub struct Flex {}
+
+impl Flex {
+    pub fn new() -> FlexBuilder {
+        FlexBuilder::new()
+    }
+}
+
+pub struct FlexBuilder {
+    align_items: Alignment,
+    children: Vec<(Entity, FlexItemComponent)>,
+    direction: Orientation,
+    gap: u32,
+    justify_content: JustifyContent,
+    line_gap: u32,
+    margin: Option<Thickness>,
+    padding: Option<Thickness>,
+    wrap: bool,
+}
+
+impl FlexBuilder {
+    fn new() -> Self {
+        FlexBuilder {
+            align_items: Alignment::Stretch,
+            children: vec![],
+            direction: Orientation::Horizontal,
+            gap: 0,
+            justify_content: JustifyContent::default(),
+            line_gap: 0,
+            margin: None,
+            padding: None,
+            wrap: false,
+        }
+    }
+
+    /// Sets the placement of the children on the cross axis of their lines. Children are stretched by default.
+    pub fn align_items(mut self, alignment: Alignment) -> Self {
+        self.align_items = alignment;
+        self
+    }
+
+    /// Appends a child keeping its desired length.
+    pub fn child(mut self, child: Entity) -> Self {
+        self.children.push((child, FlexItemComponent::default()));
+        self
+    }
+
+    /// Appends a child with custom flex properties.
+    pub fn child_with(mut self, child: Entity, item: FlexItemComponent) -> Self {
+        self.children.push((child, item));
+        self
+    }
+
+    /// Sets the main axis, the children are placed from left to right by default.
+    pub fn direction(mut self, direction: Orientation) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Sets the gap between two adjacent children of a line in pixels.
+    pub fn gap(mut self, gap: u32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn justify_content(mut self, justify_content: JustifyContent) -> Self {
+        self.justify_content = justify_content;
+        self
+    }
+
+    /// Sets the gap between two adjacent lines in pixels.
+    pub fn line_gap(mut self, gap: u32) -> Self {
+        self.line_gap = gap;
+        self
+    }
+
+    /// Sets the space around the flex, kept free by the layout of its parent.
+    pub fn margin(mut self, margin: Thickness) -> Self {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Sets the space between the bounds of the flex and its lines.
+    pub fn padding(mut self, padding: Thickness) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
+    /// Moves the children not fitting into a line to a new line, instead of shrinking them.
+    pub fn wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn build(self, world: &mut World) -> Entity {
+        world.register::<FlexItemComponent>();
+        world.register::<LayoutComponent>();
+        world.register::<MarginComponent>();
+        world.register::<PaddingComponent>();
+        world.register::<PositionComponent>();
+        world.register::<RelativePositionComponent>();
+
+        let layout = LayoutComponent {
+            constraints: Constraints::default(),
+            object: Box::new(FlexLayout {
+                align_items: self.align_items,
+                direction: self.direction,
+                gap: self.gap,
+                justify_content: self.justify_content,
+                line_gap: self.line_gap,
+                wrap: self.wrap,
+            }),
+        };
+
+        let widget = world.create_entity()
+            .with(layout)
+            .with(PositionComponent::default())
+            .maybe_with(self.margin.map(|margin| MarginComponent { margin }))
+            .maybe_with(self.padding.map(|padding| PaddingComponent { padding }))
+            .build();
+
+        {
+            let mut items = world.write_storage::<FlexItemComponent>();
+            for (child, item) in self.children.iter() {
+                items.insert(*child, *item).expect("Cannot attach flex properties to a dead entity!");
+            }
+        }
+
+        let mut tree = world.write_resource::<EntityTree>();
+        tree.add_node(widget);
+        for (child, _) in self.children {
+            tree.add_node(child);
+            tree.append_child(widget, child);
+        }
+
+        widget
+    }
+}
+
+/// Places the children of a widget in lines along the main axis, like the flexible box layout of CSS.
+///
+/// A Flex fills the main axis of the space given by its parent, so it follows the size of the window,
+/// and its cross axis fits its lines.
+pub struct FlexLayout {
+    pub align_items: Alignment,
+    pub direction: Orientation,
+    pub gap: u32,
+    pub justify_content: JustifyContent,
+    pub line_gap: u32,
+    pub wrap: bool,
+}
+
+/// A child with the lengths it desires along the main and the cross axis.
+struct FlexChild {
+    entity: Entity,
+    item: FlexItemComponent,
+    basis: u32,
+    cross: u32,
+}
+
+impl FlexLayout {
+    /// Splits a (width, height) pair to (main axis, cross axis) lengths.
+    fn split(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.direction {
+            Orientation::Horizontal => (width, height),
+            Orientation::Vertical => (height, width),
+        }
+    }
+
+    /// Joins main and cross axis lengths to a (width, height) pair.
+    fn join(&self, main: u32, cross: u32) -> (u32, u32) {
+        self.split(main, cross)
+    }
+
+    fn constraints(&self, main: u32, cross: u32) -> Constraints {
+        let (max_width, max_height) = self.join(main, cross);
+        Constraints {
+            min_width: 0,
+            max_width,
+            min_height: 0,
+            max_height,
+        }
+    }
+
+    fn children(&self, widget: Entity, main: u32, cross: u32, world: &World) -> Vec<FlexChild> {
+        let children: Vec<Entity> = {
+            let tree = world.read_resource::<EntityTree>();
+            match tree.try_children_of(widget) {
+                Some(children) => children.filter_map(|node| tree.entitiy_of(node)).collect(),
+                None => vec![],
+            }
+        };
+        let constraints = self.constraints(main, cross);
+
+        children.into_iter()
+            .map(|entity| {
+                let item = world.read_storage::<FlexItemComponent>().get(entity).copied().unwrap_or_default();
+                let size = measure_widget(entity, &constraints, world);
+                let (desired_main, cross) = self.split(size.width, size.height);
+                FlexChild {
+                    entity,
+                    item,
+                    basis: item.flex_basis.unwrap_or(desired_main),
+                    cross,
+                }
+            })
+            .collect()
+    }
+
+    /// Breaks the children into lines no longer than the available length, every line holds at least one child.
+    /// Returns the ranges of the children in each line.
+    fn lines(&self, children: &[FlexChild], available: u32) -> Vec<std::ops::Range<usize>> {
+        let mut lines = Vec::with_capacity(1);
+        if !self.wrap {
+            lines.push(0..children.len());
+            return lines;
+        }
+        let mut start = 0;
+        let mut length = 0;
+        for (index, child) in children.iter().enumerate() {
+            let gap = if index > start { self.gap } else { 0 };
+            let basis = child.item.limit(child.basis);
+            if index > start && length + gap + basis > available {
+                lines.push(start..index);
+                start = index;
+                length = basis;
+            } else {
+                length += gap + basis;
+            }
+        }
+        if start < children.len() {
+            lines.push(start..children.len());
+        }
+        lines
+    }
+
+    fn total_gap(gap: u32, count: usize) -> u32 {
+        gap * (count.saturating_sub(1) as u32)
+    }
+}
+
+impl Layout for FlexLayout {
+    fn arrange(&self, widget: Entity, desired_size: &DesiredSize, world: &World) {
+        let (available_main, available_cross) = self.split(desired_size.width, desired_size.height);
+        let children = self.children(widget, available_main, available_cross, world);
+        let lines = self.lines(&children, available_main);
+        let single_line = lines.len() == 1;
+
+        let mut line_offset = 0;
+
+        for line in lines {
+            let children = &children[line];
+            let mains = flex_lengths(children, available_main.saturating_sub(FlexLayout::total_gap(self.gap, children.len())));
+            let used = mains.iter().sum::<u32>() + FlexLayout::total_gap(self.gap, children.len());
+            let (mut offset, spacing) = justify(self.justify_content, available_main.saturating_sub(used), children.len());
+
+            // the children are measured again, as their content may depend on the main length they get
+            let crosses: Vec<u32> = children.iter().zip(mains.iter())
+                .map(|(child, main)| {
+                    let size = measure_widget(child.entity, &self.constraints(*main, available_cross), world);
+                    self.split(size.width, size.height).1
+                })
+                .collect();
+            // a single line takes the whole cross axis of the flex
+            let line_cross = if single_line {
+                available_cross
+            } else {
+                crosses.iter().copied().max().unwrap_or(0)
+            };
+
+            for ((child, main), cross) in children.iter().zip(mains.iter()).zip(crosses.iter()) {
+                let alignment = child.item.align_self.unwrap_or(self.align_items);
+                let (cross_offset, cross) = alignment.align(*cross, line_cross);
+                let (x, y) = self.join(offset, line_offset + cross_offset);
+                let (width, height) = self.join(*main, cross);
+
+                place_widget(child.entity, x, y, &DesiredSize { width, height }, world);
+
+                offset += main + self.gap + spacing;
+            }
+            line_offset += line_cross + self.line_gap;
+        }
+    }
+
+    fn measure(&self, widget: Entity, constraints: &Constraints, world: &World) -> DesiredSize {
+        let (available_main, available_cross) = self.split(constraints.max_width, constraints.max_height);
+        let children = self.children(widget, available_main, available_cross, world);
+        let lines = self.lines(&children, available_main);
+
+        let cross = lines.iter()
+            .map(|line| children[line.clone()].iter().map(|child| child.cross).max().unwrap_or(0))
+            .sum::<u32>()
+            + FlexLayout::total_gap(self.line_gap, lines.len());
+
+        let (width, height) = self.join(available_main, cross);
+        DesiredSize {
+            width: width.max(constraints.min_width).min(constraints.max_width),
+            height: height.max(constraints.min_height).min(constraints.max_height)
+        }
+    }
+}
+
+/// Grows or shrinks the children of a line to the available length.
+/// Growing shares the free space by the grow factors, shrinking shares the missing space
+/// by the shrink factors weighted by the bases, so large children shrink more.
+/// Children reaching their min or max length are frozen there, and the others share the space again.
+fn flex_lengths(children: &[FlexChild], available: u32) -> Vec<u32> {
+    let mut lengths: Vec<u32> = children.iter().map(|child| child.basis).collect();
+    let mut frozen = vec![false; children.len()];
+
+    loop {
+        for ((length, child), frozen) in lengths.iter_mut().zip(children.iter()).zip(frozen.iter()) {
+            if !frozen {
+                *length = child.basis;
+            }
+        }
+        let used: u32 = lengths.iter().sum();
+        if used < available {
+            grow(children, &frozen, &mut lengths, available - used);
+        } else if used > available {
+            shrink(children, &frozen, &mut lengths, used - available);
+        }
+
+        // a positive violation means that children were held at their min lengths, a negative one at their max lengths
+        let mut violation: i64 = 0;
+        for ((length, child), frozen) in lengths.iter().zip(children.iter()).zip(frozen.iter()) {
+            if !frozen {
+                violation += child.item.limit(*length) as i64 - *length as i64;
+            }
+        }
+        for ((length, child), frozen) in lengths.iter_mut().zip(children.iter()).zip(frozen.iter_mut()) {
+            let limited = child.item.limit(*length);
+            if !*frozen && ((violation > 0 && limited > *length) || (violation < 0 && limited < *length)) {
+                *frozen = true;
+            }
+            *length = limited;
+        }
+        if violation == 0 {
+            return lengths;
+        }
+    }
+}
+
+fn grow(children: &[FlexChild], frozen: &[bool], lengths: &mut [u32], mut free: u32) {
+    let factor = |index: usize| if frozen[index] { 0.0 } else { children[index].item.flex_grow.max(0.0) };
+    let mut total: f32 = (0..children.len()).map(factor).sum();
+    // the last growing child takes the remainder of the division
+    for (index, length) in lengths.iter_mut().enumerate() {
+        let grow = factor(index);
+        if grow > 0.0 {
+            let share = ((free as f32 * grow / total).round() as u32).min(free);
+            *length += share;
+            free -= share;
+            total -= grow;
+        }
+    }
+}
+
+fn shrink(children: &[FlexChild], frozen: &[bool], lengths: &mut [u32], mut missing: u32) {
+    let weight = |index: usize| if frozen[index] { 0.0 } else { children[index].item.flex_shrink.max(0.0) * children[index].basis as f32 };
+    let mut total: f32 = (0..children.len()).map(weight).sum();
+    for (index, length) in lengths.iter_mut().enumerate() {
+        let weight = weight(index);
+        if weight > 0.0 {
+            let share = ((missing as f32 * weight / total).round() as u32).min(missing).min(*length);
+            *length -= share;
+            missing -= share;
+            total -= weight;
+        }
+    }
+}
+
+/// Returns the offset of the first child and the extra space between two children of a line.
+fn justify(justify_content: JustifyContent, free: u32, count: usize) -> (u32, u32) {
+    let count = count as u32;
+    match justify_content {
+        JustifyContent::Start => (0, 0),
+        JustifyContent::End => (free, 0),
+        JustifyContent::Center => (free / 2, 0),
+        JustifyContent::SpaceBetween if count > 1 => (0, free / (count - 1)),
+        JustifyContent::SpaceBetween => (0, 0),
+        JustifyContent::SpaceAround if count > 0 => (free / count / 2, free / count),
+        JustifyContent::SpaceEvenly => (free / (count + 1), free / (count + 1)),
+        JustifyContent::SpaceAround => (0, 0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FlexChild, FlexLayout, flex_lengths, justify};
+    use crate::{Alignment, FlexItemComponent, JustifyContent, Orientation};
+    use specs::{Builder, World, WorldExt};
+
+    fn child(basis: u32, item: FlexItemComponent) -> FlexChild {
+        FlexChild {
+            entity: World::new().create_entity().build(),
+            item,
+            basis,
+            cross: 10,
+        }
+    }
+
+    fn growing(grow: f32) -> FlexItemComponent {
+        FlexItemComponent { flex_grow: grow, ..FlexItemComponent::default() }
+    }
+
+    fn wrapping(gap: u32) -> FlexLayout {
+        FlexLayout {
+            align_items: Alignment::Stretch,
+            direction: Orientation::Horizontal,
+            gap,
+            justify_content: JustifyContent::Start,
+            line_gap: 0,
+            wrap: true,
+        }
+    }
+
+    #[test]
+    fn children_grow_by_their_factors() {
+        let children = [child(50, growing(1.0)), child(50, growing(3.0)), child(20, growing(0.0))];
+        assert_eq!(flex_lengths(&children, 320), vec![100, 200, 20]);
+    }
+
+    #[test]
+    fn children_shrink_by_their_factors_and_bases() {
+        let children = [child(100, FlexItemComponent::default()), child(300, FlexItemComponent::default())];
+        assert_eq!(flex_lengths(&children, 200), vec![50, 150]);
+        let rigid = FlexItemComponent { flex_shrink: 0.0, ..FlexItemComponent::default() };
+        let children = [child(100, rigid), child(300, FlexItemComponent::default())];
+        assert_eq!(flex_lengths(&children, 200), vec![100, 100]);
+    }
+
+    #[test]
+    fn growing_children_stop_at_their_max_length() {
+        let limited = FlexItemComponent { max_length: Some(80), ..growing(1.0) };
+        let children = [child(50, limited), child(50, growing(1.0))];
+        // the space the first child cannot take goes to the second one
+        assert_eq!(flex_lengths(&children, 300), vec![80, 220]);
+    }
+
+    #[test]
+    fn shrinking_children_stop_at_their_min_length() {
+        let limited = FlexItemComponent { min_length: Some(90), ..FlexItemComponent::default() };
+        let children = [child(100, limited), child(100, FlexItemComponent::default())];
+        assert_eq!(flex_lengths(&children, 100), vec![90, 10]);
+    }
+
+    #[test]
+    fn limits_apply_without_free_space() {
+        let limited = FlexItemComponent { min_length: Some(40), max_length: Some(100), ..FlexItemComponent::default() };
+        assert_eq!(flex_lengths(&[child(120, limited)], 120), vec![100]);
+        assert_eq!(flex_lengths(&[child(20, limited)], 20), vec![40]);
+    }
+
+    #[test]
+    fn children_wrap_onto_new_lines() {
+        let item = FlexItemComponent::default();
+        let children = [child(60, item), child(60, item), child(60, item), child(200, item), child(10, item)];
+        // the third child does not fit with the gaps, the fourth is too long for any line
+        assert_eq!(wrapping(10).lines(&children, 150), vec![0..2, 2..3, 3..4, 4..5]);
+        assert_eq!(wrapping(0).lines(&children, 385), vec![0..4, 4..5]);
+    }
+
+    #[test]
+    fn lines_are_broken_at_the_limited_lengths() {
+        let limited = FlexItemComponent { max_length: Some(50), ..FlexItemComponent::default() };
+        let children = [child(100, limited), child(100, limited)];
+        assert_eq!(wrapping(0).lines(&children, 100), vec![0..2]);
+    }
+
+    #[test]
+    fn free_space_is_justified() {
+        assert_eq!(justify(JustifyContent::Start, 100, 3), (0, 0));
+        assert_eq!(justify(JustifyContent::End, 100, 3), (100, 0));
+        assert_eq!(justify(JustifyContent::Center, 100, 3), (50, 0));
+        assert_eq!(justify(JustifyContent::SpaceBetween, 100, 3), (0, 50));
+        assert_eq!(justify(JustifyContent::SpaceAround, 100, 4), (12, 25));
+        assert_eq!(justify(JustifyContent::SpaceEvenly, 100, 3), (25, 25));
+    }
+
+    #[test]
+    fn single_child_is_justified() {
+        assert_eq!(justify(JustifyContent::End, 100, 1), (100, 0));
+        assert_eq!(justify(JustifyContent::Center, 100, 1), (50, 0));
+        assert_eq!(justify(JustifyContent::SpaceBetween, 100, 1), (0, 0));
+        assert_eq!(justify(JustifyContent::SpaceAround, 100, 1), (50, 100));
+        assert_eq!(justify(JustifyContent::SpaceEvenly, 100, 1), (50, 50));
+    }
+
+    #[test]
+    fn overflowing_lines_start_at_the_beginning() {
+        // the line has no free space left, whatever the justification is
+        for justify_content in [JustifyContent::Start, JustifyContent::End, JustifyContent::Center, JustifyContent::SpaceBetween, JustifyContent::SpaceAround, JustifyContent::SpaceEvenly].iter() {
+            assert_eq!(justify(*justify_content, 0, 3), (0, 0));
+        }
+    }
+}
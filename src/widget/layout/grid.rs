@@ -0,0 +1,484 @@
+use specs::{Builder, Component, Entity, VecStorage, World, WorldExt};
+use crate::{Alignment, Constraints, DesiredSize, EntityTree, Layout, LayoutComponent, MarginComponent, PaddingComponent, PositionComponent, RelativePositionComponent, Thickness, measure_widget, place_widget};
+
+/// The length of a row or a column of a Grid.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GridLength {
+    /// A fixed length in pixels.
+    Fixed(u32),
+    /// The length of the largest child in the row or column.
+    Auto,
+    /// A share of the space left by the other rows or columns, proportional to the weight.
+    Star(f32),
+}
+
+impl Default for GridLength {
+    fn default() -> Self {
+        GridLength::Star(1.0)
+    }
+}
+
+/// Attached properties of a child inside a Grid.
+/// Rows and columns are counted from zero, a child outside of the grid is moved into its last row or column.
+#[derive(Copy, Clone, Debug)]
+pub struct GridItemComponent {
+    pub row: usize,
+    pub column: usize,
+    /// The number of rows the child spans, at least one.
+    pub row_span: usize,
+    /// The number of columns the child spans, at least one.
+    pub column_span: usize,
+    /// Placement of the child within the width of its cell.
+    pub horizontal_alignment: Alignment,
+    /// Placement of the child within the height of its cell.
+    pub vertical_alignment: Alignment,
+}
+
+impl GridItemComponent {
+    /// Places a child into a single cell, stretched to fill it.
+    pub fn new(row: usize, column: usize) -> GridItemComponent {
+        GridItemComponent {
+            row,
+            column,
+            row_span: 1,
+            column_span: 1,
+            horizontal_alignment: Alignment::Stretch,
+            vertical_alignment: Alignment::Stretch,
+        }
+    }
+}
+
+impl Default for GridItemComponent {
+    fn default() -> Self {
+        GridItemComponent::new(0, 0)
+    }
+}
+
+impl Component for GridItemComponent {
+    type Storage = VecStorage<Self>;
+}
+
+/// A layout widget that places its children into the cells of rows and columns.
+pub struct Grid {}
+
+impl Grid {
+    pub fn new() -> GridBuilder {
+        GridBuilder::new()
+    }
+}
+
+pub struct GridBuilder {
+    children: Vec<(Entity, GridItemComponent)>,
+    columns: Vec<GridLength>,
+    column_gap: u32,
+    margin: Option<Thickness>,
+    padding: Option<Thickness>,
+    rows: Vec<GridLength>,
+    row_gap: u32,
+}
+
+impl GridBuilder {
+    fn new() -> Self {
+        GridBuilder {
+            children: vec![],
+            columns: vec![],
+            column_gap: 0,
+            margin: None,
+            padding: None,
+            rows: vec![],
+            row_gap: 0,
+        }
+    }
+
+    /// Appends a column. A grid without columns has a single column filling its width.
+    pub fn column(mut self, length: GridLength) -> Self {
+        self.columns.push(length);
+        self
+    }
+
+    /// Appends a row. A grid without rows has a single row filling its height.
+    pub fn row(mut self, length: GridLength) -> Self {
+        self.rows.push(length);
+        self
+    }
+
+    /// Sets the gap between two adjacent columns in pixels.
+    pub fn column_gap(mut self, gap: u32) -> Self {
+        self.column_gap = gap;
+        self
+    }
+
+    /// Sets the gap between two adjacent rows in pixels.
+    pub fn row_gap(mut self, gap: u32) -> Self {
+        self.row_gap = gap;
+        self
+    }
+
+    /// Sets the gap between two adjacent rows and between two adjacent columns.
+    pub fn gap(self, gap: u32) -> Self {
+        self.column_gap(gap).row_gap(gap)
+    }
+
+    /// Places a child into a cell, stretched to fill it.
+    pub fn child(mut self, child: Entity, row: usize, column: usize) -> Self {
+        self.children.push((child, GridItemComponent::new(row, column)));
+        self
+    }
+
+    /// Places a child with custom spans and alignment.
+    pub fn child_with(mut self, child: Entity, item: GridItemComponent) -> Self {
+        self.children.push((child, item));
+        self
+    }
+
+    /// Sets the space around the grid, kept free by the layout of its parent.
+    pub fn margin(mut self, margin: Thickness) -> Self {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Sets the space between the bounds of the grid and its tracks.
+    pub fn padding(mut self, padding: Thickness) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
+    pub fn build(self, world: &mut World) -> Entity {
+        world.register::<GridItemComponent>();
+        world.register::<LayoutComponent>();
+        world.register::<MarginComponent>();
+        world.register::<PaddingComponent>();
+        world.register::<PositionComponent>();
+        world.register::<RelativePositionComponent>();
+
+        let layout = LayoutComponent {
+            constraints: Constraints::default(),
+            object: Box::new(GridLayout {
+                columns: self.columns,
+                column_gap: self.column_gap,
+                rows: self.rows,
+                row_gap: self.row_gap,
+            }),
+        };
+
+        let widget = world.create_entity()
+            .with(layout)
+            .with(PositionComponent::default())
+            .maybe_with(self.margin.map(|margin| MarginComponent { margin }))
+            .maybe_with(self.padding.map(|padding| PaddingComponent { padding }))
+            .build();
+
+        {
+            let mut items = world.write_storage::<GridItemComponent>();
+            for (child, item) in self.children.iter() {
+                items.insert(*child, *item).expect("Cannot attach grid properties to a dead entity!");
+            }
+        }
+
+        let mut tree = world.write_resource::<EntityTree>();
+        tree.add_node(widget);
+        for (child, _) in self.children {
+            tree.add_node(child);
+            tree.append_child(widget, child);
+        }
+
+        widget
+    }
+}
+
+/// Places the children of a widget into the cells of rows and columns.
+///
+/// Fixed rows and columns get their length, auto ones the length of their largest child.
+/// When arranging, star rows and columns share the rest of the space by their weights;
+/// when measuring, they desire the smallest length fitting their children in the same proportions.
+pub struct GridLayout {
+    pub columns: Vec<GridLength>,
+    pub column_gap: u32,
+    pub rows: Vec<GridLength>,
+    pub row_gap: u32,
+}
+
+/// A child resolved to the cells it occupies.
+struct Cell {
+    entity: Entity,
+    item: GridItemComponent,
+    columns: (usize, usize),
+    rows: (usize, usize),
+}
+
+impl GridLayout {
+    fn definitions(definitions: &[GridLength]) -> Vec<GridLength> {
+        if definitions.is_empty() {
+            vec![GridLength::default()]
+        } else {
+            definitions.to_vec()
+        }
+    }
+
+    fn cells(&self, widget: Entity, world: &World) -> Vec<Cell> {
+        let tree = world.read_resource::<EntityTree>();
+        let items = world.read_storage::<GridItemComponent>();
+        let children: Vec<Entity> = match tree.try_children_of(widget) {
+            Some(children) => children.filter_map(|node| tree.entitiy_of(node)).collect(),
+            None => vec![],
+        };
+        let (columns, rows) = (self.columns.len().max(1), self.rows.len().max(1));
+
+        children.into_iter()
+            .map(|entity| {
+                let item = items.get(entity).copied().unwrap_or_default();
+                Cell {
+                    entity,
+                    item,
+                    columns: span(item.column, item.column_span, columns),
+                    rows: span(item.row, item.row_span, rows),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the offsets of the tracks from the start of the grid.
+    fn offsets(lengths: &[u32], gap: u32) -> Vec<u32> {
+        let mut offset = 0;
+        lengths.iter().map(|length| {
+            let start = offset;
+            offset += length + gap;
+            start
+        }).collect()
+    }
+}
+
+impl Layout for GridLayout {
+    fn arrange(&self, widget: Entity, desired_size: &DesiredSize, world: &World) {
+        let cells = self.cells(widget, world);
+        let available = Constraints {
+            min_width: 0,
+            max_width: desired_size.width,
+            min_height: 0,
+            max_height: desired_size.height,
+        };
+        let desired: Vec<DesiredSize> = cells.iter().map(|cell| measure_widget(cell.entity, &available, world)).collect();
+
+        let widths = track_lengths(
+            &GridLayout::definitions(&self.columns),
+            self.column_gap,
+            cells.iter().zip(desired.iter()).map(|(cell, size)| (cell.columns, size.width)),
+            Some(desired_size.width),
+        );
+        let heights = track_lengths(
+            &GridLayout::definitions(&self.rows),
+            self.row_gap,
+            cells.iter().zip(desired.iter()).map(|(cell, size)| (cell.rows, size.height)),
+            Some(desired_size.height),
+        );
+        let (lefts, tops) = (GridLayout::offsets(&widths, self.column_gap), GridLayout::offsets(&heights, self.row_gap));
+
+        for cell in cells.iter() {
+            let (first_column, last_column) = cell.columns;
+            let (first_row, last_row) = cell.rows;
+            let cell_width = lefts[last_column] + widths[last_column] - lefts[first_column];
+            let cell_height = tops[last_row] + heights[last_row] - tops[first_row];
+
+            // the child is measured again, as its content may depend on the width it gets
+            let size = measure_widget(cell.entity, &Constraints {
+                min_width: 0,
+                max_width: cell_width,
+                min_height: 0,
+                max_height: cell_height,
+            }, world);
+            let (x_offset, width) = cell.item.horizontal_alignment.align(size.width, cell_width);
+            let (y_offset, height) = cell.item.vertical_alignment.align(size.height, cell_height);
+            let (x, y) = (lefts[first_column] + x_offset, tops[first_row] + y_offset);
+
+            place_widget(cell.entity, x, y, &DesiredSize { width, height }, world);
+        }
+    }
+
+    fn measure(&self, widget: Entity, constraints: &Constraints, world: &World) -> DesiredSize {
+        let cells = self.cells(widget, world);
+        let available = Constraints {
+            min_width: 0,
+            max_width: constraints.max_width,
+            min_height: 0,
+            max_height: constraints.max_height,
+        };
+        let desired: Vec<DesiredSize> = cells.iter().map(|cell| measure_widget(cell.entity, &available, world)).collect();
+
+        let widths = track_lengths(
+            &GridLayout::definitions(&self.columns),
+            self.column_gap,
+            cells.iter().zip(desired.iter()).map(|(cell, size)| (cell.columns, size.width)),
+            None,
+        );
+        let heights = track_lengths(
+            &GridLayout::definitions(&self.rows),
+            self.row_gap,
+            cells.iter().zip(desired.iter()).map(|(cell, size)| (cell.rows, size.height)),
+            None,
+        );
+        let width = widths.iter().sum::<u32>() + total_gap(self.column_gap, widths.len());
+        let height = heights.iter().sum::<u32>() + total_gap(self.row_gap, heights.len());
+
+        DesiredSize {
+            width: width.max(constraints.min_width).min(constraints.max_width),
+            height: height.max(constraints.min_height).min(constraints.max_height)
+        }
+    }
+}
+
+/// Returns the first and the last track covered by a span, moved into the grid when it goes beyond its end.
+fn span(start: usize, span: usize, count: usize) -> (usize, usize) {
+    let first = start.min(count - 1);
+    let last = (first + span.max(1) - 1).min(count - 1);
+    (first, last)
+}
+
+fn total_gap(gap: u32, count: usize) -> u32 {
+    gap * (count.saturating_sub(1) as u32)
+}
+
+/// Computes the lengths of the rows or the columns of a grid along one axis.
+///
+/// `children` gives the first and the last track of each child, and the length the child desires.
+/// `available` is the length of the grid when it is arranged, or None when it is measured.
+fn track_lengths<I>(definitions: &[GridLength], gap: u32, children: I, available: Option<u32>) -> Vec<u32>
+where
+    I: Iterator<Item = ((usize, usize), u32)>,
+{
+    // star tracks are sized to their content only while measuring
+    let sized_to_content = |length: &GridLength| match length {
+        GridLength::Fixed(_) => false,
+        GridLength::Auto => true,
+        GridLength::Star(_) => available.is_none(),
+    };
+    let mut lengths: Vec<u32> = definitions.iter().map(|length| match length {
+        GridLength::Fixed(pixels) => *pixels,
+        _ => 0,
+    }).collect();
+
+    // children spanning more tracks are fitted after the single track ones,
+    // growing the tracks sized to their content equally
+    let mut children: Vec<((usize, usize), u32)> = children.collect();
+    children.sort_by_key(|((first, last), _)| last - first);
+    for ((first, last), desired) in children {
+        let flexible: Vec<usize> = (first..=last).filter(|track| sized_to_content(&definitions[*track])).collect();
+        if flexible.is_empty() {
+            continue;
+        }
+        let spanned = lengths[first..=last].iter().sum::<u32>() + total_gap(gap, last - first + 1);
+        let mut missing = desired.saturating_sub(spanned);
+        let mut left = flexible.len() as u32;
+        for track in flexible {
+            let share = missing / left;
+            lengths[track] += share;
+            missing -= share;
+            left -= 1;
+        }
+    }
+
+    let weight = |length: &GridLength| match length {
+        GridLength::Star(weight) if *weight > 0.0 => *weight,
+        _ => 0.0,
+    };
+    let stars: Vec<usize> = (0..definitions.len()).filter(|track| matches!(definitions[*track], GridLength::Star(_))).collect();
+    match available {
+        // the stars keep their proportions, so the one needing the most space per weight decides the length of all
+        None => {
+            let unit = stars.iter()
+                .filter(|track| weight(&definitions[**track]) > 0.0)
+                .map(|track| lengths[*track] as f32 / weight(&definitions[*track]))
+                .fold(0.0, f32::max);
+            for track in stars {
+                lengths[track] = (unit * weight(&definitions[track])).ceil() as u32;
+            }
+        }
+        Some(available) => {
+            let used = lengths.iter().sum::<u32>() + total_gap(gap, lengths.len());
+            let mut free = available.saturating_sub(used);
+            let mut total_weight: f32 = stars.iter().map(|track| weight(&definitions[*track])).sum();
+            // the last star takes the remainder of the division
+            for track in stars {
+                let track_weight = weight(&definitions[track]);
+                let share = if total_weight > 0.0 {
+                    (free as f32 * track_weight / total_weight).round() as u32
+                } else {
+                    0
+                };
+                let share = share.min(free);
+                lengths[track] = share;
+                free -= share;
+                total_weight -= track_weight;
+            }
+        }
+    }
+    lengths
+}
+
+#[cfg(test)]
+mod test {
+    use super::{span, track_lengths};
+    use crate::GridLength::{Auto, Fixed, Star};
+
+    #[test]
+    fn fixed_tracks_keep_their_length() {
+        let lengths = track_lengths(&[Fixed(30), Fixed(50)], 0, vec![((0, 0), 100)].into_iter(), Some(200));
+        assert_eq!(lengths, vec![30, 50]);
+    }
+
+    #[test]
+    fn auto_tracks_fit_their_largest_child() {
+        let children = vec![((0, 0), 40), ((0, 0), 60), ((1, 1), 10)];
+        let lengths = track_lengths(&[Auto, Auto, Auto], 0, children.into_iter(), Some(200));
+        assert_eq!(lengths, vec![60, 10, 0]);
+    }
+
+    #[test]
+    fn star_tracks_share_the_free_space_by_weight() {
+        let lengths = track_lengths(&[Fixed(40), Star(1.0), Star(3.0)], 0, std::iter::empty(), Some(240));
+        assert_eq!(lengths, vec![40, 50, 150]);
+    }
+
+    #[test]
+    fn measured_star_tracks_keep_their_proportions() {
+        // the second star needs 30 pixels per weight, so the first gets 30 too
+        let children = vec![((0, 0), 20), ((1, 1), 60)];
+        let lengths = track_lengths(&[Star(1.0), Star(2.0)], 0, children.into_iter(), None);
+        assert_eq!(lengths, vec![30, 60]);
+    }
+
+    #[test]
+    fn star_tracks_get_nothing_without_free_space() {
+        let lengths = track_lengths(&[Fixed(80), Star(1.0), Fixed(40), Star(2.0)], 10, std::iter::empty(), Some(100));
+        assert_eq!(lengths, vec![80, 0, 40, 0]);
+        let lengths = track_lengths(&[Star(1.0)], 0, vec![((0, 0), 50)].into_iter(), Some(0));
+        assert_eq!(lengths, vec![0]);
+    }
+
+    #[test]
+    fn gaps_are_left_out_of_the_star_tracks() {
+        let lengths = track_lengths(&[Star(1.0), Star(1.0), Star(1.0)], 15, std::iter::empty(), Some(150));
+        assert_eq!(lengths, vec![40, 40, 40]);
+    }
+
+    #[test]
+    fn spans_grow_the_auto_tracks_they_cross() {
+        // the single cell child is fitted first, the spanning one shares what is still missing
+        let children = vec![((0, 2), 100), ((1, 1), 50)];
+        let lengths = track_lengths(&[Fixed(20), Auto, Auto], 5, children.into_iter(), Some(300));
+        assert_eq!(lengths, vec![20, 60, 10]);
+    }
+
+    #[test]
+    fn spans_covering_the_gaps_only_need_the_rest() {
+        let children = vec![((0, 1), 30)];
+        let lengths = track_lengths(&[Auto, Auto], 10, children.into_iter(), Some(300));
+        assert_eq!(lengths, vec![10, 10]);
+    }
+
+    #[test]
+    fn spans_are_moved_into_the_grid() {
+        assert_eq!(span(1, 2, 4), (1, 2));
+        assert_eq!(span(3, 3, 4), (3, 3));
+        assert_eq!(span(7, 0, 4), (3, 3));
+    }
+}
@@ -1,3 +1,9 @@
+mod box_layout;
 mod center;
+mod flex;
+mod grid;
 
-pub use self::center::*;
\ No newline at end of file
+pub use self::box_layout::*;
+pub use self::center::*;
+pub use self::flex::*;
+pub use self::grid::*;
\ No newline at end of file
@@ -1,7 +1,13 @@
+mod button;
+mod image;
 mod label;
 mod layout;
+mod text_box;
 mod window;
 
+pub use self::button::*;
+pub use self::image::*;
 pub use self::label::*;
 pub use self::layout::*;
+pub use self::text_box::*;
 pub use self::window::*;
@@ -0,0 +1,690 @@
+use std::time::{Duration, Instant};
+
+use crate::{Border, EventHandlerComponent, EventResult, FocusComponent, FocusManager, FontRegistry, FontStyle, FontWeight, InputState, MarginComponent, MouseButton, PaddingComponent, PositionComponent, RenderComponent, Scheduler, Shape, SizeComponent, Thickness, WidgetEvent, caret_offsets, component::TextComponent, grapheme_boundaries, text_area};
+use orbclient::Color;
+use specs::{Builder, Component, Entity, FlaggedStorage, VecStorage, World, WorldExt};
+
+const TEXT_BOX_DEFAULT_WIDTH: usize = 150;
+const TEXT_BOX_DEFAULT_HEIGHT: usize = 24;
+const CARET_BLINK_MILLIS: u128 = 500;
+
+/// Holds the editing state of a TextBox widget.
+/// The caret and the selection anchor are character (not byte) indices into the text of the TextComponent.
+#[derive(Copy, Clone, Debug)]
+pub struct TextBoxComponent {
+    /// The position where the next character is inserted.
+    pub caret: usize,
+    /// The other end of the selection. The selection is empty when it equals to the caret.
+    pub anchor: usize,
+    pub selection_color: Color,
+    /// When the caret started blinking, None until the next frame after the caret is moved.
+    blink_start: Option<Instant>,
+    scroll: f32,
+}
+
+impl TextBoxComponent {
+    pub fn new() -> TextBoxComponent {
+        TextBoxComponent {
+            caret: 0,
+            anchor: 0,
+            selection_color: Color::rgba(51, 153, 255, 128),
+            blink_start: None,
+            scroll: 0.0,
+        }
+    }
+
+    /// Returns the selected range of characters as (start, end), the end is exclusive.
+    pub fn selection(&self) -> (usize, usize) {
+        (self.caret.min(self.anchor), self.caret.max(self.anchor))
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.caret != self.anchor
+    }
+
+    /// Returns how far the text is scrolled to the left, in pixels.
+    pub fn scroll(&self) -> f32 {
+        self.scroll
+    }
+
+    /// Scrolls the text horizontally, so the caret at the given offset stays within the visible width,
+    /// without scrolling the end of the text further to the left than needed.
+    pub fn scroll_to_caret(&mut self, caret_offset: f32, text_width: f32, visible_width: f32) {
+        // the caret is one pixel wide
+        let mut scroll = self.scroll;
+        if caret_offset < scroll {
+            scroll = caret_offset;
+        } else if caret_offset + 1.0 > scroll + visible_width {
+            scroll = caret_offset + 1.0 - visible_width;
+        }
+        self.scroll = scroll.min(text_width + 1.0 - visible_width).max(0.0);
+    }
+
+    /// Tells whether the caret is in the visible half of its blinking period at the given time.
+    /// A caret which has just been moved is visible.
+    pub fn caret_visible(&self, now: Instant) -> bool {
+        match self.blink_start {
+            Some(blink_start) => now.saturating_duration_since(blink_start).as_millis() % (2 * CARET_BLINK_MILLIS) < CARET_BLINK_MILLIS,
+            None => true,
+        }
+    }
+
+    /// Returns when the caret appears or disappears next after the given time.
+    pub fn next_blink(&self, now: Instant) -> Instant {
+        let blink_start = self.blink_start.unwrap_or(now);
+        let periods = now.saturating_duration_since(blink_start).as_millis() / CARET_BLINK_MILLIS + 1;
+        blink_start + Duration::from_millis((periods * CARET_BLINK_MILLIS) as u64)
+    }
+
+    /// Moves the caret. The selection is extended when `select` is true, collapsed otherwise.
+    pub fn move_caret(&mut self, caret: usize, select: bool) {
+        self.caret = caret;
+        if !select {
+            self.anchor = caret;
+        }
+        self.restart_blinking();
+    }
+
+    /// Inserts a character in place of the selection.
+    pub fn insert(&mut self, text: &mut String, character: char) {
+        self.delete_selection(text);
+        text.insert(byte_index(text, self.caret), character);
+        self.move_caret(self.caret + 1, false);
+    }
+
+    /// Deletes the selection, or the grapheme before the caret, e.g. a letter with its combining marks.
+    pub fn backspace(&mut self, text: &mut String) {
+        if self.has_selection() {
+            self.delete_selection(text);
+        } else if self.caret > 0 {
+            let start = previous_grapheme(text, self.caret);
+            text.replace_range(byte_index(text, start)..byte_index(text, self.caret), "");
+            self.move_caret(start, false);
+        }
+    }
+
+    /// Deletes the selection, or the grapheme after the caret.
+    pub fn delete(&mut self, text: &mut String) {
+        if self.has_selection() {
+            self.delete_selection(text);
+        } else if self.caret < text.chars().count() {
+            let end = next_grapheme(text, self.caret);
+            text.replace_range(byte_index(text, self.caret)..byte_index(text, end), "");
+            self.restart_blinking();
+        }
+    }
+
+    pub fn delete_selection(&mut self, text: &mut String) {
+        let (start, end) = self.selection();
+        if start != end {
+            text.replace_range(byte_index(text, start)..byte_index(text, end), "");
+            self.move_caret(start, false);
+        }
+    }
+
+    /// Shows the caret, it starts blinking again from the next frame.
+    fn restart_blinking(&mut self) {
+        self.blink_start = None;
+    }
+}
+
+/// Wakes the shell when the caret of the focused TextBox blinks.
+/// A caret moved during the frame starts blinking at the time of the frame.
+pub(crate) fn schedule_caret_blink(world: &World) {
+    let focused = match world.read_resource::<FocusManager>().focused() {
+        Some(focused) => focused,
+        None => return,
+    };
+    let mut scheduler = world.write_resource::<Scheduler>();
+    let now = scheduler.now();
+    let mut text_boxes = world.write_storage::<TextBoxComponent>();
+    // the storage is only borrowed mutably when the caret has been moved, as it flags the component changed
+    if matches!(text_boxes.get(focused), Some(TextBoxComponent { blink_start: None, .. })) {
+        if let Some(text_box) = text_boxes.get_mut(focused) {
+            text_box.blink_start = Some(now);
+        }
+    }
+    if let Some(text_box) = text_boxes.get(focused) {
+        scheduler.wake_at(text_box.next_blink(now));
+    }
+}
+
+impl Default for TextBoxComponent {
+    fn default() -> Self {
+        TextBoxComponent::new()
+    }
+}
+
+impl Component for TextBoxComponent {
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
+}
+
+/// A widget for editing a single line of text.
+/// The text is cut off at the padding and scrolled horizontally to keep the caret visible.
+pub struct TextBox {}
+
+impl TextBox {
+    pub fn new() -> TextBoxBuilder {
+        TextBoxBuilder::new()
+    }
+}
+
+pub struct TextBoxBuilder {
+    background: Option<Color>,
+    border: Option<Border>,
+    font_family: String,
+    font_size: usize,
+    font_style: FontStyle,
+    font_weight: FontWeight,
+    margin: Option<Thickness>,
+    padding: Option<Thickness>,
+    position: PositionComponent,
+    selection_color: Color,
+    size: SizeComponent,
+    tab_index: i32,
+    text: String,
+    text_color: Color,
+}
+
+impl TextBoxBuilder {
+    pub fn new() -> TextBoxBuilder {
+        TextBoxBuilder {
+            background: Some(Color::rgb(255, 255, 255)),
+            border: Some(Border::default()),
+            font_family: "Roboto-Medium".to_string(),
+            font_size: 12,
+            font_style: FontStyle::default(),
+            font_weight: FontWeight::default(),
+            margin: None,
+            padding: None,
+            position: PositionComponent::default(),
+            selection_color: Color::rgba(51, 153, 255, 128),
+            size: SizeComponent {
+                width: TEXT_BOX_DEFAULT_WIDTH,
+                height: TEXT_BOX_DEFAULT_HEIGHT,
+            },
+            tab_index: 0,
+            text: String::new(),
+            text_color: Color::rgba(0, 0, 0, 255),
+        }
+    }
+
+    pub fn background(mut self, bg_color: Color) -> TextBoxBuilder {
+        self.background = Some(bg_color);
+        self
+    }
+
+    pub fn border(mut self, border: Border) -> TextBoxBuilder {
+        self.border = Some(border);
+        self
+    }
+
+    pub fn font_family(mut self, font: String) -> TextBoxBuilder {
+        self.font_family = font;
+        self
+    }
+
+    pub fn font_size(mut self, font_size: usize) -> TextBoxBuilder {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn font_style(mut self, font_style: FontStyle) -> TextBoxBuilder {
+        self.font_style = font_style;
+        self
+    }
+
+    /// Sets the weight of the font, e.g. FontWeight::BOLD. Fonts registered by name ignore it.
+    pub fn font_weight(mut self, font_weight: FontWeight) -> TextBoxBuilder {
+        self.font_weight = font_weight;
+        self
+    }
+
+    /// Sets the space around the box, kept free by the layout of its parent.
+    pub fn margin(mut self, margin: Thickness) -> TextBoxBuilder {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Sets the space between the bounds of the box and the edited text.
+    pub fn padding(mut self, padding: Thickness) -> TextBoxBuilder {
+        self.padding = Some(padding);
+        self
+    }
+
+    pub fn position(mut self, x: u32, y: u32) -> TextBoxBuilder {
+        self.position = PositionComponent { x, y };
+        self
+    }
+
+    /// Color of the highlight behind the selected text.
+    pub fn selection_color(mut self, color: Color) -> TextBoxBuilder {
+        self.selection_color = color;
+        self
+    }
+
+    pub fn size(mut self, width: usize, height: usize) -> TextBoxBuilder {
+        self.size = SizeComponent { width, height };
+        self
+    }
+
+    /// Sets the position of the box in the Tab traversal order. See FocusComponent for details.
+    pub fn tab_index(mut self, tab_index: i32) -> TextBoxBuilder {
+        self.tab_index = tab_index;
+        self
+    }
+
+    /// The initial text of the box.
+    pub fn text(mut self, text: String) -> Self {
+        self.text = text;
+        self
+    }
+
+    pub fn text_color(mut self, color: Color) -> TextBoxBuilder {
+        self.text_color = color;
+        self
+    }
+
+    pub fn build(self, world: &mut World) -> Entity {
+        world.register::<EventHandlerComponent>();
+        world.register::<FocusComponent>();
+        world.register::<MarginComponent>();
+        world.register::<PaddingComponent>();
+        world.register::<PositionComponent>();
+        world.register::<RenderComponent>();
+        world.register::<SizeComponent>();
+        world.register::<TextBoxComponent>();
+        world.register::<TextComponent>();
+
+        let render_component = RenderComponent {
+            background: self.background,
+            border: self.border,
+            shape: Shape::Rectangle,
+        };
+        let mut text_box = TextBoxComponent::new();
+        text_box.selection_color = self.selection_color;
+        text_box.move_caret(self.text.chars().count(), false);
+        let text = TextComponent {
+            font_family: self.font_family,
+            font_size: self.font_size,
+            font_style: self.font_style,
+            font_weight: self.font_weight,
+            text: self.text,
+            text_color: self.text_color,
+            // the box edits a single line, the caret and the selection assume it starts at the top left corner inside the padding
+            ..TextComponent::default()
+        };
+
+        world
+            .create_entity()
+            .with(self.position)
+            .with(render_component)
+            .with(self.size)
+            .with(text)
+            .with(text_box)
+            .with(FocusComponent { focusable: true, tab_index: self.tab_index })
+            .with(EventHandlerComponent::new().on_bubble(handle_event))
+            .maybe_with(self.margin.map(|margin| MarginComponent { margin }))
+            .maybe_with(self.padding.map(|padding| PaddingComponent { padding }))
+            .build()
+    }
+}
+
+impl Default for TextBoxBuilder {
+    fn default() -> Self {
+        TextBoxBuilder::new()
+    }
+}
+
+fn handle_event(widget: Entity, event: &WidgetEvent, world: &World) -> EventResult {
+    match event {
+        WidgetEvent::MouseDown { x, button: MouseButton::Left, .. } => {
+            // the shell has already moved the focus to the box
+            let select = world.read_resource::<InputState>().shift;
+            if let Some(caret) = caret_at(widget, *x, world) {
+                edit(widget, world, |text_box, _| text_box.move_caret(caret, select));
+            }
+        }
+        WidgetEvent::MouseMove { x, .. } => {
+            // dragging with the left button pressed extends the selection
+            let focused = world.read_resource::<FocusManager>().focused();
+            if focused != Some(widget) || !world.read_resource::<InputState>().left_button {
+                return EventResult::Ignored;
+            }
+            if let Some(caret) = caret_at(widget, *x, world) {
+                edit(widget, world, |text_box, _| text_box.move_caret(caret, true));
+            }
+        }
+        WidgetEvent::TextInput { character } => {
+            if character.is_control() {
+                return EventResult::Ignored;
+            }
+            edit(widget, world, |text_box, text| text_box.insert(text, *character));
+        }
+        WidgetEvent::KeyDown { scancode, .. } => {
+            let input = *world.read_resource::<InputState>();
+            let select = input.shift;
+            let word_wise = input.ctrl;
+            match *scancode {
+                orbclient::K_BKSP => edit(widget, world, |text_box, text| text_box.backspace(text)),
+                orbclient::K_DEL => edit(widget, world, |text_box, text| text_box.delete(text)),
+                orbclient::K_HOME => edit(widget, world, |text_box, _| text_box.move_caret(0, select)),
+                orbclient::K_END => edit(widget, world, |text_box, text| text_box.move_caret(text.chars().count(), select)),
+                orbclient::K_LEFT => edit(widget, world, |text_box, text| {
+                    let caret = if word_wise {
+                        previous_word_start(text, text_box.caret)
+                    } else if text_box.has_selection() && !select {
+                        text_box.selection().0
+                    } else {
+                        previous_grapheme(text, text_box.caret)
+                    };
+                    text_box.move_caret(caret, select);
+                }),
+                orbclient::K_RIGHT => edit(widget, world, |text_box, text| {
+                    let caret = if word_wise {
+                        next_word_end(text, text_box.caret)
+                    } else if text_box.has_selection() && !select {
+                        text_box.selection().1
+                    } else {
+                        next_grapheme(text, text_box.caret)
+                    };
+                    text_box.move_caret(caret, select);
+                }),
+                orbclient::K_A if input.ctrl => edit(widget, world, |text_box, text| {
+                    text_box.move_caret(0, false);
+                    text_box.move_caret(text.chars().count(), true);
+                }),
+                _ => return EventResult::Ignored,
+            }
+        }
+        _ => return EventResult::Ignored,
+    }
+    EventResult::Handled
+}
+
+/// Runs an editing operation on the TextBoxComponent and the text of the widget,
+/// then scrolls the text to the caret.
+fn edit<F: FnOnce(&mut TextBoxComponent, &mut String)>(widget: Entity, world: &World, operation: F) {
+    let positions = world.read_storage::<PositionComponent>();
+    let sizes = world.read_storage::<SizeComponent>();
+    let paddings = world.read_storage::<PaddingComponent>();
+    let mut text_boxes = world.write_storage::<TextBoxComponent>();
+    let mut texts = world.write_storage::<TextComponent>();
+    let mut fonts = world.write_resource::<FontRegistry>();
+    if let (Some(text_box), Some(text)) = (text_boxes.get_mut(widget), texts.get_mut(widget)) {
+        operation(text_box, &mut text.text);
+        let area = positions.get(widget).map(|position| text_area(position, sizes.get(widget), paddings.get(widget)));
+        if let Some((_, _, Some(width), _)) = area {
+            let offsets = caret_offsets(&mut fonts, text);
+            let text_width = offsets.iter().copied().fold(0.0, f32::max);
+            text_box.scroll_to_caret(offsets[text_box.caret.min(offsets.len() - 1)], text_width, width);
+        }
+    }
+}
+
+/// Returns the caret position closest to the given horizontal window coordinate.
+/// The offsets of the caret positions follow the visual order, so the right-to-left runs are hit too.
+fn caret_at(widget: Entity, x: i32, world: &World) -> Option<usize> {
+    let positions = world.read_storage::<PositionComponent>();
+    let texts = world.read_storage::<TextComponent>();
+    let text_boxes = world.read_storage::<TextBoxComponent>();
+    let paddings = world.read_storage::<PaddingComponent>();
+    let mut fonts = world.write_resource::<FontRegistry>();
+
+    let position = positions.get(widget)?;
+    let text = texts.get(widget)?;
+    let scroll = text_boxes.get(widget).map_or(0.0, TextBoxComponent::scroll);
+    let (left, _, _, _) = text_area(position, None, paddings.get(widget));
+    let x = x as f32 - left + scroll;
+
+    let boundaries = grapheme_boundaries(&text.text);
+    caret_offsets(&mut fonts, text)
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| boundaries[*index])
+        .min_by(|(_, a), (_, b)| (*a - x).abs().total_cmp(&(*b - x).abs()))
+        .map(|(index, _)| index)
+}
+
+/// Returns the char index of the grapheme boundary before the caret.
+fn previous_grapheme(text: &str, caret: usize) -> usize {
+    let boundaries = grapheme_boundaries(text);
+    let caret = caret.min(boundaries.len() - 1);
+    (0..caret).rev().find(|index| boundaries[*index]).unwrap_or(0)
+}
+
+/// Returns the char index of the grapheme boundary after the caret.
+fn next_grapheme(text: &str, caret: usize) -> usize {
+    let boundaries = grapheme_boundaries(text);
+    let end = boundaries.len() - 1;
+    (caret + 1..end).find(|index| boundaries[*index]).unwrap_or(end)
+}
+
+/// Returns the char index of the start of the word before the caret. The combining marks belong to the words.
+fn previous_word_start(text: &str, caret: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut index = caret.min(chars.len());
+    let boundaries = grapheme_boundaries(text);
+    let in_word = |index: usize| chars[index].is_alphanumeric() || !boundaries[index];
+    while index > 0 && !in_word(index - 1) {
+        index -= 1;
+    }
+    while index > 0 && in_word(index - 1) {
+        index -= 1;
+    }
+    index
+}
+
+/// Returns the char index of the end of the word after the caret. The combining marks belong to the words.
+fn next_word_end(text: &str, caret: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut index = caret.min(chars.len());
+    let boundaries = grapheme_boundaries(text);
+    let in_word = |index: usize| chars[index].is_alphanumeric() || !boundaries[index];
+    while index < chars.len() && !in_word(index) {
+        index += 1;
+    }
+    while index < chars.len() && in_word(index) {
+        index += 1;
+    }
+    index
+}
+
+/// Converts a char index to a byte index of the text.
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices().nth(char_index).map_or(text.len(), |(index, _)| index)
+}
+
+#[cfg(test)]
+mod test {
+    use specs::{Entity, World, WorldExt};
+    use crate::{FontRegistry, HeadlessShell, TextBox, TextBoxComponent, TextComponent, caret_offsets};
+    use super::{caret_at, edit, next_grapheme, next_word_end, previous_grapheme, previous_word_start};
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert(FontRegistry::new());
+        world
+    }
+
+    #[test]
+    fn clicks_hit_the_caret_positions_of_right_to_left_runs() {
+        let mut world = world();
+        let text = "abc \u{5D0}\u{5D1}\u{5D2}";
+        let widget = TextBox::new().text(text.to_string()).font_size(20).build(&mut world);
+        let offsets = caret_offsets(&mut world.write_resource::<FontRegistry>(), world.read_storage::<TextComponent>().get(widget).unwrap());
+
+        assert_eq!(caret_at(widget, 0, &world), Some(0));
+        for (index, offset) in offsets.iter().enumerate() {
+            assert_eq!(caret_at(widget, offset.round() as i32, &world), Some(index));
+        }
+        // the rightmost position is before the first hebrew letter
+        assert_eq!(caret_at(widget, 1000, &world), Some(4));
+    }
+
+    #[test]
+    fn clicks_do_not_put_the_caret_within_a_grapheme() {
+        let mut world = world();
+        let widget = TextBox::new().text("xe\u{301}y".to_string()).font_size(20).build(&mut world);
+        let offsets = caret_offsets(&mut world.write_resource::<FontRegistry>(), world.read_storage::<TextComponent>().get(widget).unwrap());
+        assert_eq!(caret_at(widget, offsets[2].round() as i32, &world), Some(1));
+    }
+
+    #[test]
+    fn the_caret_moves_and_deletes_by_graphemes() {
+        let text = "xe\u{301}y";
+        assert_eq!(next_grapheme(text, 1), 3);
+        assert_eq!(previous_grapheme(text, 3), 1);
+        assert_eq!(next_grapheme(text, 4), 4);
+        assert_eq!(previous_grapheme(text, 0), 0);
+
+        let mut text = text.to_string();
+        let mut text_box = TextBoxComponent::new();
+        text_box.move_caret(3, false);
+        text_box.backspace(&mut text);
+        assert_eq!((text.as_str(), text_box.caret), ("xy", 1));
+
+        let mut text = "xe\u{301}y".to_string();
+        text_box.move_caret(1, false);
+        text_box.delete(&mut text);
+        assert_eq!((text.as_str(), text_box.caret), ("xy", 1));
+    }
+
+    #[test]
+    fn backspace_and_delete_remove_whole_flags() {
+        let flags = "\u{1F1ED}\u{1F1FA}\u{1F1EB}\u{1F1EE}";
+        let mut text = flags.to_string();
+        let mut text_box = TextBoxComponent::new();
+        text_box.move_caret(4, false);
+        text_box.backspace(&mut text);
+        assert_eq!((text.as_str(), text_box.caret), ("\u{1F1ED}\u{1F1FA}", 2));
+
+        let mut text = flags.to_string();
+        text_box.move_caret(0, false);
+        text_box.delete(&mut text);
+        assert_eq!((text.as_str(), text_box.caret), ("\u{1F1EB}\u{1F1EE}", 0));
+    }
+
+    #[test]
+    fn previous_word_start_skips_the_separators_then_the_word() {
+        let text = "one two, three";
+        assert_eq!(previous_word_start(text, 14), 9);
+        assert_eq!(previous_word_start(text, 11), 9);
+        assert_eq!(previous_word_start(text, 9), 4);
+        assert_eq!(previous_word_start(text, 3), 0);
+        assert_eq!(previous_word_start(text, 0), 0);
+        assert_eq!(previous_word_start(text, 100), 9);
+        assert_eq!(previous_word_start("caf\u{65}\u{301} au", 6), 0);
+    }
+
+    #[test]
+    fn next_word_end_skips_the_separators_then_the_word() {
+        let text = "one two, three";
+        assert_eq!(next_word_end(text, 0), 3);
+        assert_eq!(next_word_end(text, 3), 7);
+        assert_eq!(next_word_end(text, 5), 7);
+        assert_eq!(next_word_end(text, 7), 14);
+        assert_eq!(next_word_end(text, 14), 14);
+        assert_eq!(next_word_end(text, 100), 14);
+        assert_eq!(next_word_end("caf\u{65}\u{301} au", 0), 5);
+    }
+
+    #[test]
+    fn characters_are_inserted_at_the_caret() {
+        let mut text = "ab".to_string();
+        let mut text_box = TextBoxComponent::new();
+        text_box.move_caret(1, false);
+        text_box.insert(&mut text, '\u{151}');
+        text_box.insert(&mut text, 'x');
+        assert_eq!((text.as_str(), text_box.caret, text_box.has_selection()), ("a\u{151}xb", 3, false));
+    }
+
+    #[test]
+    fn insertion_replaces_the_selection() {
+        let mut text = "hello world".to_string();
+        let mut text_box = TextBoxComponent::new();
+        text_box.move_caret(6, false);
+        text_box.move_caret(11, true);
+        assert_eq!(text_box.selection(), (6, 11));
+        text_box.insert(&mut text, '!');
+        assert_eq!((text.as_str(), text_box.caret, text_box.anchor), ("hello !", 7, 7));
+    }
+
+    #[test]
+    fn backspace_and_delete_remove_one_character_or_the_selection() {
+        let mut text = "abcd".to_string();
+        let mut text_box = TextBoxComponent::new();
+        text_box.backspace(&mut text);
+        assert_eq!((text.as_str(), text_box.caret), ("abcd", 0));
+        text_box.delete(&mut text);
+        assert_eq!((text.as_str(), text_box.caret), ("bcd", 0));
+
+        text_box.move_caret(3, false);
+        text_box.delete(&mut text);
+        assert_eq!((text.as_str(), text_box.caret), ("bcd", 3));
+        text_box.backspace(&mut text);
+        assert_eq!((text.as_str(), text_box.caret), ("bc", 2));
+
+        // a selection made backwards is deleted the same way
+        text_box.move_caret(2, false);
+        text_box.move_caret(0, true);
+        text_box.delete(&mut text);
+        assert_eq!((text.as_str(), text_box.caret, text_box.has_selection()), ("", 0, false));
+    }
+
+    #[test]
+    fn scrolling_keeps_the_caret_visible() {
+        let mut text_box = TextBoxComponent::new();
+        text_box.scroll_to_caret(50.0, 200.0, 100.0);
+        assert_eq!(text_box.scroll(), 0.0);
+        text_box.scroll_to_caret(150.0, 200.0, 100.0);
+        assert_eq!(text_box.scroll(), 51.0);
+        // the scroll only changes when the caret leaves the visible part
+        text_box.scroll_to_caret(100.0, 200.0, 100.0);
+        assert_eq!(text_box.scroll(), 51.0);
+        text_box.scroll_to_caret(20.0, 200.0, 100.0);
+        assert_eq!(text_box.scroll(), 20.0);
+        // the end of a shortened text does not stay scrolled out to the left
+        text_box.scroll_to_caret(60.0, 200.0, 100.0);
+        text_box.scroll_to_caret(60.0, 90.0, 100.0);
+        assert_eq!(text_box.scroll(), 0.0);
+    }
+
+    fn text_box_widget(text: &'static str) -> impl Fn(&mut World) -> Vec<Entity> {
+        move |world: &mut World| vec![TextBox::new().text(text.to_string()).position(10, 10).size(60, 24).build(world)]
+    }
+
+    #[test]
+    fn long_text_is_cut_off_at_the_box() {
+        let mut empty = HeadlessShell::with_widgets(200, 50, text_box_widget(""));
+        let mut long = HeadlessShell::with_widgets(200, 50, text_box_widget("a text far too long for the box"));
+        let (empty, long) = (empty.render().clone(), long.render());
+        for y in 0..50 {
+            for x in 0..200 {
+                if !(10..70).contains(&x) {
+                    assert_eq!(empty.pixel(x, y), long.pixel(x, y), "pixel at {}, {}", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn text_is_scrolled_to_the_caret() {
+        let text = "a text far too long for the box";
+        let mut shell = HeadlessShell::with_widgets(200, 50, text_box_widget(text));
+        shell.render();
+        let widget = shell.widgets()[0];
+        let scroll = |shell: &HeadlessShell| shell.world().read_storage::<TextBoxComponent>().get(widget).unwrap().scroll();
+
+        edit(widget, shell.world(), |text_box, text| text_box.move_caret(text.chars().count(), false));
+        let offsets = caret_offsets(&mut shell.world().write_resource::<FontRegistry>(), shell.world().read_storage::<TextComponent>().get(widget).unwrap());
+        let end = offsets[text.chars().count()];
+        assert!(end > 60.0);
+        assert_eq!(scroll(&shell), end + 1.0 - 60.0);
+        // the clicks hit the scrolled text
+        assert_eq!(caret_at(widget, 69, shell.world()), Some(text.chars().count()));
+
+        edit(widget, shell.world(), |text_box, _| text_box.move_caret(0, false));
+        assert_eq!(scroll(&shell), 0.0);
+    }
+}
@@ -232,7 +232,19 @@ impl WindowBuilder {
     }
 
     /// Constructs a Window object from the given properties.
+    ///
+    /// # Panics
+    /// This method panics when the window cannot be created, e.g. there is no display server to connect to.
+    /// For the non-panicking version, use try_build().
     pub fn build(self) -> Window {
+        self.try_build().expect("Error creating window")
+    }
+
+    /// Constructs a Window object from the given properties.
+    ///
+    /// # Errors
+    /// Returns an error when the window cannot be created, e.g. there is no display server to connect to.
+    pub fn try_build(self) -> Result<Window, String> {
         let mut flags: Vec<WindowFlag> = Vec::new();
         if self.borderless {
             flags.push(WindowFlag::Borderless);
@@ -261,21 +273,23 @@ impl WindowBuilder {
             }
         }
 
-        Window {
-            inner: orbclient::Window::new_flags(
-                self.x,
-                self.y,
-                self.width,
-                self.height,
-                &self.title,
-                &flags,
-            )
-            .expect("Error creating window"),
+        let inner = orbclient::Window::new_flags(
+            self.x,
+            self.y,
+            self.width,
+            self.height,
+            &self.title,
+            &flags,
+        )
+        .ok_or_else(|| format!("Cannot create window \"{}\"", self.title))?;
+
+        Ok(Window {
+            inner,
             max_height: max_height,
             max_width: max_width,
             min_height: self.min_height,
             min_width: self.min_width,
             ui: self.ui,
-        }
+        })
     }
 }